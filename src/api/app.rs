@@ -16,20 +16,27 @@ use tower_http::cors::CorsLayer;
 
 use crate::{
     db::repository::{
-        agenda::AgendaRepository, draft::DraftRepository, notification::NotificationRepository,
-        project::ProjectRepository, requirement::RequirementRepository, task::TaskRepository,
+        agenda::AgendaRepository, comment::CommentRepository, draft::DraftRepository, notification::NotificationRepository,
+        outgoing_webhook::OutgoingWebhookRepository, project::ProjectRepository,
+        requirement::RequirementRepository, task::TaskRepository, template::TemplateRepository,
         user::UserRepository,
     },
     usecase::{
         draft_collaboration::DraftCollaborationManager,
-        invitation_token::InvitationTokenRepository, util::auth_backend::AuthBackend,
+        invitation_token::InvitationTokenRepository,
+        mailer::{run_mailer, Mailer, MailerConfig},
+        outgoing_webhook::WebhookDispatcher,
+        reminder::{run_reminder_scheduler, ReminderConfig},
+        util::auth_backend::AuthBackend,
     },
 };
 
 use super::handler::*;
 
 use super::handler::{
-    draft::draft_ws_handler, webhook::{filter_github_webhook_requests, handle_pull_request_event},
+    draft::draft_ws_handler,
+    project_feed::{relay_task_events, ProjectFeed},
+    webhook::{filter_github_webhook_requests, handle_pull_request_event},
 };
 
 #[derive(Clone)]
@@ -41,6 +48,11 @@ pub struct AppState {
     pub draft_repo: DraftRepository,
     pub notif_repo: NotificationRepository,
     pub requ_repo: RequirementRepository,
+    pub comment_repo: CommentRepository,
+    pub template_repo: TemplateRepository,
+    pub project_feed: ProjectFeed,
+    pub hook_repo: OutgoingWebhookRepository,
+    pub webhooks: WebhookDispatcher,
     pub invitation_token_repo: Arc<Mutex<InvitationTokenRepository>>,
     pub draft_collaboration_manager: Arc<Mutex<DraftCollaborationManager>>,
 }
@@ -56,6 +68,7 @@ pub struct AppConfig {
 
 impl App {
     pub async fn new() -> Self {
+        let hook_repo = OutgoingWebhookRepository::new().await;
         let state = Arc::new(Mutex::new(AppState {
             user_repo: UserRepository::new().await,
             task_repo: TaskRepository::new().await,
@@ -64,10 +77,41 @@ impl App {
             draft_repo: DraftRepository::new().await,
             notif_repo: NotificationRepository::new().await,
             requ_repo: RequirementRepository::new().await,
+            comment_repo: CommentRepository::new().await,
+            template_repo: TemplateRepository::new().await,
+            project_feed: ProjectFeed::default(),
+            webhooks: WebhookDispatcher::new(hook_repo.clone()),
+            hook_repo,
             invitation_token_repo: Arc::new(Mutex::new(InvitationTokenRepository::default())),
             draft_collaboration_manager: Arc::new(Mutex::new(DraftCollaborationManager::new())),
         }));
 
+        let task_events = state.lock().await.task_repo.events.subscribe();
+        tokio::spawn(relay_task_events(state.clone(), task_events));
+        {
+            let state = state.lock().await;
+            tokio::spawn(run_reminder_scheduler(
+                state.task_repo.clone(),
+                state.agenda_repo.clone(),
+                state.user_repo.clone(),
+                state.notif_repo.clone(),
+                ReminderConfig::from_env(),
+            ));
+            if let Some(config) = MailerConfig::from_env() {
+                match Mailer::new(&config) {
+                    Ok(mailer) => {
+                        tokio::spawn(run_mailer(
+                            state.notif_repo.clone(),
+                            state.user_repo.clone(),
+                            mailer,
+                            config,
+                        ));
+                    }
+                    Err(err) => tracing::warn!("Mail is disabled, SMTP setup failed: {err}"),
+                }
+            }
+        }
+
         let session_store = MemoryStore::default();
         let session_layer = SessionManagerLayer::new(session_store)
             .with_secure(false)
@@ -103,9 +147,13 @@ impl App {
                 .nest("/api/users", user::router())
                 .nest("/api/task_lists", task_list::router())
                 .nest("/api/links", task_link::router())
+                .nest("/api/comments", comment::router())
                 .nest("/api/agendas", agenda::router())
                 .nest("/api/drafts", draft::router())
                 .nest("/api/invitation", project::invitation_router())
+                .nest("/api/search", search::router())
+                .nest("/api/templates", template::router())
+                .nest("/api/tasks", bulk_task::router())
                 .route_layer(login_required!(AuthBackend, login_url = "/login"))
                 .nest("/api/auth", auth::router())
                 .layer(auth_layer)
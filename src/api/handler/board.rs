@@ -0,0 +1,309 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use axum_login::{AuthSession, AuthUser};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    api::{
+        app::AppState,
+        model::{
+            board::{AssigneeCount, BoardAssignee, BoardCard, BoardColumn, ColumnSummary},
+            change::ProjectChange,
+            status::Status,
+            util::Id,
+        },
+    },
+    db::{
+        model::{status::StatusPool, task::Task},
+        repository::utils::{get_str_id, DbModelId},
+    },
+    usecase::{
+        board::{
+            count_by_status, group_by_status, list_position_for_card, query_project_scope,
+            query_scope_tasks, query_task_list_scope, StatusCount,
+        },
+        task_order::move_task,
+        util::auth_backend::AuthBackend,
+        workflow::status_key,
+    },
+};
+
+use super::{
+    project_feed::publish_task_list_change,
+    task::{
+        check_task_in_list, update_task_from_request, IoErrorWrapper, PatchTaskRequest,
+        PatchTaskResponse,
+    },
+    util::{authorize_against_project_id, authorize_against_task_list_id, task_db_to_api},
+};
+
+pub fn project_router() -> Router<Arc<Mutex<AppState>>> {
+    Router::new()
+        .route("/board", get(get_board_for_project))
+        .route("/board/summary", get(get_board_summary_for_project))
+}
+
+pub fn task_list_router() -> Router<Arc<Mutex<AppState>>> {
+    Router::new()
+        .route("/board", get(get_board_for_task_list))
+        .route("/board/move", post(move_card))
+}
+
+fn column_summaries(pool: &StatusPool, counts: Vec<StatusCount>) -> Vec<ColumnSummary> {
+    counts
+        .into_iter()
+        .map(|count| {
+            let column = pool.incomplete.iter().find(|s| s.number == count.status);
+            let wip_limit = column.and_then(|column| column.wip_limit);
+            let over_limit = match (column, wip_limit) {
+                (Some(column), Some(limit)) if column.wip_per_assignee => {
+                    count.per_assignee.values().any(|n| *n as u32 > limit)
+                }
+                (_, Some(limit)) => count.count as u32 > limit,
+                _ => false,
+            };
+            let mut per_assignee: Vec<_> = count
+                .per_assignee
+                .into_iter()
+                .map(|(id, count)| AssigneeCount {
+                    user: Id { id },
+                    count,
+                })
+                .collect();
+            per_assignee.sort_by(|a, b| a.user.id.cmp(&b.user.id));
+
+            ColumnSummary {
+                name: column.map_or(pool.complete.name.clone(), |column| column.name.clone()),
+                status: match column {
+                    Some(_) => Status::Incomplete { id: count.status },
+                    None => Status::Complete,
+                },
+                count: count.count,
+                wip_limit,
+                over_limit,
+                per_assignee,
+            }
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetBoardSummaryResponse {
+    pub columns: Vec<ColumnSummary>,
+}
+
+pub async fn get_board_summary_for_project(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(project_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) =
+        authorize_against_project_id(auth_session, &state.project_repo, &project_id).await
+    {
+        return Ok(value);
+    }
+
+    let scope = query_project_scope(&state.project_repo, &project_id).await?;
+    let tasks = query_scope_tasks(&state.task_repo, &scope.task_lists).await?;
+    let tasks: Vec<_> = tasks.iter().map(|(task, _)| task).collect();
+    let counts = count_by_status(&scope.status_pool, &tasks);
+
+    Ok((
+        StatusCode::OK,
+        Json(GetBoardSummaryResponse {
+            columns: column_summaries(&scope.status_pool, counts),
+        }),
+    )
+        .into_response())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetBoardResponse {
+    pub columns: Vec<BoardColumn>,
+}
+
+async fn build_board(
+    state: &AppState,
+    pool: &StatusPool,
+    tasks: Vec<(Task, DbModelId)>,
+) -> Result<Vec<BoardColumn>, std::io::Error> {
+    let mut profiles: HashMap<DbModelId, BoardAssignee> = HashMap::new();
+    let mut columns = vec![];
+    for (status, cards) in group_by_status(pool, tasks) {
+        let column = pool.incomplete.iter().find(|s| s.number == status);
+        let mut board_cards = vec![];
+        for (task, task_list_id) in cards {
+            let mut assignee_profiles = vec![];
+            for assignee in task.assignees.iter().flatten() {
+                if !profiles.contains_key(assignee) {
+                    let user = state.user_repo.query_user_by_id(assignee).await?;
+                    profiles.insert(
+                        assignee.clone(),
+                        BoardAssignee {
+                            id: assignee.clone(),
+                            username: user.username,
+                            avatar: user.avatar,
+                        },
+                    );
+                }
+                assignee_profiles.push(profiles[assignee].clone());
+            }
+            board_cards.push(BoardCard {
+                task: task_db_to_api(task),
+                task_list_id,
+                assignee_profiles,
+            });
+        }
+        columns.push(BoardColumn {
+            name: column.map_or(pool.complete.name.clone(), |column| column.name.clone()),
+            status: match column {
+                Some(_) => Status::Incomplete { id: status },
+                None => Status::Complete,
+            },
+            count: board_cards.len(),
+            wip_limit: column.and_then(|column| column.wip_limit),
+            cards: board_cards,
+        });
+    }
+    Ok(columns)
+}
+
+pub async fn get_board_for_project(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(project_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) =
+        authorize_against_project_id(auth_session, &state.project_repo, &project_id).await
+    {
+        return Ok(value);
+    }
+
+    let scope = query_project_scope(&state.project_repo, &project_id).await?;
+    let tasks = query_scope_tasks(&state.task_repo, &scope.task_lists).await?;
+    let columns = build_board(state, &scope.status_pool, tasks).await?;
+
+    Ok((StatusCode::OK, Json(GetBoardResponse { columns })).into_response())
+}
+
+pub async fn get_board_for_task_list(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(task_list_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) = authorize_against_task_list_id(
+        auth_session,
+        &state.project_repo,
+        &state.task_repo,
+        &task_list_id,
+    )
+    .await
+    {
+        return Ok(value);
+    }
+
+    let scope = query_task_list_scope(
+        &state.task_repo,
+        &state.project_repo,
+        &state.user_repo,
+        &task_list_id,
+    )
+    .await?;
+    let tasks = query_scope_tasks(&state.task_repo, &[task_list_id]).await?;
+    let columns = build_board(state, &scope.status_pool, tasks).await?;
+
+    Ok((StatusCode::OK, Json(GetBoardResponse { columns })).into_response())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MoveCardRequest {
+    pub task: Id,
+    pub status: Status,
+    /// Zero based position inside the target column, last when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+}
+
+/// Changes the status of a card and its position in the column in one call
+pub async fn move_card(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(task_list_id): Path<String>,
+    Json(req): Json<MoveCardRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+    if let Some(value) = authorize_against_task_list_id(
+        auth_session,
+        &state.project_repo,
+        &state.task_repo,
+        &task_list_id,
+    )
+    .await
+    {
+        return Ok(value);
+    }
+    let task_id = req.task.id;
+    check_task_in_list(state, &task_list_id, &task_id).await?;
+
+    // an absent deadline clears it, so carry the current one over
+    let current = state.task_repo.query_task_by_id(&task_id).await?;
+    let patch = PatchTaskRequest {
+        name: None,
+        description: None,
+        assignees: None,
+        status: Some(req.status),
+        deadline: current.ddl.map(|ddl| ddl.0),
+        pr: None,
+        auto_complete: None,
+        priority: None,
+        labels: None,
+        custom_fields: None,
+        estimate: None,
+        recurrence: None,
+    };
+    let (task, warnings) =
+        update_task_from_request(state, &task_list_id, &task_id, &author_id, patch).await?;
+
+    // the status may differ from the requested one when task links forbid it
+    let key = status_key(&task);
+    let tasks = query_scope_tasks(&state.task_repo, &[task_list_id.clone()]).await?;
+    let order: Vec<_> = tasks
+        .iter()
+        .map(|(task, _)| get_str_id(&task.id))
+        .filter(|id| *id != task_id)
+        .collect();
+    let column: Vec<_> = tasks
+        .iter()
+        .filter(|(other, _)| status_key(other) == key && get_str_id(&other.id) != task_id)
+        .map(|(other, _)| get_str_id(&other.id))
+        .collect();
+    let position = list_position_for_card(&order, &column, req.position);
+    let task = move_task(
+        &task_id,
+        &task_list_id,
+        Some(position),
+        &state.task_repo,
+        &state.notif_repo,
+    )
+    .await?;
+    let task = task_db_to_api(task);
+    let change = ProjectChange::TaskUpdated {
+        task_list_id: task_list_id.clone(),
+        task: task.clone(),
+    };
+    publish_task_list_change(state, &task_list_id, &author_id, change).await;
+
+    Ok((StatusCode::OK, Json(PatchTaskResponse { task, warnings })).into_response())
+}
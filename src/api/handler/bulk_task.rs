@@ -0,0 +1,166 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::State, http::StatusCode, response::IntoResponse, routing::post, Json, Router,
+};
+use axum_login::{AuthSession, AuthUser};
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    api::{
+        app::AppState,
+        model::{
+            change::ProjectChange, outgoing_webhook::WebhookEvent, status::Status, task::Task,
+            util::Id,
+        },
+    },
+    db::repository::utils::get_str_id,
+    usecase::{
+        bulk_task::{apply_bulk_patch, BulkPatch},
+        util::auth_backend::AuthBackend,
+    },
+};
+
+use super::{
+    outgoing_webhook::dispatch_task_event,
+    project_feed::publish_task_list_change,
+    task::IoErrorWrapper,
+    util::{authorize_against_task_list_id, task_db_to_api},
+};
+
+pub fn router() -> Router<Arc<Mutex<AppState>>> {
+    Router::new().route("/bulk", post(bulk_update_tasks))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BulkTaskRequest {
+    pub tasks: Vec<Id>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+    #[serde(default)]
+    pub add_assignees: Vec<Id>,
+    #[serde(default)]
+    pub remove_assignees: Vec<Id>,
+    /// Minutes added to every deadline, negative to bring them forward
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shift_deadline: Option<i64>,
+    /// Moves the tasks to the end of this task list
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_list: Option<Id>,
+    /// Deletes the tasks and their subtasks
+    #[serde(default)]
+    pub delete: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BulkTaskResponse {
+    pub tasks: Vec<Task>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deleted: Vec<String>,
+    /// Exceeded WIP limits that were not enforced
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+pub async fn bulk_update_tasks(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Json(req): Json<BulkTaskRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+
+    // deleted tasks can no longer be traced back to their list afterwards
+    let mut list_of_task = HashMap::new();
+    let mut task_lists = vec![];
+    for Id { id } in &req.tasks {
+        let task_list = state.task_repo.query_task_list_id_by_task(id).await?;
+        if req.delete {
+            // subtasks share the list of their root
+            for descendant in state.task_repo.query_descendants_of_task(id).await? {
+                list_of_task.insert(descendant, task_list.clone());
+            }
+        }
+        list_of_task.insert(id.clone(), task_list.clone());
+        if !task_lists.contains(&task_list) {
+            task_lists.push(task_list);
+        }
+    }
+    if let Some(Id { id }) = &req.task_list {
+        if !task_lists.contains(id) {
+            task_lists.push(id.clone());
+        }
+    }
+    for task_list in &task_lists {
+        if let Some(value) = authorize_against_task_list_id(
+            auth_session.clone(),
+            &state.project_repo,
+            &state.task_repo,
+            task_list,
+        )
+        .await
+        {
+            return Ok(value);
+        }
+    }
+
+    let ids = |ids: Vec<Id>| -> Vec<String> { ids.into_iter().map(|id| id.id).collect() };
+    let patch = BulkPatch {
+        status: req.status.map(|status| match status {
+            Status::Complete => "complete".to_owned(),
+            Status::Incomplete { id } => id,
+        }),
+        add_assignees: ids(req.add_assignees),
+        remove_assignees: ids(req.remove_assignees),
+        shift_deadline: req.shift_deadline.map(Duration::minutes),
+        task_list: req.task_list.map(|task_list| task_list.id),
+        delete: req.delete,
+    };
+    let outcome = apply_bulk_patch(
+        &state.task_repo,
+        &state.project_repo,
+        &state.user_repo,
+        &state.notif_repo,
+        &ids(req.tasks),
+        &patch,
+        &author_id,
+    )
+    .await?;
+    for task in &outcome.tasks {
+        let task_id = get_str_id(&task.id);
+        let task_list = state.task_repo.query_task_list_id_by_task(&task_id).await?;
+        if outcome.completed.contains(&task_id) {
+            let event = WebhookEvent::TaskCompleted;
+            let actor = Some(author_id.as_str());
+            if let Err(err) = dispatch_task_event(state, &task_list, event, actor, task).await {
+                tracing::warn!("Sending webhooks failed: {err}");
+            }
+        }
+        let change = ProjectChange::TaskUpdated {
+            task_list_id: task_list.clone(),
+            task: task_db_to_api(task.clone()),
+        };
+        publish_task_list_change(state, &task_list, &author_id, change).await;
+    }
+    for task_id in &outcome.deleted {
+        if let Some(task_list) = list_of_task.get(task_id) {
+            let change = ProjectChange::TaskDeleted {
+                task_list_id: task_list.clone(),
+                task_id: task_id.clone(),
+            };
+            publish_task_list_change(state, task_list, &author_id, change).await;
+        }
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(BulkTaskResponse {
+            tasks: outcome.tasks.into_iter().map(task_db_to_api).collect(),
+            deleted: outcome.deleted,
+            warnings: outcome.warnings,
+        }),
+    )
+        .into_response())
+}
@@ -0,0 +1,265 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, patch},
+    Json, Router,
+};
+use axum_login::{AuthSession, AuthUser};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
+use tokio::sync::Mutex;
+
+use crate::{
+    api::{
+        app::AppState,
+        model::{comment::Comment, util::Id},
+    },
+    usecase::{
+        mention::{notify_task_mentions, MentionEdit},
+        notification::notify_task_comment,
+        util::auth_backend::AuthBackend,
+    },
+};
+
+use super::{
+    task::IoErrorWrapper,
+    util::{authorize_against_task_id, comment_db_to_api, comments_db_to_api_thread},
+};
+
+pub fn router() -> Router<Arc<Mutex<AppState>>> {
+    Router::new()
+        .route(
+            "/tasks/:task_id",
+            get(get_comments_for_task).post(create_comment_for_task),
+        )
+        .route(
+            "/:comment_id",
+            patch(patch_comment).delete(delete_comment),
+        )
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetCommentsForTaskResponse {
+    pub comments: Vec<Comment>,
+}
+
+pub async fn get_comments_for_task(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(task_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) =
+        authorize_against_task_id(&auth_session, &state.project_repo, &state.task_repo, &task_id)
+            .await
+    {
+        return Ok(value);
+    }
+
+    let comments = state.comment_repo.query_comments_by_task_id(&task_id).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetCommentsForTaskResponse {
+            comments: comments_db_to_api_thread(comments),
+        }),
+    )
+        .into_response())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateCommentForTaskRequest {
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<Id>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateCommentForTaskResponse {
+    #[serde(flatten)]
+    pub comment: Comment,
+}
+
+pub async fn create_comment_for_task(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(task_id): Path<String>,
+    Json(req): Json<CreateCommentForTaskRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) =
+        authorize_against_task_id(&auth_session, &state.project_repo, &state.task_repo, &task_id)
+            .await
+    {
+        return Ok(value);
+    }
+    let user_id = match auth_session.user {
+        None => return Ok(StatusCode::UNAUTHORIZED.into_response()),
+        Some(user) => user.id(),
+    };
+
+    if let Some(Id { id }) = &req.reply_to {
+        let parent_task = state.comment_repo.query_task_id_by_comment(id).await?;
+        if parent_task != task_id {
+            return Ok((
+                StatusCode::BAD_REQUEST,
+                "The replied comment is not under this task",
+            )
+                .into_response());
+        }
+    }
+
+    let comment = state
+        .comment_repo
+        .insert_comment_for_task(
+            &task_id,
+            &crate::db::model::comment::Comment::new(
+                user_id,
+                req.content,
+                req.reply_to.map(|id| id.id),
+            ),
+        )
+        .await?;
+
+    notify_task_comment(&state.task_repo, &state.notif_repo, &task_id, &comment).await?;
+    if let Err(err) = notify_task_mentions(
+        &state.project_repo,
+        &state.task_repo,
+        &state.notif_repo,
+        &task_id,
+        "Comment on task",
+        MentionEdit {
+            author_id: &comment.author,
+            old_text: "",
+            new_text: &comment.content,
+        },
+    )
+    .await
+    {
+        tracing::warn!("Notifying mentioned users failed: {err}");
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(CreateCommentForTaskResponse {
+            comment: comment_db_to_api(comment),
+        }),
+    )
+        .into_response())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PatchCommentRequest {
+    pub content: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PatchCommentResponse {
+    #[serde(flatten)]
+    pub comment: Comment,
+}
+
+pub async fn patch_comment(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(comment_id): Path<String>,
+    Json(req): Json<PatchCommentRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    let comment = match authorize_against_own_comment(&auth_session, state, &comment_id).await {
+        Ok(comment) => comment,
+        Err(value) => return Ok(value),
+    };
+    if comment.deleted {
+        return Ok((StatusCode::BAD_REQUEST, "The comment is deleted").into_response());
+    }
+    let task_id = state.comment_repo.query_task_id_by_comment(&comment_id).await?;
+    let old_content = comment.content.clone();
+
+    let comment = state
+        .comment_repo
+        .update_comment(
+            &comment_id,
+            &crate::db::model::comment::Comment {
+                id: None,
+                content: req.content,
+                updated_at: Some(Datetime(Utc::now())),
+                ..comment
+            },
+        )
+        .await?;
+
+    if let Err(err) = notify_task_mentions(
+        &state.project_repo,
+        &state.task_repo,
+        &state.notif_repo,
+        &task_id,
+        "Comment on task",
+        MentionEdit {
+            author_id: &comment.author,
+            old_text: &old_content,
+            new_text: &comment.content,
+        },
+    )
+    .await
+    {
+        tracing::warn!("Notifying mentioned users failed: {err}");
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(PatchCommentResponse {
+            comment: comment_db_to_api(comment),
+        }),
+    )
+        .into_response())
+}
+
+pub async fn delete_comment(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(comment_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Err(value) = authorize_against_own_comment(&auth_session, state, &comment_id).await {
+        return Ok(value);
+    }
+
+    state.comment_repo.delete_comment(&comment_id).await?;
+
+    Ok(StatusCode::OK.into_response())
+}
+
+/// The caller must still have access to the task and be the author of the comment
+async fn authorize_against_own_comment(
+    auth_session: &AuthSession<AuthBackend>,
+    state: &AppState,
+    comment_id: &str,
+) -> Result<crate::db::model::comment::Comment, axum::http::Response<axum::body::Body>> {
+    let user_id = match auth_session.user.clone() {
+        None => return Err(StatusCode::UNAUTHORIZED.into_response()),
+        Some(user) => user.id(),
+    };
+    let task_id = match state.comment_repo.query_task_id_by_comment(comment_id).await {
+        Ok(task_id) => task_id,
+        Err(err) => return Err((StatusCode::NOT_FOUND, err.to_string()).into_response()),
+    };
+    if let Some(value) =
+        authorize_against_task_id(auth_session, &state.project_repo, &state.task_repo, &task_id)
+            .await
+    {
+        return Err(value);
+    }
+    let comment = match state.comment_repo.query_comment_by_id(comment_id).await {
+        Ok(comment) => comment,
+        Err(err) => return Err((StatusCode::NOT_FOUND, err.to_string()).into_response()),
+    };
+    if comment.author != user_id {
+        return Err((StatusCode::FORBIDDEN, "The comment is not written by you").into_response());
+    }
+    Ok(comment)
+}
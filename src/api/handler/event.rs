@@ -3,6 +3,7 @@ use std::sync::Arc;
 use crate::db::model::agenda::Event as DbEvent;
 use crate::db::repository::utils::get_str_id;
 use crate::db::repository::utils::unwrap_thing;
+use crate::usecase::mention::{notify_event_mentions, MentionEdit};
 use crate::usecase::notification::assign_event_for_user;
 use crate::usecase::notification::deassign_event_for_user;
 
@@ -12,7 +13,7 @@ use axum::{
     response::IntoResponse,
     Json,
 };
-use axum_login::AuthSession;
+use axum_login::{AuthSession, AuthUser};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
@@ -20,11 +21,13 @@ use tokio::sync::Mutex;
 use crate::{
     api::{
         app::AppState,
-        model::{agenda::Event, util::Id},
+        model::{agenda::Event, change::ProjectChange, util::Id},
     },
     usecase::util::auth_backend::AuthBackend,
 };
 
+use super::outgoing_webhook::dispatch_event_scheduled;
+use super::project_feed::publish_agenda_change;
 use super::task::IoErrorWrapper;
 use super::util::{authorize_against_agenda_id, authorize_against_event_id, event_db_to_api};
 
@@ -60,6 +63,25 @@ pub async fn create_event_for_agenda(
         .insert_event_for_agenda(&DbEvent::from_create_request(req), &agenda_id)
         .await?;
 
+    // the event is saved already
+    if let Err(err) = notify_event_mentions(
+        &state.project_repo,
+        agenda_repo,
+        &state.notif_repo,
+        &agenda_id,
+        &get_str_id(&event.id),
+        &event.name,
+        MentionEdit {
+            author_id: &auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default(),
+            old_text: "",
+            new_text: &event.description,
+        },
+    )
+    .await
+    {
+        tracing::warn!("Notifying mentioned users failed: {err}");
+    }
+
     for Id { id } in &participants {
         let _ = assign_event_for_user(
             agenda_repo,
@@ -70,13 +92,19 @@ pub async fn create_event_for_agenda(
         .await?;
     }
 
-    Ok((
-        StatusCode::OK,
-        Json(CreateEventForAgendaResponse {
-            event: event_db_to_api(event, participants),
-        }),
-    )
-        .into_response())
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+    // the event is saved already
+    if let Err(err) = dispatch_event_scheduled(state, &agenda_id, Some(&author_id), &event).await {
+        tracing::warn!("Sending webhooks failed: {err}");
+    }
+    let event = event_db_to_api(event, participants);
+    let change = ProjectChange::EventCreated {
+        agenda_id: agenda_id.clone(),
+        event: event.clone(),
+    };
+    publish_agenda_change(state, &agenda_id, &author_id, change).await;
+
+    Ok((StatusCode::OK, Json(CreateEventForAgendaResponse { event })).into_response())
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -151,6 +179,7 @@ pub async fn patch_event(
         Err(msg) => return (StatusCode::INTERNAL_SERVER_ERROR, msg.to_string()).into_response(),
     };
 
+    let old_description = event_ref.description.clone();
     let event = crate::db::model::agenda::Event {
         id: event_ref.id,
         name: req.name.unwrap_or(event_ref.name),
@@ -166,6 +195,24 @@ pub async fn patch_event(
 
     let event_id = get_str_id(&event.as_ref().unwrap().id);
 
+    if let Err(msg) = notify_event_mentions(
+        &state.project_repo,
+        agenda_repo,
+        &state.notif_repo,
+        &agenda_id,
+        &event_id,
+        &event.as_ref().unwrap().name,
+        MentionEdit {
+            author_id: &auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default(),
+            old_text: &old_description,
+            new_text: &event.as_ref().unwrap().description,
+        },
+    )
+    .await
+    {
+        tracing::warn!("Notifying mentioned users failed: {msg}");
+    }
+
     let assignees_ref = match agenda_repo.query_assignees_of_event(&event_id).await {
         Ok(assignees) => assignees,
         Err(msg) => return (StatusCode::INTERNAL_SERVER_ERROR, msg.to_string()).into_response(),
@@ -198,13 +245,14 @@ pub async fn patch_event(
             }
         }
     }
-    (
-        StatusCode::OK,
-        Json(PatchEventResponse {
-            event: event_db_to_api(event.unwrap(), req.participants.unwrap_or_default()),
-        }),
-    )
-        .into_response()
+    let event = event_db_to_api(event.unwrap(), req.participants.unwrap_or_default());
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+    let change = ProjectChange::EventUpdated {
+        agenda_id: agenda_id.clone(),
+        event: event.clone(),
+    };
+    publish_agenda_change(state, &agenda_id, &author_id, change).await;
+    (StatusCode::OK, Json(PatchEventResponse { event })).into_response()
 }
 
 pub async fn delete_event(
@@ -224,7 +272,15 @@ pub async fn delete_event(
     }
 
     match agenda_repo.delete_event(&event_id).await {
-        Ok(_) => StatusCode::OK.into_response(),
+        Ok(_) => {
+            let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+            let change = ProjectChange::EventDeleted {
+                agenda_id: agenda_id.clone(),
+                event_id,
+            };
+            publish_agenda_change(state, &agenda_id, &author_id, change).await;
+            StatusCode::OK.into_response()
+        }
         Err(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.to_string()).into_response(),
     }
 }
@@ -1,13 +1,21 @@
 pub mod agenda;
 pub mod auth;
+pub mod board;
+pub mod bulk_task;
+pub mod comment;
 pub mod draft;
 pub mod event;
 pub mod notification;
+pub mod outgoing_webhook;
 pub mod project;
+pub mod project_feed;
 pub mod requirement;
+pub mod search;
 pub mod task;
 pub mod task_link;
 pub mod task_list;
+pub mod template;
+pub mod time_entry;
 pub mod user;
 pub mod util;
 pub mod webhook;
@@ -1,77 +1,117 @@
-use std::sync::Arc;
+use std::{convert::Infallible, io, sync::Arc};
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
-    routing::{get, patch},
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    routing::{delete, get, patch, post},
     Json, Router,
 };
 use axum_login::AuthSession;
-use futures::future::{join_all, try_join_all};
+use futures::{stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast::error::RecvError, Mutex};
 
 use crate::{
-    api::{app::AppState, model::notification::Notification},
-    db::repository::utils::unwrap_thing,
-    usecase::{notification::query_notif_by_id, util::auth_backend::AuthBackend},
+    api::{
+        app::AppState,
+        model::notification::{Notification, NotificationPreference},
+    },
+    db::repository::{
+        notification::{NotificationEvent, NotificationQuery},
+        utils::unwrap_thing,
+    },
+    usecase::{
+        notification::{query_notif_by_id, query_notifs_with_sources},
+        util::auth_backend::AuthBackend,
+    },
 };
 
 use super::{
     task::IoErrorWrapper,
-    util::{authorize_against_user_id, notif_db_to_api},
+    util::{
+        authorize_against_user_id, notif_db_to_api, notif_preference_api_to_db,
+        notif_preference_db_to_api,
+    },
 };
 
 pub fn user_router() -> Router<Arc<Mutex<AppState>>> {
     Router::new()
         .route(
             "/notifications/:notification_id",
-            patch(handle_notification),
+            patch(handle_notification).delete(delete_notification),
         )
+        .route("/notifications/read", post(handle_notifications))
+        .route("/notifications/delete", post(delete_notifications))
+        .route("/notifications/handled", delete(clear_handled_notifications))
         .route("/notifications", get(get_notifications))
+        .route("/notifications/stream", get(stream_notifications))
+        .route(
+            "/notifications/preferences",
+            get(get_notification_preference).put(put_notification_preference),
+        )
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GetNotificationsResponse {
     notifications: Vec<Notification>,
+    /// Pass as `cursor` to get the next page; missing on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Newest first. `source` is `task`, `event`, `draft`, `requirement`, `project` or
+/// `invitation`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetNotificationsQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
 }
 
 pub async fn get_notifications(
     auth_session: AuthSession<AuthBackend>,
     State(state): State<Arc<Mutex<AppState>>>,
     Path(user_id): Path<String>,
+    Query(query): Query<GetNotificationsQuery>,
 ) -> Result<impl IntoResponse, IoErrorWrapper> {
     if let Some(value) = authorize_against_user_id(auth_session, &user_id) {
         return Ok(value);
     }
 
+    let query = NotificationQuery {
+        cursor: query.cursor,
+        limit: query.limit.unwrap_or(DEFAULT_PAGE_SIZE).max(1),
+        handled: query.handled,
+        source: query.source,
+        project: query.project,
+    };
     let notif_ids = state
         .lock()
         .await
-        .user_repo
-        .query_notif_by_user_id(&user_id)
+        .notif_repo
+        .query_notifs_of_user(&user_id, &query)
         .await?;
+    let next_cursor = match notif_ids.len() == query.limit {
+        true => notif_ids.last().cloned(),
+        false => None,
+    };
 
     let ref state = state.lock().await;
-    let notifs = notif_ids
-        .into_iter()
-        .map(|id| async move {
-            query_notif_by_id(
-                &state.notif_repo,
-                &state.task_repo,
-                &state.agenda_repo,
-                id.as_str(),
-            )
-            .await
-        })
-        .collect::<Vec<_>>();
-
-    let notifs: Vec<_> = join_all(notifs)
-        .await
-        .into_iter()
-        .filter_map(|notif| notif.ok())
-        .collect();
+    let invitations = state.invitation_token_repo.lock().await;
+    let notifs = query_notifs_with_sources(&state.notif_repo, &invitations, &notif_ids).await?;
 
     Ok((
         StatusCode::OK,
@@ -80,6 +120,7 @@ pub async fn get_notifications(
                 .into_iter()
                 .map(|(notif, source)| notif_db_to_api(notif, source))
                 .collect(),
+            next_cursor,
         }),
     )
         .into_response())
@@ -104,14 +145,12 @@ pub async fn handle_notification(
     let ref notif_repo = state.notif_repo;
 
     let notif = notif_repo.handle_notif_by_id(&notification_id).await?;
+    notif_repo.hub.publish(&user_id, NotificationEvent::Updated);
 
-    let (notif, source) = query_notif_by_id(
-        &state.notif_repo,
-        &state.task_repo,
-        &state.agenda_repo,
-        &unwrap_thing(notif.id.unwrap()),
-    )
-    .await?;
+    let invitations = state.invitation_token_repo.lock().await;
+    let (notif, source) =
+        query_notif_by_id(&state.notif_repo, &invitations, &unwrap_thing(notif.id.unwrap()))
+            .await?;
 
     Ok((
         StatusCode::OK,
@@ -121,3 +160,223 @@ pub async fn handle_notification(
     )
         .into_response())
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct NotificationIdsRequest {
+    /// Every notification of the user when left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ids: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NotificationCountResponse {
+    pub count: usize,
+}
+
+/// Marks many notifications handled at once
+pub async fn handle_notifications(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(user_id): Path<String>,
+    Json(req): Json<NotificationIdsRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    if let Some(value) = authorize_against_user_id(auth_session, &user_id) {
+        return Ok(value);
+    }
+
+    let ref notif_repo = state.lock().await.notif_repo;
+    let count = notif_repo.handle_notifs_of_user(&user_id, req.ids.as_deref()).await?;
+    notif_repo.hub.publish(&user_id, NotificationEvent::Updated);
+    Ok((StatusCode::OK, Json(NotificationCountResponse { count })).into_response())
+}
+
+pub async fn delete_notification(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((user_id, notification_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    if let Some(value) = authorize_against_user_id(auth_session, &user_id) {
+        return Ok(value);
+    }
+
+    let ref notif_repo = state.lock().await.notif_repo;
+    let ids = [notification_id];
+    if notif_repo.delete_notifs_of_user(&user_id, Some(&ids)).await? == 0 {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    notif_repo.hub.publish(&user_id, NotificationEvent::Updated);
+    Ok(StatusCode::OK.into_response())
+}
+
+/// Deletes the listed notifications; `ids` is required here
+pub async fn delete_notifications(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(user_id): Path<String>,
+    Json(req): Json<NotificationIdsRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    if let Some(value) = authorize_against_user_id(auth_session, &user_id) {
+        return Ok(value);
+    }
+    let Some(ids) = req.ids else {
+        return Ok((StatusCode::BAD_REQUEST, "ids are required").into_response());
+    };
+
+    let ref notif_repo = state.lock().await.notif_repo;
+    let count = notif_repo.delete_notifs_of_user(&user_id, Some(&ids)).await?;
+    notif_repo.hub.publish(&user_id, NotificationEvent::Updated);
+    Ok((StatusCode::OK, Json(NotificationCountResponse { count })).into_response())
+}
+
+/// Deletes every handled notification of the user
+pub async fn clear_handled_notifications(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(user_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    if let Some(value) = authorize_against_user_id(auth_session, &user_id) {
+        return Ok(value);
+    }
+
+    let ref notif_repo = state.lock().await.notif_repo;
+    let count = notif_repo.delete_notifs_of_user(&user_id, None).await?;
+    Ok((StatusCode::OK, Json(NotificationCountResponse { count })).into_response())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UnreadCount {
+    pub unread: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct StreamNotificationsQuery {
+    /// Resumes after this notification, for clients that cannot set `Last-Event-ID`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_id: Option<String>,
+}
+
+async fn notification_event(state: &Arc<Mutex<AppState>>, id: &str) -> Option<Event> {
+    let state = state.lock().await;
+    let invitations = state.invitation_token_repo.lock().await;
+    let (notif, source) = query_notif_by_id(&state.notif_repo, &invitations, id).await.ok()?;
+    Event::default()
+        .id(id)
+        .event("notification")
+        .json_data(notif_db_to_api(notif, source))
+        .ok()
+}
+
+async fn unread_event(state: &Arc<Mutex<AppState>>, user_id: &str) -> Option<Event> {
+    let unread = state
+        .lock()
+        .await
+        .notif_repo
+        .count_unhandled_by_user(user_id)
+        .await
+        .ok()?;
+    Event::default()
+        .event("unread")
+        .json_data(UnreadCount { unread })
+        .ok()
+}
+
+/// Server-sent events for the user: `notification` for every new notification, with its id
+/// as event id, and `unread` whenever the unread count may have changed. Reconnecting with
+/// `Last-Event-ID` (or `last_id`) first replays the notifications missed in between.
+pub async fn stream_notifications(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(user_id): Path<String>,
+    Query(query): Query<StreamNotificationsQuery>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    if let Some(value) = authorize_against_user_id(auth_session, &user_id) {
+        return Ok(value);
+    }
+    let last_id = headers
+        .get("last-event-id")
+        .and_then(|id| id.to_str().ok())
+        .map(str::to_owned)
+        .or(query.last_id);
+
+    // subscribe first so nothing inserted while replaying gets lost
+    let receiver = state.lock().await.notif_repo.hub.subscribe(&user_id);
+    let mut missed = vec![];
+    if let Some(last_id) = last_id {
+        let ref state = state.lock().await;
+        // a deleted anchor leaves nothing to resume from, so skip the replay
+        let since = match state.notif_repo._query_notif_by_id(&last_id).await {
+            Ok(last) => last.created_at,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(since) = since {
+            missed = state.notif_repo.query_notifs_of_user_since(&user_id, since).await?;
+        }
+    }
+
+    let mut initial = vec![];
+    for id in &missed {
+        initial.extend(notification_event(&state, id).await);
+    }
+    initial.extend(unread_event(&state, &user_id).await);
+
+    // notifications inserted after subscribing may be replayed already
+    let live = stream::unfold((receiver, state, user_id, missed), |live| async move {
+        let (mut receiver, state, user_id, replayed) = live;
+        let events = match receiver.recv().await {
+            Ok(NotificationEvent::Inserted(id)) if replayed.contains(&id) => {
+                vec![unread_event(&state, &user_id).await]
+            }
+            Ok(NotificationEvent::Inserted(id)) => {
+                vec![
+                    notification_event(&state, &id).await,
+                    unread_event(&state, &user_id).await,
+                ]
+            }
+            // a slow client missed events; the count is all it can catch up on
+            Ok(NotificationEvent::Updated) | Err(RecvError::Lagged(_)) => {
+                vec![unread_event(&state, &user_id).await]
+            }
+            Err(RecvError::Closed) => return None,
+        };
+        let live = (receiver, state, user_id, replayed);
+        Some((stream::iter(events.into_iter().flatten()), live))
+    })
+    .flatten();
+    let events = stream::iter(initial).chain(live).map(Ok::<_, Infallible>);
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()).into_response())
+}
+
+pub async fn get_notification_preference(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(user_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    if let Some(value) = authorize_against_user_id(auth_session, &user_id) {
+        return Ok(value);
+    }
+
+    let preference = state.lock().await.notif_repo.query_preference(&user_id).await?;
+    Ok((StatusCode::OK, Json(notif_preference_db_to_api(preference))).into_response())
+}
+
+pub async fn put_notification_preference(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(user_id): Path<String>,
+    Json(req): Json<NotificationPreference>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    if let Some(value) = authorize_against_user_id(auth_session, &user_id) {
+        return Ok(value);
+    }
+
+    let preference = state
+        .lock()
+        .await
+        .notif_repo
+        .update_preference(&user_id, &notif_preference_api_to_db(req))
+        .await?;
+    Ok((StatusCode::OK, Json(notif_preference_db_to_api(preference))).into_response())
+}
@@ -0,0 +1,328 @@
+use std::{io, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use axum_login::AuthSession;
+use chrono::Utc;
+use nanoid::nanoid;
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
+use tokio::sync::Mutex;
+
+use crate::{
+    api::{
+        app::AppState,
+        model::outgoing_webhook::{
+            OutgoingWebhook, WebhookDelivery, WebhookEvent, WebhookFormat,
+        },
+    },
+    db::{
+        model::{agenda::Event as DbEvent, task::Task as DbTask},
+        repository::utils::get_str_id,
+    },
+    usecase::{
+        custom_field::query_project_of_task_list, outgoing_webhook::WebhookPayload,
+        util::auth_backend::AuthBackend,
+    },
+};
+
+use super::{
+    task::IoErrorWrapper,
+    util::{
+        authorize_admin_against_project_id, authorize_against_project_id, event_db_to_api,
+        task_db_to_api, webhook_db_to_api, webhook_delivery_db_to_api, webhook_event_api_to_db,
+        webhook_format_api_to_db,
+    },
+};
+
+pub fn project_router() -> Router<Arc<Mutex<AppState>>> {
+    Router::new()
+        .route("/webhooks", get(get_webhooks).post(create_webhook))
+        .route(
+            "/webhooks/:webhook_id",
+            get(get_webhook).patch(patch_webhook).delete(delete_webhook),
+        )
+        .route("/webhooks/:webhook_id/deliveries", get(get_webhook_deliveries))
+}
+
+/// Sends a task event to the webhooks of the project owning the task list
+pub async fn dispatch_task_event(
+    state: &AppState,
+    task_list_id: &str,
+    event: WebhookEvent,
+    actor: Option<&str>,
+    task: &DbTask,
+) -> Result<(), io::Error> {
+    let project =
+        query_project_of_task_list(&state.task_repo, &state.project_repo, task_list_id).await?;
+    let Some(project) = project else {
+        return Ok(());
+    };
+    let text = match event {
+        WebhookEvent::TaskCompleted => format!("Task \"{}\" was completed", task.name),
+        WebhookEvent::PrLinked => format!(
+            "PR #{} ({}) was linked to task \"{}\"",
+            task.pr.pull_number, task.pr.repo, task.name
+        ),
+        _ => format!("Task \"{}\" was created", task.name),
+    };
+    let payload = WebhookPayload::new(
+        &webhook_event_api_to_db(event),
+        &get_str_id(&project.id),
+        actor,
+        format!("[{}] {text}", project.name),
+        task_db_to_api(task.clone()),
+    );
+    state.webhooks.dispatch(payload).await;
+    Ok(())
+}
+
+/// Sends `event_scheduled` to the webhooks of the project owning the agenda
+pub async fn dispatch_event_scheduled(
+    state: &AppState,
+    agenda_id: &str,
+    actor: Option<&str>,
+    event: &DbEvent,
+) -> Result<(), io::Error> {
+    let Ok(project_id) = state.agenda_repo.query_agenda_source_by_id(agenda_id).await else {
+        return Ok(());
+    };
+    let project = state.project_repo.query_project_by_id(&project_id).await?;
+    let text = format!(
+        "[{}] Event \"{}\" was scheduled for {} UTC",
+        project.name,
+        event.name,
+        event.start_time.0.format("%Y-%m-%d %H:%M")
+    );
+    let payload = WebhookPayload::new(
+        &webhook_event_api_to_db(WebhookEvent::EventScheduled),
+        &project_id,
+        actor,
+        text,
+        event_db_to_api(event.clone(), vec![]),
+    );
+    state.webhooks.dispatch(payload).await;
+    Ok(())
+}
+
+/// Sends `member_joined` to the webhooks of the project
+pub async fn dispatch_member_joined(
+    state: &AppState,
+    project_id: &str,
+    user_id: &str,
+) -> Result<(), io::Error> {
+    let project = state.project_repo.query_project_by_id(project_id).await?;
+    let user = state.user_repo.query_user_by_id(user_id).await?;
+    let payload = WebhookPayload::new(
+        &webhook_event_api_to_db(WebhookEvent::MemberJoined),
+        project_id,
+        Some(user_id),
+        format!("[{}] {} joined the project", project.name, user.username),
+        serde_json::json!({ "user": { "id": user_id, "username": user.username } }),
+    );
+    state.webhooks.dispatch(payload).await;
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetWebhooksResponse {
+    pub webhooks: Vec<OutgoingWebhook>,
+}
+
+pub async fn get_webhooks(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(project_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) =
+        authorize_against_project_id(auth_session, &state.project_repo, &project_id).await
+    {
+        return Ok(value);
+    }
+
+    let webhooks = state
+        .hook_repo
+        .query_webhooks_by_project(&project_id)
+        .await?
+        .into_iter()
+        .map(webhook_db_to_api)
+        .collect();
+    Ok((StatusCode::OK, Json(GetWebhooksResponse { webhooks })).into_response())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Generated when left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+/// Loads the webhook, making sure it belongs to the project
+async fn query_project_webhook(
+    state: &AppState,
+    project_id: &str,
+    webhook_id: &str,
+) -> Result<crate::db::model::outgoing_webhook::OutgoingWebhook, io::Error> {
+    let webhook = state.hook_repo.query_webhook_by_id(webhook_id).await?;
+    match webhook.project == project_id {
+        true => Ok(webhook),
+        false => Err(io::Error::new(io::ErrorKind::NotFound, "Webhook not found")),
+    }
+}
+
+pub async fn create_webhook(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(project_id): Path<String>,
+    Json(req): Json<CreateWebhookRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) =
+        authorize_admin_against_project_id(&auth_session, &state.project_repo, &project_id).await
+    {
+        return Ok(value);
+    }
+    state.webhooks.check_url(&req.url).await?;
+
+    let secret = req.secret.unwrap_or_else(|| nanoid!(32));
+    let webhook = crate::db::model::outgoing_webhook::OutgoingWebhook {
+        id: None,
+        project: project_id,
+        url: req.url,
+        secret: secret.clone(),
+        events: req.events.into_iter().map(webhook_event_api_to_db).collect(),
+        format: webhook_format_api_to_db(req.format),
+        active: true,
+        created_at: Datetime(Utc::now()),
+    };
+    let webhook = state.hook_repo.insert_webhook(&webhook).await?;
+    let webhook = OutgoingWebhook {
+        secret: Some(secret),
+        ..webhook_db_to_api(webhook)
+    };
+    Ok((StatusCode::OK, Json(webhook)).into_response())
+}
+
+pub async fn get_webhook(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((project_id, webhook_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) =
+        authorize_against_project_id(auth_session, &state.project_repo, &project_id).await
+    {
+        return Ok(value);
+    }
+
+    let webhook = query_project_webhook(state, &project_id, &webhook_id).await?;
+    Ok((StatusCode::OK, Json(webhook_db_to_api(webhook))).into_response())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PatchWebhookRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<WebhookEvent>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<WebhookFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+pub async fn patch_webhook(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((project_id, webhook_id)): Path<(String, String)>,
+    Json(req): Json<PatchWebhookRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) =
+        authorize_admin_against_project_id(&auth_session, &state.project_repo, &project_id).await
+    {
+        return Ok(value);
+    }
+
+    let mut webhook = query_project_webhook(state, &project_id, &webhook_id).await?;
+    if let Some(url) = req.url {
+        state.webhooks.check_url(&url).await?;
+        webhook.url = url;
+    }
+    if let Some(events) = req.events {
+        webhook.events = events.into_iter().map(webhook_event_api_to_db).collect();
+    }
+    if let Some(format) = req.format {
+        webhook.format = webhook_format_api_to_db(format);
+    }
+    webhook.active = req.active.unwrap_or(webhook.active);
+    webhook.secret = req.secret.unwrap_or(webhook.secret);
+    let webhook = state.hook_repo.update_webhook(&webhook_id, &webhook).await?;
+    Ok((StatusCode::OK, Json(webhook_db_to_api(webhook))).into_response())
+}
+
+pub async fn delete_webhook(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((project_id, webhook_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) =
+        authorize_admin_against_project_id(&auth_session, &state.project_repo, &project_id).await
+    {
+        return Ok(value);
+    }
+
+    query_project_webhook(state, &project_id, &webhook_id).await?;
+    let webhook = state.hook_repo.delete_webhook(&webhook_id).await?;
+    Ok((StatusCode::OK, Json(webhook_db_to_api(webhook))).into_response())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GetWebhookDeliveriesQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetWebhookDeliveriesResponse {
+    pub deliveries: Vec<WebhookDelivery>,
+}
+
+/// Latest deliveries of the webhook, newest first
+pub async fn get_webhook_deliveries(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((project_id, webhook_id)): Path<(String, String)>,
+    Query(query): Query<GetWebhookDeliveriesQuery>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) =
+        authorize_against_project_id(auth_session, &state.project_repo, &project_id).await
+    {
+        return Ok(value);
+    }
+
+    query_project_webhook(state, &project_id, &webhook_id).await?;
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let deliveries = state
+        .hook_repo
+        .query_deliveries_by_webhook(&webhook_id, limit)
+        .await?
+        .into_iter()
+        .filter_map(webhook_delivery_db_to_api)
+        .collect();
+    Ok((StatusCode::OK, Json(GetWebhookDeliveriesResponse { deliveries })).into_response())
+}
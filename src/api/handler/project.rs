@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use axum::{
     extract::{Path, State},
@@ -10,21 +10,34 @@ use axum::{
 use axum_login::{AuthSession, AuthUser};
 use nanoid::nanoid;
 use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
 use tokio::sync::Mutex;
 
 use crate::api::handler::{agenda, requirement};
 use crate::{
     api::{
         app::AppState,
-        model::{pr::PullRequest, project::Project, status::StatusPool, user::User},
+        model::{
+            pr::PullRequest,
+            project::{CustomFieldDef, Label, Project},
+            status::{Status, StatusPool},
+            user::User,
+        },
+    },
+    usecase::{
+        custom_field::prepare_definitions,
+        invitation_token::{gen_token, InvitationInfo},
+        notification::{notify_invitation, notify_invitation_answer},
+        status_pool::{finish_status_migration, migrate_status_pool},
+        util::auth_backend::AuthBackend,
     },
-    usecase::{invitation_token::InvitationInfo, util::auth_backend::AuthBackend},
 };
 
 use super::{
-    draft, task::IoErrorWrapper, task_link, task_list, util::{
+    board, draft, outgoing_webhook::{self, dispatch_member_joined}, project_feed, task::IoErrorWrapper, task_link, task_list, template, time_entry, util::{
         authorize_admin_against_project_id, authorize_against_project_id,
-        authorize_against_user_id, project_api_to_db, project_db_to_api, user_db_to_api,
+        authorize_against_user_id, project_api_to_db, project_db_to_api, status_key_api_to_db,
+        user_db_to_api,
     }
 };
 
@@ -35,6 +48,11 @@ pub fn router() -> axum::Router<Arc<Mutex<AppState>>> {
         .merge(task_link::project_router())
         .merge(task_list::project_router())
         .merge(draft::project_router())
+        .merge(board::project_router())
+        .merge(time_entry::project_router())
+        .merge(template::project_router())
+        .merge(project_feed::project_router())
+        .merge(outgoing_webhook::project_router())
         .route("/", get(get_project_info).patch(patch_project))
         .route("/prs", get(get_all_prs))
         .route("/users", get(get_users_for_project));
@@ -48,6 +66,7 @@ pub fn invitation_router() -> axum::Router<Arc<Mutex<AppState>>> {
     Router::new()
         .route("/:token_id", get(get_token_info))
         .route("/accept", post(accept_invitation))
+        .route("/decline", post(decline_invitation))
         .route("/generate", post(gen_invitation_token))
 }
 
@@ -255,6 +274,8 @@ pub async fn create_project(
         avatar: req.avatar,
         status_pool: req.status_pool,
         github: None,
+        labels: vec![],
+        custom_fields: vec![],
     });
     let state = state.lock().await;
 
@@ -297,12 +318,22 @@ pub struct PatchProjectRequest {
     pub avatar: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub status_pool: Option<StatusPool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<Label>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_fields: Option<Vec<CustomFieldDef>>,
+    /// Replacement for every incomplete status removed from `status_pool`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub status_mapping: HashMap<String, Status>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PatchProjectResponse {
     #[serde(flatten)]
     pub project: Project,
+    /// Number of tasks moved out of each removed status
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub migrated_tasks: HashMap<String, usize>,
 }
 
 pub async fn patch_project(
@@ -312,6 +343,7 @@ pub async fn patch_project(
     Json(req): Json<PatchProjectRequest>,
 ) -> impl IntoResponse {
     let state = state.lock().await;
+    let actor_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
     if let Some(value) =
         authorize_against_project_id(auth_session, &state.project_repo, &project_id).await
     {
@@ -324,6 +356,8 @@ pub async fn patch_project(
         Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
         Ok(p) => p,
     };
+    let original_status_pool = original_db_project.status_pool.clone();
+    let pool_changed = req.status_pool.is_some();
 
     let original_api_project = project_db_to_api(original_db_project);
 
@@ -339,9 +373,53 @@ pub async fn patch_project(
         avatar: req.avatar.or(original_api_project.avatar),
         status_pool: req.status_pool.or(original_api_project.status_pool),
         github: None,
+        labels: req.labels.unwrap_or(original_api_project.labels),
+        custom_fields: req.custom_fields.unwrap_or(original_api_project.custom_fields),
     };
 
-    let new_db_project = project_api_to_db(new_api_project);
+    let mut new_db_project = project_api_to_db(new_api_project);
+    if let Err(err) = prepare_definitions(&mut new_db_project, || nanoid!()) {
+        return IoErrorWrapper::from(err).into_response();
+    }
+
+    let mut migrated_tasks = HashMap::new();
+    if pool_changed {
+        let task_lists = match state.project_repo.query_task_list_by_id(&project_id).await {
+            Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            Ok(task_lists) => task_lists,
+        };
+        let mapping = req
+            .status_mapping
+            .into_iter()
+            .map(|(removed, target)| (removed, status_key_api_to_db(target)))
+            .collect();
+        let migration = match migrate_status_pool(
+            &state.task_repo,
+            Thing::from(("project", project_id.as_str())),
+            &task_lists,
+            &original_status_pool,
+            &new_db_project.status_pool,
+            &mapping,
+            &actor_id,
+        )
+        .await
+        {
+            Err(err) => return IoErrorWrapper::from(err).into_response(),
+            Ok(migration) => migration,
+        };
+        let finished = finish_status_migration(
+            &state.task_repo,
+            &state.project_repo,
+            &state.user_repo,
+            &state.notif_repo,
+            &migration.completed,
+        )
+        .await;
+        if let Err(err) = finished {
+            return IoErrorWrapper::from(err).into_response();
+        }
+        migrated_tasks = migration.counts;
+    }
 
     let updated_db_project = state
         .project_repo
@@ -357,7 +435,14 @@ pub async fn patch_project(
 
     match updated_api_project {
         None => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-        Some(p) => (StatusCode::OK, Json(PatchProjectResponse { project: p })).into_response(),
+        Some(p) => (
+            StatusCode::OK,
+            Json(PatchProjectResponse {
+                project: p,
+                migrated_tasks,
+            }),
+        )
+            .into_response(),
     }
 }
 
@@ -391,16 +476,27 @@ pub async fn gen_invitation_token(
         return value;
     }
 
-    let invitation_token = nanoid!();
+    let invitation_token = gen_token();
+    let invitation_info = InvitationInfo {
+        inviter: req.invitor_id,
+        invitee: req.invitee_id,
+        project: req.project_id,
+    };
+    if let Err(err) = notify_invitation(
+        project_repo,
+        &state.user_repo,
+        &state.notif_repo,
+        &invitation_token,
+        &invitation_info,
+    )
+    .await
+    {
+        tracing::warn!("Notifying about the invitation failed: {err}");
+    }
 
-    invitation_token_repo.tokens.insert(
-        invitation_token.clone(),
-        InvitationInfo {
-            inviter: req.invitor_id,
-            invitee: req.invitee_id,
-            project: req.project_id,
-        },
-    );
+    invitation_token_repo
+        .tokens
+        .insert(invitation_token.clone(), invitation_info);
 
     (
         StatusCode::OK,
@@ -435,6 +531,13 @@ pub async fn accept_invitation(
         return value;
     }
 
+    // the same invitation may have been sent more than once
+    let tokens: Vec<_> = invitation_token_repo
+        .tokens
+        .iter()
+        .filter(|(_, v)| **v == invitation_info)
+        .map(|(token, _)| token.clone())
+        .collect();
     invitation_token_repo
         .tokens
         .retain(|_, v| v.clone() != invitation_info);
@@ -444,10 +547,67 @@ pub async fn accept_invitation(
         .set_user_for_project(&invitation_info.invitee, &invitation_info.project, false)
         .await;
 
-    match result {
-        Ok(_) => StatusCode::OK.into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    if result.is_err() {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    let joined =
+        dispatch_member_joined(&state, &invitation_info.project, &invitation_info.invitee).await;
+    if let Err(err) = joined {
+        tracing::warn!("Sending member_joined failed: {err}");
+    }
+    if let Err(err) = notify_invitation_answer(
+        &state.project_repo,
+        &state.user_repo,
+        &state.notif_repo,
+        &tokens,
+        &invitation_info,
+        true,
+    )
+    .await
+    {
+        tracing::warn!("Notifying about the accepted invitation failed: {err}");
+    }
+    StatusCode::OK.into_response()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeclineInvitationRequest {
+    invitation_token: String,
+}
+
+/// Drops the invitation and lets the inviter know
+pub async fn decline_invitation(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Json(req): Json<DeclineInvitationRequest>,
+) -> impl IntoResponse {
+    let state = state.lock().await;
+    let mut invitation_token_repo = state.invitation_token_repo.lock().await;
+
+    let invitation_info = match invitation_token_repo.tokens.get(&req.invitation_token) {
+        None => return StatusCode::NOT_FOUND.into_response(),
+        Some(info) => info.clone(),
+    };
+
+    if let Some(value) = authorize_against_user_id(auth_session, &invitation_info.invitee) {
+        return value;
+    }
+
+    invitation_token_repo.tokens.remove(&req.invitation_token);
+
+    if let Err(err) = notify_invitation_answer(
+        &state.project_repo,
+        &state.user_repo,
+        &state.notif_repo,
+        &[req.invitation_token],
+        &invitation_info,
+        false,
+    )
+    .await
+    {
+        tracing::warn!("Notifying about the declined invitation failed: {err}");
     }
+    StatusCode::OK.into_response()
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -0,0 +1,230 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    io,
+    sync::{Arc, Mutex as StdMutex},
+};
+
+use axum::{
+    extract::{Path, State},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    routing::get,
+    Router,
+};
+use axum_login::{AuthSession, AuthUser};
+use futures::{stream, StreamExt};
+use tokio::sync::{
+    broadcast::{self, error::RecvError, Receiver},
+    Mutex,
+};
+
+use crate::{
+    api::{
+        app::AppState,
+        model::{
+            change::{ProjectChange, ProjectChangeEvent},
+            outgoing_webhook::WebhookEvent,
+        },
+    },
+    db::{
+        model::task::Task,
+        repository::{task::TaskEvent, utils::DbModelId},
+    },
+    usecase::{recurrence::spawn_next_occurrence, util::auth_backend::AuthBackend},
+};
+
+use super::{
+    outgoing_webhook::dispatch_task_event,
+    task::IoErrorWrapper,
+    util::{authorize_against_project_id, task_db_to_api},
+};
+
+/// Fans project changes out to the members watching the project
+#[derive(Clone, Default)]
+pub struct ProjectFeed {
+    channels: Arc<StdMutex<HashMap<DbModelId, broadcast::Sender<ProjectChangeEvent>>>>,
+}
+
+impl ProjectFeed {
+    pub fn subscribe(&self, project_id: &str) -> broadcast::Receiver<ProjectChangeEvent> {
+        let mut channels = self.channels.lock().unwrap();
+        channels
+            .entry(project_id.to_owned())
+            .or_insert_with(|| broadcast::channel(256).0)
+            .subscribe()
+    }
+
+    pub fn publish(&self, project_id: &str, actor: &str, change: ProjectChange) {
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(sender) = channels.get(project_id) {
+            let event = ProjectChangeEvent {
+                actor: actor.to_owned(),
+                change,
+            };
+            // nobody watches the project any more
+            if sender.send(event).is_err() {
+                channels.remove(project_id);
+            }
+        }
+    }
+}
+
+/// Publishes a change to the project owning the task list; personal lists have no feed
+pub async fn publish_task_list_change(
+    state: &AppState,
+    task_list_id: &str,
+    actor: &str,
+    change: ProjectChange,
+) {
+    if let Ok(source) = state.task_repo.query_task_list_source(task_list_id).await {
+        if source.tb == "project" {
+            state.project_feed.publish(&source.id.to_string(), actor, change);
+        }
+    }
+}
+
+/// Publishes a change to the project owning the task's list
+pub async fn publish_task_change(
+    state: &AppState,
+    task_id: &str,
+    actor: &str,
+    change: ProjectChange,
+) {
+    if let Ok(task_list_id) = state.task_repo.query_task_list_id_by_task(task_id).await {
+        publish_task_list_change(state, &task_list_id, actor, change).await;
+    }
+}
+
+/// Reports the task writes the server makes on its own, see `TaskEvent`, to the project
+/// feeds and webhooks. Recurring tasks they completed get their next occurrence.
+pub async fn relay_task_events(state: Arc<Mutex<AppState>>, mut receiver: Receiver<TaskEvent>) {
+    loop {
+        let event = match receiver.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::warn!("Skipped {skipped} task events");
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+        let ref state = state.lock().await;
+        if let Err(err) = relay_task_event(state, event).await {
+            tracing::warn!("Relaying a task event failed: {err}");
+        }
+    }
+}
+
+async fn relay_task_event(state: &AppState, event: TaskEvent) -> Result<(), io::Error> {
+    let (TaskEvent::Created(task_id) | TaskEvent::Updated(task_id)) = &event;
+    let task = state.task_repo.query_task_by_id(task_id).await?;
+    let task_list_id = state.task_repo.query_task_list_id_by_task(task_id).await?;
+    let change = match event {
+        TaskEvent::Created(_) => {
+            let webhook = WebhookEvent::TaskCreated;
+            if let Err(err) = dispatch_task_event(state, &task_list_id, webhook, None, &task).await
+            {
+                tracing::warn!("Sending webhooks failed: {err}");
+            }
+            ProjectChange::TaskCreated {
+                task_list_id: task_list_id.clone(),
+                task: task_db_to_api(task),
+            }
+        }
+        TaskEvent::Updated(_) => {
+            // updates are completion flips, and spawning is a no-op unless one completed
+            let reopened = Task {
+                complete: false,
+                ..task.clone()
+            };
+            spawn_next_occurrence(
+                &state.task_repo,
+                &state.project_repo,
+                &state.user_repo,
+                &state.notif_repo,
+                task_id,
+                &reopened,
+                &task,
+            )
+            .await?;
+            ProjectChange::TaskUpdated {
+                task_list_id: task_list_id.clone(),
+                task: task_db_to_api(task),
+            }
+        }
+    };
+    publish_task_list_change(state, &task_list_id, "", change).await;
+    Ok(())
+}
+
+/// Publishes a change to the project owning the agenda; personal agendas have no feed
+pub async fn publish_agenda_change(
+    state: &AppState,
+    agenda_id: &str,
+    actor: &str,
+    change: ProjectChange,
+) {
+    if let Ok(project_id) = state.agenda_repo.query_agenda_source_by_id(agenda_id).await {
+        state.project_feed.publish(&project_id, actor, change);
+    }
+}
+
+pub fn project_router() -> Router<Arc<Mutex<AppState>>> {
+    Router::new().route("/changes", get(stream_project_changes))
+}
+
+async fn is_member(state: &Arc<Mutex<AppState>>, project_id: &str, user_id: &str) -> bool {
+    let state = state.lock().await;
+    let admin = state.project_repo.query_admin_by_id(project_id).await;
+    let members = state.project_repo.query_members_by_id(project_id).await;
+    match (admin, members) {
+        (Ok(admin), Ok(members)) => {
+            admin.id() == user_id || members.iter().any(|member| member.id() == user_id)
+        }
+        _ => false,
+    }
+}
+
+/// Server-sent `change` events for everything members do in the project. The stream
+/// ends once the subscriber leaves the project.
+pub async fn stream_project_changes(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(project_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let user_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+    let receiver = {
+        let ref state = state.lock().await;
+        if let Some(value) =
+            authorize_against_project_id(auth_session, &state.project_repo, &project_id).await
+        {
+            return Ok(value);
+        }
+        state.project_feed.subscribe(&project_id)
+    };
+
+    let watcher = (receiver, state, project_id, user_id);
+    let events = stream::unfold(watcher, |(mut receiver, state, project_id, user_id)| {
+        async move {
+            loop {
+                let change = match receiver.recv().await {
+                    Ok(change) => change,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                };
+                if !is_member(&state, &project_id, &user_id).await {
+                    return None;
+                }
+                let Ok(event) = Event::default().event("change").json_data(change) else {
+                    continue;
+                };
+                return Some((event, (receiver, state, project_id, user_id)));
+            }
+        }
+    })
+    .map(Ok::<_, Infallible>);
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()).into_response())
+}
@@ -7,14 +7,21 @@ use axum::{
     routing::get,
     Json, Router,
 };
-use axum_login::AuthSession;
+use axum_login::{AuthSession, AuthUser};
 use futures::future::try_join_all;
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 
 use crate::{
-    api::{app::AppState, model::requirement::Requirement},
-    usecase::util::auth_backend::AuthBackend,
+    api::{
+        app::AppState,
+        model::{change::ProjectChange, requirement::Requirement},
+    },
+    db::repository::utils::get_str_id,
+    usecase::{
+        mention::{notify_new_mentions, MentionEdit, MentionTarget},
+        util::auth_backend::AuthBackend,
+    },
 };
 
 use super::util::{authorize_against_project_id, requ_db_to_api};
@@ -128,6 +135,7 @@ pub async fn create_requirement_for_project(
     let ref state = state.lock().await;
     let ref project_repo = state.project_repo;
     let ref requ_repo = state.requ_repo;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
     if let Some(value) = authorize_against_project_id(auth_session, project_repo, &project_id).await
     {
         return value;
@@ -142,7 +150,16 @@ pub async fn create_requirement_for_project(
             return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
     };
-    (StatusCode::OK, Json(requ_db_to_api(requ))).into_response()
+    // the requirement is saved already
+    if let Err(err) = notify_requ_mentions(state, &project_id, &author_id, "", &requ).await {
+        tracing::warn!("Notifying mentioned users failed: {err}");
+    }
+    let requirement = requ_db_to_api(requ);
+    let change = ProjectChange::RequirementCreated {
+        requirement: requirement.clone(),
+    };
+    state.project_feed.publish(&project_id, &author_id, change);
+    (StatusCode::OK, Json(requirement)).into_response()
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -168,6 +185,7 @@ pub async fn patch_requirement(
     let ref state = state.lock().await;
     let ref project_repo = state.project_repo;
     let ref requ_repo = state.requ_repo;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
     if let Some(value) = authorize_against_project_id(auth_session, project_repo, &project_id).await
     {
         return value;
@@ -180,6 +198,8 @@ pub async fn patch_requirement(
         }
     };
 
+    let old_description = requ.description.clone();
+
     if let (Some(name), Some(content)) = (req.name, req.content) {
         requ.name = name;
         requ.description = content;
@@ -192,7 +212,17 @@ pub async fn patch_requirement(
             return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
     };
-    (StatusCode::OK, Json(requ_db_to_api(requ))).into_response()
+    if let Err(err) =
+        notify_requ_mentions(state, &project_id, &author_id, &old_description, &requ).await
+    {
+        tracing::warn!("Notifying mentioned users failed: {err}");
+    }
+    let requirement = requ_db_to_api(requ);
+    let change = ProjectChange::RequirementUpdated {
+        requirement: requirement.clone(),
+    };
+    state.project_feed.publish(&project_id, &author_id, change);
+    (StatusCode::OK, Json(requirement)).into_response()
 }
 
 pub async fn delete_requirement(
@@ -203,6 +233,7 @@ pub async fn delete_requirement(
     let ref state = state.lock().await;
     let ref project_repo = state.project_repo;
     let ref requ_repo = state.requ_repo;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
     if let Some(value) = authorize_against_project_id(auth_session, project_repo, &project_id).await
     {
         return value;
@@ -214,5 +245,33 @@ pub async fn delete_requirement(
             return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
     };
+    let change = ProjectChange::RequirementDeleted { requirement_id };
+    state.project_feed.publish(&project_id, &author_id, change);
     (StatusCode::OK, Json(requ_db_to_api(requ))).into_response()
 }
+
+async fn notify_requ_mentions(
+    state: &AppState,
+    project_id: &str,
+    author_id: &str,
+    old_description: &str,
+    requ: &crate::db::model::requirement::Requirement,
+) -> Result<(), std::io::Error> {
+    notify_new_mentions(
+        &state.project_repo,
+        &state.notif_repo,
+        project_id,
+        MentionEdit {
+            author_id,
+            old_text: old_description,
+            new_text: &requ.description,
+        },
+        MentionTarget {
+            subject: "Requirement",
+            name: &requ.name,
+            about_id: &get_str_id(&requ.id),
+            about_table: "requirement",
+        },
+    )
+    .await
+}
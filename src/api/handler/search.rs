@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use axum_login::{AuthSession, AuthUser};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    api::{app::AppState, model::search::SearchResult},
+    usecase::{
+        search::{search_for_user, SearchRepos},
+        util::auth_backend::AuthBackend,
+    },
+};
+
+use super::task::IoErrorWrapper;
+
+pub fn router() -> Router<Arc<Mutex<AppState>>> {
+    Router::new().route("/", get(search))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SearchRequest {
+    pub q: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SearchResponse {
+    pub results: Vec<SearchResult>,
+}
+
+pub async fn search(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Query(req): Query<SearchRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let user_id = match auth_session.user {
+        None => return Ok(StatusCode::UNAUTHORIZED.into_response()),
+        Some(user) => user.id(),
+    };
+    if req.q.trim().is_empty() {
+        return Ok((StatusCode::BAD_REQUEST, "Search keyword is empty").into_response());
+    }
+
+    let ref state = state.lock().await;
+    let mut results = search_for_user(
+        SearchRepos {
+            user_repo: &state.user_repo,
+            project_repo: &state.project_repo,
+            task_repo: &state.task_repo,
+            agenda_repo: &state.agenda_repo,
+            requ_repo: &state.requ_repo,
+            draft_repo: &state.draft_repo,
+        },
+        &user_id,
+        &req.q,
+    )
+    .await?;
+    if let Some(limit) = req.limit {
+        results.truncate(limit);
+    }
+
+    Ok((StatusCode::OK, Json(SearchResponse { results })).into_response())
+}
@@ -2,12 +2,12 @@ use std::{io, sync::Arc};
 
 use axum::{
     body::Body,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{Response, StatusCode},
     response::IntoResponse,
     Json,
 };
-use axum_login::AuthSession;
+use axum_login::{AuthSession, AuthUser};
 use chrono::{DateTime, Utc};
 use futures::future::try_join_all;
 use serde::{Deserialize, Serialize};
@@ -18,19 +18,37 @@ use tokio::sync::Mutex;
 use crate::{
     api::{
         app::AppState,
-        model::{pr::PullRequest, status::Status, task::Task, util::Id},
+        model::{
+            change::ProjectChange,
+            outgoing_webhook::WebhookEvent,
+            pr::PullRequest,
+            status::Status,
+            task::{CustomFieldValue, Priority, Task, TaskChange, TaskNode},
+            util::Id,
+        },
     },
-    db::repository::utils::unwrap_thing,
+    db::repository::utils::{get_str_id, unwrap_thing},
     usecase::{
+        board::check_wip_limits,
+        custom_field::{query_project_of_task_list, validate_task_fields, TaskFilter},
+        mention::{notify_task_mentions, MentionEdit},
         notification::{assign_task_to_user, deassign_task_for_user},
+        recurrence::{parse_rrule, spawn_next_occurrence},
         task_stream::{check_task_switch_complete, refresh_task_status_entry, TaskSwitchable},
+        task_history::{record_task_change, SOURCE_USER},
+        task_order::move_task,
+        task_tree::{delete_subtree, move_subtree, refresh_auto_complete},
         util::auth_backend::AuthBackend,
+        workflow::{check_transition, query_workflow_of_task, status_key},
     },
 };
 
+use super::outgoing_webhook::dispatch_task_event;
+use super::project_feed::publish_task_list_change;
 use super::util::{
     authorize_against_project_id, authorize_against_task_list_id, authorize_against_user_id,
-    task_db_to_api, task_db_to_api_assigned,
+    custom_field_values_api_to_db, priority_api_to_db, task_change_db_to_api, task_db_to_api,
+    task_db_to_api_assigned,
 };
 
 pub struct IoErrorWrapper(io::Error);
@@ -40,6 +58,7 @@ impl IntoResponse for IoErrorWrapper {
         let status_code = match self.0.kind() {
             io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
             io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+            io::ErrorKind::InvalidInput => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
@@ -65,23 +84,65 @@ pub struct GetTasksForUser {
     pub tasks: Vec<TaskWithListId>,
 }
 
+/// Filters accepted by the task listing endpoints
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TaskFilterQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// Comma separated label ids; tasks with any of them match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<String>,
+    /// Custom field id the task must have a value for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// Value `field` must match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Only incomplete tasks past their deadline, or only the others with `false`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overdue: Option<bool>,
+}
+
+impl TaskFilterQuery {
+    fn into_filter(self) -> TaskFilter {
+        TaskFilter {
+            priority: self.priority.map(priority_api_to_db),
+            labels: self
+                .labels
+                .iter()
+                .flat_map(|labels| labels.split(','))
+                .filter(|label| !label.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            field: self.field.map(|field| (field, self.value)),
+            overdue: self.overdue,
+        }
+    }
+}
+
 pub async fn get_all_tasks_for_user(
     auth_session: AuthSession<AuthBackend>,
     State(state): State<Arc<Mutex<AppState>>>,
     Path(user_id): Path<String>,
+    Query(query): Query<TaskFilterQuery>,
 ) -> Result<impl IntoResponse, IoErrorWrapper> {
     let ref state = state.lock().await;
     if let Some(value) = authorize_against_user_id(auth_session, &user_id) {
         return Ok(value);
     }
 
+    let filter = query.into_filter();
     let task_lists = state.user_repo.query_task_list_by_id_without_from_project(&user_id).await?;
     let mut tasks = vec![];
     for list in task_lists {
         let list_tasks = state.task_repo.query_all_tasks_of_task_list(&list).await?;
         for task in list_tasks {
+            let task = state.task_repo.query_task_by_id(&task).await?;
+            if !filter.matches(&task) {
+                continue;
+            }
             tasks.push(TaskWithListId {
-                task: task_db_to_api(state.task_repo.query_task_by_id(&task).await?),
+                task: task_db_to_api(task),
                 task_list_id: list.clone(),
             });
         }
@@ -99,6 +160,7 @@ pub async fn get_all_tasks_for_project(
     auth_session: AuthSession<AuthBackend>,
     State(state): State<Arc<Mutex<AppState>>>,
     Path(project_id): Path<String>,
+    Query(query): Query<TaskFilterQuery>,
 ) -> Result<impl IntoResponse, IoErrorWrapper> {
     let ref state = state.lock().await;
     let ref task_repo = state.task_repo;
@@ -108,6 +170,7 @@ pub async fn get_all_tasks_for_project(
         return Ok(value);
     }
 
+    let filter = query.into_filter();
     let task_lists = state
         .project_repo
         .query_task_list_by_id(&project_id)
@@ -116,8 +179,12 @@ pub async fn get_all_tasks_for_project(
     for list in task_lists {
         let list_tasks = task_repo.query_all_tasks_of_task_list(&list).await?;
         for task in list_tasks {
+            let task = task_repo.query_task_by_id(&task).await?;
+            if !filter.matches(&task) {
+                continue;
+            }
             tasks.push(TaskWithListId {
-                task: task_db_to_api(state.task_repo.query_task_by_id(&task).await?),
+                task: task_db_to_api(task),
                 task_list_id: list.clone(),
             });
         }
@@ -135,6 +202,7 @@ pub async fn get_tasks_for_list(
     auth_session: AuthSession<AuthBackend>,
     State(state): State<Arc<Mutex<AppState>>>,
     Path(task_list_id): Path<String>,
+    Query(query): Query<TaskFilterQuery>,
 ) -> impl IntoResponse {
     let ref state = state.lock().await;
     if let Some(value) = authorize_against_task_list_id(
@@ -148,6 +216,7 @@ pub async fn get_tasks_for_list(
         return value;
     }
 
+    let filter = query.into_filter();
     let tasks = state
         .task_repo
         .query_all_tasks_of_task_list(&task_list_id)
@@ -175,7 +244,11 @@ pub async fn get_tasks_for_list(
     (
         StatusCode::OK,
         Json(GetTasksForList {
-            tasks: tasks.into_iter().map(|task| task_db_to_api(task)).collect(),
+            tasks: tasks
+                .into_iter()
+                .filter(|task| filter.matches(task))
+                .map(|task| task_db_to_api(task))
+                .collect(),
         }),
     )
         .into_response()
@@ -190,12 +263,30 @@ pub struct CreateTaskForListRequest {
     pub deadline: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pr: Option<PullRequest>,
+    #[serde(default)]
+    pub auto_complete: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// Label ids of the project
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub custom_fields: Vec<CustomFieldValue>,
+    /// Estimated effort in minutes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<u32>,
+    /// RRULE; completing the task spawns its next occurrence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreateTaskForListResponse {
     #[serde(flatten)]
     pub task: Task,
+    /// Exceeded WIP limits that were not enforced
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 pub async fn create_task_for_list(
@@ -205,6 +296,7 @@ pub async fn create_task_for_list(
     Json(req): Json<CreateTaskForListRequest>,
 ) -> Result<impl IntoResponse, IoErrorWrapper> {
     let ref state = state.lock().await;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
     if let Some(value) = authorize_against_task_list_id(
         auth_session,
         &state.project_repo,
@@ -216,8 +308,48 @@ pub async fn create_task_for_list(
         return Ok(value);
     }
 
+    let (task, warnings) = insert_task_from_request(&state, &task_list_id, &author_id, req).await?;
+    let task = task_db_to_api(task);
+    let change = ProjectChange::TaskCreated {
+        task_list_id: task_list_id.clone(),
+        task: task.clone(),
+    };
+    publish_task_list_change(state, &task_list_id, &author_id, change).await;
+
+    Ok((StatusCode::OK, Json(CreateTaskForListResponse { task, warnings })).into_response())
+}
+
+/// Creates the task with its assignees and mention notifications.
+/// Also returns the WIP limits it exceeds when they are not enforced.
+async fn insert_task_from_request(
+    state: &AppState,
+    task_list_id: &str,
+    author_id: &str,
+    req: CreateTaskForListRequest,
+) -> Result<(crate::db::model::task::Task, Vec<String>), io::Error> {
     let assignees: Vec<_> = req.assignees.clone().into_iter().map(|id| id.id).collect();
 
+    let custom_fields = custom_field_values_api_to_db(req.custom_fields.clone());
+    let project =
+        query_project_of_task_list(&state.task_repo, &state.project_repo, task_list_id).await?;
+    validate_task_fields(project.as_ref(), &req.labels, &custom_fields)?;
+
+    let warnings = match &req.status {
+        Status::Incomplete { id } => {
+            check_wip_limits(
+                &state.task_repo,
+                &state.project_repo,
+                &state.user_repo,
+                task_list_id,
+                None,
+                id,
+                &assignees,
+            )
+            .await?
+        }
+        Status::Complete => vec![],
+    };
+
     let mut task = crate::db::model::task::Task {
         id: None,
         name: req.name.clone(),
@@ -237,6 +369,17 @@ pub async fn create_task_for_list(
         pr_assigned: false,
         pr_number: 0,
         pr: crate::api::model::pr::PullRequest::default(),
+        rank: 0,
+        auto_complete: req.auto_complete,
+        parent: None,
+        progress: None,
+        priority: req.priority.clone().map(priority_api_to_db),
+        labels: req.labels.clone(),
+        custom_fields,
+        estimate: req.estimate,
+        recurrence: req.recurrence.as_deref().map(parse_rrule).transpose()?,
+        series: None,
+        occurrence: 0,
     };
 
     match req.pr {
@@ -253,7 +396,7 @@ pub async fn create_task_for_list(
 
     let mut task = state
         .task_repo
-        .insert_task_for_task_list(&task, &task_list_id)
+        .insert_task_for_task_list(&task, task_list_id)
         .await?;
 
     let task_id = unwrap_thing(task.id.clone().unwrap());
@@ -261,15 +404,36 @@ pub async fn create_task_for_list(
         let _ = assign_task_to_user(&state.task_repo, &state.notif_repo, &task_id, &id).await?;
     }
 
-    task.assignees = Some(assignees);
-
-    Ok((
-        StatusCode::OK,
-        Json(CreateTaskForListResponse {
-            task: task_db_to_api(task),
-        }),
+    if let Err(err) = notify_task_mentions(
+        &state.project_repo,
+        &state.task_repo,
+        &state.notif_repo,
+        &task_id,
+        "Task",
+        MentionEdit {
+            author_id,
+            old_text: "",
+            new_text: &task.description,
+        },
     )
-        .into_response())
+    .await
+    {
+        tracing::warn!("Notifying mentioned users failed: {err}");
+    }
+
+    task.assignees = Some(assignees);
+    let mut events = vec![WebhookEvent::TaskCreated];
+    if task.pr_assigned {
+        events.push(WebhookEvent::PrLinked);
+    }
+    // the task is saved already
+    for event in events {
+        let actor = Some(author_id);
+        if let Err(err) = dispatch_task_event(state, task_list_id, event, actor, &task).await {
+            tracing::warn!("Sending webhooks failed: {err}");
+        }
+    }
+    Ok((task, warnings))
 }
 
 pub async fn delete_task_from_list(
@@ -278,6 +442,7 @@ pub async fn delete_task_from_list(
     Path((task_list_id, task_id)): Path<(String, String)>,
 ) -> impl IntoResponse {
     let state = state.lock().await;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
     if let Some(value) = authorize_against_task_list_id(
         auth_session,
         &state.project_repo,
@@ -289,8 +454,17 @@ pub async fn delete_task_from_list(
         return value;
     };
 
-    match state.task_repo.delete_task(&task_id).await {
-        Ok(_) => (StatusCode::OK, Json("")).into_response(),
+    match delete_subtree(&task_id, &state.task_repo, &state.notif_repo).await {
+        Ok(deleted) => {
+            for task_id in deleted {
+                let change = ProjectChange::TaskDeleted {
+                    task_list_id: task_list_id.clone(),
+                    task_id,
+                };
+                publish_task_list_change(&state, &task_list_id, &author_id, change).await;
+            }
+            (StatusCode::OK, Json("")).into_response()
+        }
         Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, Json(err.to_string())).into_response(),
     }
 }
@@ -309,12 +483,52 @@ pub struct PatchTaskRequest {
     pub deadline: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pr: Option<PullRequest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_complete: Option<bool>,
+    /// `null` clears the priority
+    #[serde(
+        default,
+        deserialize_with = "deserialize_nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub priority: Option<Option<Priority>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<String>>,
+    /// Replaces all custom field values of the task
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_fields: Option<Vec<CustomFieldValue>>,
+    /// Estimated effort in minutes, `null` clears it
+    #[serde(
+        default,
+        deserialize_with = "deserialize_nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub estimate: Option<Option<u32>>,
+    /// RRULE, `null` stops the recurrence
+    #[serde(
+        default,
+        deserialize_with = "deserialize_nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recurrence: Option<Option<String>>,
+}
+
+/// Tells an explicit `null` (`Some(None)`) apart from a missing field (`None`)
+fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PatchTaskResponse {
     #[serde(flatten)]
     pub task: Task,
+    /// Exceeded WIP limits that were not enforced
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 pub async fn patch_task(
@@ -324,6 +538,7 @@ pub async fn patch_task(
     Json(req): Json<PatchTaskRequest>,
 ) -> Result<impl IntoResponse, IoErrorWrapper> {
     let state = state.lock().await;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
     if let Some(value) = authorize_against_task_list_id(
         auth_session,
         &state.project_repo,
@@ -335,6 +550,28 @@ pub async fn patch_task(
         return Ok(value);
     };
 
+    let (new_task, warnings) =
+        update_task_from_request(&state, &task_list_id, &task_id, &author_id, req).await?;
+    let task = task_db_to_api(new_task);
+    let change = ProjectChange::TaskUpdated {
+        task_list_id: task_list_id.clone(),
+        task: task.clone(),
+    };
+    publish_task_list_change(&state, &task_list_id, &author_id, change).await;
+
+    Ok((StatusCode::OK, Json(PatchTaskResponse { task, warnings })).into_response())
+}
+
+/// Applies a patch to a task, enforcing workflow rules and WIP limits and running
+/// notifications, history and status propagation. Returns the saved task and WIP warnings.
+pub async fn update_task_from_request(
+    state: &AppState,
+    task_list_id: &str,
+    task_id: &str,
+    author_id: &str,
+    req: PatchTaskRequest,
+) -> Result<(crate::db::model::task::Task, Vec<String>), io::Error> {
+    let (task_list_id, task_id) = (task_list_id.to_owned(), task_id.to_owned());
     let task = state.task_repo.query_task_by_id(&task_id).await?;
 
     let mut new_task = crate::db::model::task::Task {
@@ -342,10 +579,35 @@ pub async fn patch_task(
         description: req.description.unwrap_or(task.description.clone()),
         ddl: req.deadline.map(|ddl| Datetime { 0: ddl }),
         complete: task.complete,
+        auto_complete: req.auto_complete.unwrap_or(task.auto_complete),
+        priority: match req.priority {
+            Some(priority) => priority.map(priority_api_to_db),
+            None => task.priority.clone(),
+        },
+        labels: req.labels.unwrap_or(task.labels.clone()),
+        custom_fields: req
+            .custom_fields
+            .map(custom_field_values_api_to_db)
+            .unwrap_or(task.custom_fields.clone()),
+        estimate: req.estimate.unwrap_or(task.estimate),
+        recurrence: match req.recurrence {
+            Some(rule) => rule.as_deref().map(parse_rrule).transpose()?,
+            None => task.recurrence.clone(),
+        },
         id: None,
         ..task.clone()
     };
+    if new_task.labels != task.labels || new_task.custom_fields != task.custom_fields {
+        let project =
+            query_project_of_task_list(&state.task_repo, &state.project_repo, &task_list_id)
+                .await?;
+        validate_task_fields(project.as_ref(), &new_task.labels, &new_task.custom_fields)?;
+    }
 
+    let requested_status = req.status.clone().map(|status| match status {
+        Status::Complete => "complete".to_owned(),
+        Status::Incomplete { id } => id,
+    });
     let mut switchable = TaskSwitchable::TrueAndFalse;
 
     if let Some(_) = req.status {
@@ -361,7 +623,7 @@ pub async fn patch_task(
             TaskSwitchable::True => (true, id),
             _ => (false, id),
         },
-        None => (task.complete, task.status),
+        None => (task.complete, task.status.clone()),
     };
 
     if let Some(_pr) = req.pr {
@@ -369,6 +631,43 @@ pub async fn patch_task(
         new_task.pr = _pr;
     }
 
+    if let Some(requested_status) = requested_status {
+        let (pool, is_admin) = query_workflow_of_task(
+            &state.task_repo,
+            &state.project_repo,
+            &state.user_repo,
+            &task_id,
+            &author_id,
+        )
+        .await?;
+        let mut preview = new_task.clone();
+        if let Some(assignees) = &req.assignees {
+            preview.assignees = Some(assignees.iter().map(|a| a.id.clone()).collect());
+        }
+        check_transition(&pool, &status_key(&task), &requested_status, &preview, is_admin)?;
+    }
+
+    let mut warnings = vec![];
+    let preview_assignees = match &req.assignees {
+        Some(assignees) => assignees.iter().map(|a| a.id.clone()).collect(),
+        None => task.assignees.clone().unwrap_or_default(),
+    };
+    if !new_task.complete
+        && (status_key(&new_task) != status_key(&task)
+            || Some(&preview_assignees) != task.assignees.as_ref())
+    {
+        warnings = check_wip_limits(
+            &state.task_repo,
+            &state.project_repo,
+            &state.user_repo,
+            &task_list_id,
+            Some(&task_id),
+            &new_task.status,
+            &preview_assignees,
+        )
+        .await?;
+    }
+
     let assignees = state.task_repo.query_assignees_of_task(&task_id).await?;
 
     if let Some(assignees_ref) = req.assignees {
@@ -387,18 +686,62 @@ pub async fn patch_task(
     }
 
     let new_task =  state.task_repo.update_task_by_id(&task_id, &new_task).await?;
+    record_task_change(&state.task_repo, &task_id, &task, &new_task, SOURCE_USER, Some(&author_id))
+        .await?;
 
-    if task.complete != new_task.complete {
-        let _ = refresh_task_status_entry(&task_id, &state.task_repo).await?;
+    if let Err(err) = notify_task_mentions(
+        &state.project_repo,
+        &state.task_repo,
+        &state.notif_repo,
+        &task_id,
+        "Task",
+        MentionEdit {
+            author_id: &author_id,
+            old_text: &task.description,
+            new_text: &new_task.description,
+        },
+    )
+    .await
+    {
+        tracing::warn!("Notifying mentioned users failed: {err}");
     }
 
-    Ok((
-        StatusCode::OK,
-        Json(PatchTaskResponse {
-            task: task_db_to_api(new_task),
-        }),
+    if task.complete != new_task.complete {
+        let _ = refresh_task_status_entry(&task_id, &state.task_repo, &state.notif_repo).await?;
+    }
+    if let Some(parent) = &task.parent {
+        refresh_auto_complete(parent, &state.task_repo, &state.notif_repo).await?;
+    }
+    // turning auto-completion on may complete the task right away
+    refresh_auto_complete(&task_id, &state.task_repo, &state.notif_repo).await?;
+    let new_task = state.task_repo.query_task_by_id(&task_id).await?;
+    spawn_next_occurrence(
+        &state.task_repo,
+        &state.project_repo,
+        &state.user_repo,
+        &state.notif_repo,
+        &task_id,
+        &task,
+        &new_task,
     )
-        .into_response())
+    .await?;
+
+    let mut events = vec![];
+    if !task.complete && new_task.complete {
+        events.push(WebhookEvent::TaskCompleted);
+    }
+    if new_task.pr_assigned && (!task.pr_assigned || task.pr != new_task.pr) {
+        events.push(WebhookEvent::PrLinked);
+    }
+    // the task is saved already
+    for event in events {
+        let actor = Some(author_id);
+        if let Err(err) = dispatch_task_event(state, &task_list_id, event, actor, &new_task).await
+        {
+            tracing::warn!("Sending webhooks failed: {err}");
+        }
+    }
+    Ok((new_task, warnings))
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -446,3 +789,271 @@ pub async fn get_assigned_tasks_for_user(
     )
         .into_response()
 }
+
+/// Rejects tasks addressed through a task list they do not belong to
+pub async fn check_task_in_list(
+    state: &AppState,
+    task_list_id: &str,
+    task_id: &str,
+) -> Result<(), io::Error> {
+    if state.task_repo.query_task_list_id_by_task(task_id).await? != task_list_id {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Task not found in task list",
+        ));
+    }
+    Ok(())
+}
+
+async fn query_task_node(state: &AppState, task_id: &str) -> Result<TaskNode, io::Error> {
+    let task = task_db_to_api(state.task_repo.query_task_by_id(task_id).await?);
+    let mut subtasks = vec![];
+    for subtask in state.task_repo.query_subtasks_of_task(task_id).await? {
+        subtasks.push(Box::pin(query_task_node(state, &subtask)).await?);
+    }
+    Ok(TaskNode { task, subtasks })
+}
+
+pub async fn get_subtasks(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((task_list_id, task_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) = authorize_against_task_list_id(
+        auth_session,
+        &state.project_repo,
+        &state.task_repo,
+        &task_list_id,
+    )
+    .await
+    {
+        return Ok(value);
+    }
+    check_task_in_list(state, &task_list_id, &task_id).await?;
+
+    Ok((StatusCode::OK, Json(query_task_node(state, &task_id).await?)).into_response())
+}
+
+pub async fn create_subtask(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((task_list_id, task_id)): Path<(String, String)>,
+    Json(req): Json<CreateTaskForListRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+    if let Some(value) = authorize_against_task_list_id(
+        auth_session,
+        &state.project_repo,
+        &state.task_repo,
+        &task_list_id,
+    )
+    .await
+    {
+        return Ok(value);
+    }
+    check_task_in_list(state, &task_list_id, &task_id).await?;
+
+    let (subtask, warnings) =
+        insert_task_from_request(state, &task_list_id, &author_id, req).await?;
+    let subtask_id = get_str_id(&subtask.id);
+    let subtask =
+        move_subtree(&subtask_id, Some(&task_id), &state.task_repo, &state.notif_repo).await?;
+    let task = task_db_to_api(subtask);
+    let change = ProjectChange::TaskCreated {
+        task_list_id: task_list_id.clone(),
+        task: task.clone(),
+    };
+    publish_task_list_change(state, &task_list_id, &author_id, change).await;
+
+    Ok((StatusCode::OK, Json(CreateTaskForListResponse { task, warnings })).into_response())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MoveTaskRequest {
+    /// New parent task, `null` to make the task top level
+    pub parent: Option<Id>,
+}
+
+pub async fn move_task_subtree(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((task_list_id, task_id)): Path<(String, String)>,
+    Json(req): Json<MoveTaskRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+    if let Some(value) = authorize_against_task_list_id(
+        auth_session.clone(),
+        &state.project_repo,
+        &state.task_repo,
+        &task_list_id,
+    )
+    .await
+    {
+        return Ok(value);
+    }
+    check_task_in_list(state, &task_list_id, &task_id).await?;
+
+    if let Some(Id { id: parent_id }) = &req.parent {
+        let parent_list = state.task_repo.query_task_list_id_by_task(parent_id).await?;
+        if let Some(value) = authorize_against_task_list_id(
+            auth_session,
+            &state.project_repo,
+            &state.task_repo,
+            &parent_list,
+        )
+        .await
+        {
+            return Ok(value);
+        }
+    }
+
+    let parent = req.parent.map(|parent| parent.id);
+    let task =
+        move_subtree(&task_id, parent.as_deref(), &state.task_repo, &state.notif_repo).await?;
+    let task = task_db_to_api(task);
+    // the subtree may have followed its new parent into another list
+    let task_list_id = state.task_repo.query_task_list_id_by_task(&task_id).await?;
+    let change = ProjectChange::TaskUpdated {
+        task_list_id: task_list_id.clone(),
+        task: task.clone(),
+    };
+    publish_task_list_change(state, &task_list_id, &author_id, change).await;
+
+    Ok((
+        StatusCode::OK,
+        Json(PatchTaskResponse {
+            task,
+            warnings: vec![],
+        }),
+    )
+        .into_response())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MoveTaskToListRequest {
+    pub task_list: Id,
+    /// Zero based position in the target list, appended when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+}
+
+pub async fn move_task_to_list(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((task_list_id, task_id)): Path<(String, String)>,
+    Json(req): Json<MoveTaskToListRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+    for list in [&task_list_id, &req.task_list.id] {
+        if let Some(value) = authorize_against_task_list_id(
+            auth_session.clone(),
+            &state.project_repo,
+            &state.task_repo,
+            list,
+        )
+        .await
+        {
+            return Ok(value);
+        }
+    }
+    check_task_in_list(state, &task_list_id, &task_id).await?;
+
+    let task = move_task(
+        &task_id,
+        &req.task_list.id,
+        req.position,
+        &state.task_repo,
+        &state.notif_repo,
+    )
+    .await?;
+    let task = task_db_to_api(task);
+    let change = ProjectChange::TaskUpdated {
+        task_list_id: req.task_list.id.clone(),
+        task: task.clone(),
+    };
+    publish_task_list_change(state, &req.task_list.id, &author_id, change).await;
+
+    Ok((
+        StatusCode::OK,
+        Json(PatchTaskResponse {
+            task,
+            warnings: vec![],
+        }),
+    )
+        .into_response())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetTaskHistoryResponse {
+    pub history: Vec<TaskChange>,
+}
+
+pub async fn get_task_history(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((task_list_id, task_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) = authorize_against_task_list_id(
+        auth_session,
+        &state.project_repo,
+        &state.task_repo,
+        &task_list_id,
+    )
+    .await
+    {
+        return Ok(value);
+    }
+    check_task_in_list(state, &task_list_id, &task_id).await?;
+
+    let history = state
+        .task_repo
+        .query_task_changes(&task_id)
+        .await?
+        .into_iter()
+        .map(task_change_db_to_api)
+        .collect();
+
+    Ok((StatusCode::OK, Json(GetTaskHistoryResponse { history })).into_response())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetTaskOccurrencesResponse {
+    pub occurrences: Vec<Task>,
+}
+
+/// Every occurrence of the recurring series the task belongs to, oldest first
+pub async fn get_task_occurrences(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((task_list_id, task_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) = authorize_against_task_list_id(
+        auth_session,
+        &state.project_repo,
+        &state.task_repo,
+        &task_list_id,
+    )
+    .await
+    {
+        return Ok(value);
+    }
+    check_task_in_list(state, &task_list_id, &task_id).await?;
+
+    let task = state.task_repo.query_task_by_id(&task_id).await?;
+    let series = task.series.unwrap_or(task_id);
+    let occurrences = state
+        .task_repo
+        .query_tasks_of_series(&series)
+        .await?
+        .into_iter()
+        .map(task_db_to_api)
+        .collect();
+
+    Ok((StatusCode::OK, Json(GetTaskOccurrencesResponse { occurrences })).into_response())
+}
@@ -7,7 +7,7 @@ use axum::{
     routing::{delete, get, post},
     Json, Router,
 };
-use axum_login::AuthSession;
+use axum_login::{AuthSession, AuthUser};
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 
@@ -15,14 +15,16 @@ use crate::{
     api::{
         app::AppState,
         model::{
+            change::ProjectChange,
             task::{TaskRelation, TaskRelationType},
             util::Id,
         },
     },
     db::{model::task::TaskLink, repository::utils::unwrap_thing},
-    usecase::{task_stream::refresh_task_status, util::auth_backend::AuthBackend},
+    usecase::{task_stream::refresh_linked_task_status, util::auth_backend::AuthBackend},
 };
 
+use super::project_feed::publish_task_change;
 use super::util::{
     authorize_against_project_id, authorize_against_task_id, authorize_against_task_link,
     authorize_against_task_link_id, authorize_against_user_id, task_link_db_to_api,
@@ -144,7 +146,17 @@ pub async fn create_task_link_for_user(
         }
     };
 
-    if let Err(err) = refresh_task_status(&req.to.id, &state.task_repo).await {
+    let is_dep = task_link.kind == "dep";
+    let refreshed = refresh_linked_task_status(
+        &req.to.id,
+        &req.from.id,
+        false,
+        is_dep,
+        &state.task_repo,
+        &state.notif_repo,
+    )
+    .await;
+    if let Err(err) = refreshed {
         return (StatusCode::INTERNAL_SERVER_ERROR, Json(err.to_string())).into_response();
     }
 
@@ -212,17 +224,27 @@ pub async fn create_task_link_for_project(
         }
     };
 
-    if let Err(err) = refresh_task_status(&req.to.id, &state.task_repo).await {
+    let is_dep = task_link.kind == "dep";
+    let refreshed = refresh_linked_task_status(
+        &req.to.id,
+        &req.from.id,
+        false,
+        is_dep,
+        &state.task_repo,
+        &state.notif_repo,
+    )
+    .await;
+    if let Err(err) = refreshed {
         return (StatusCode::INTERNAL_SERVER_ERROR, Json(err.to_string())).into_response();
     }
+    let relation = task_link_db_to_api(task_link).unwrap();
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+    let change = ProjectChange::LinkCreated {
+        link: relation.clone(),
+    };
+    state.project_feed.publish(&project_id, &author_id, change);
 
-    (
-        StatusCode::OK,
-        Json(CreateTaskLinkForProjectResponse {
-            relation: task_link_db_to_api(task_link).unwrap(),
-        }),
-    )
-        .into_response()
+    (StatusCode::OK, Json(CreateTaskLinkForProjectResponse { relation })).into_response()
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -270,11 +292,24 @@ pub async fn delete_task_link(
 
     match state.task_repo.delete_task_link_by_id(&link_id).await {
         Ok(_link) => {
-            if let Err(err) =
-                refresh_task_status(&unwrap_thing(_link.outgoing.unwrap()), &state.task_repo).await
-            {
+            let to = unwrap_thing(_link.outgoing.unwrap());
+            let from = unwrap_thing(_link.incoming.unwrap());
+            let was_dep = _link.kind == "dep";
+            let refreshed = refresh_linked_task_status(
+                &to,
+                &from,
+                was_dep,
+                false,
+                &state.task_repo,
+                &state.notif_repo,
+            )
+            .await;
+            if let Err(err) = refreshed {
                 return (StatusCode::INTERNAL_SERVER_ERROR, Json(err.to_string())).into_response();
             }
+            let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+            let change = ProjectChange::LinkDeleted { link_id };
+            publish_task_change(&state, &to, &author_id, change).await;
             StatusCode::OK.into_response()
         }
         Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, Json(err.to_string())).into_response(),
@@ -330,19 +365,33 @@ pub async fn patch_task_link(
         )
         .await
     {
-        Ok(_link) => (
-            StatusCode::OK,
-            Json(PatchTaskLinkResponse {
-                task_relation: task_link_db_to_api(_link).unwrap(),
-            }),
-        )
-            .into_response(),
+        Ok(_link) => {
+            let to = unwrap_thing(_link.outgoing.clone().unwrap());
+            let task_relation = task_link_db_to_api(_link).unwrap();
+            let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+            let change = ProjectChange::LinkUpdated {
+                link: task_relation.clone(),
+            };
+            publish_task_change(&state, &to, &author_id, change).await;
+            (StatusCode::OK, Json(PatchTaskLinkResponse { task_relation })).into_response()
+        }
         Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, Json(err.to_string())).into_response(),
     };
 
-    if let Err(err) =
-        refresh_task_status(&unwrap_thing(link.outgoing.unwrap()), &state.task_repo).await
-    {
+    let to = unwrap_thing(link.outgoing.unwrap());
+    let from = unwrap_thing(link.incoming.unwrap());
+    let was_dep = link.kind == "dep";
+    let is_dep = matches!(req.category, TaskRelationType::Dep);
+    let refreshed = refresh_linked_task_status(
+        &to,
+        &from,
+        was_dep,
+        is_dep,
+        &state.task_repo,
+        &state.notif_repo,
+    )
+    .await;
+    if let Err(err) = refreshed {
         return (StatusCode::INTERNAL_SERVER_ERROR, Json(err.to_string())).into_response();
     }
 
@@ -4,22 +4,31 @@ use axum::{
     extract::{Path, State},
     http::StatusCode,
     response::IntoResponse,
-    routing::{delete, get},
+    routing::{delete, get, post, put},
     Json, Router,
 };
-use axum_login::AuthSession;
+use axum_login::{AuthSession, AuthUser};
 use futures::future::try_join_all;
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 
 use crate::{
-    api::{app::AppState, model::task::TaskList},
-    usecase::util::auth_backend::AuthBackend,
+    api::{
+        app::AppState,
+        model::{change::ProjectChange, task::TaskList, util::Id},
+    },
+    usecase::{task_order::reorder_tasks, util::auth_backend::AuthBackend},
 };
 
 use super::{
+    board,
+    project_feed::publish_task_list_change,
+    time_entry,
     task::{
-        create_task_for_list, delete_task_from_list, get_all_tasks_for_project, get_all_tasks_for_user, get_assigned_tasks_for_user, get_tasks_for_list, patch_task
+        create_subtask, create_task_for_list, delete_task_from_list, get_all_tasks_for_project,
+        get_all_tasks_for_user, get_assigned_tasks_for_user, get_subtasks, get_task_history,
+        get_task_occurrences, get_tasks_for_list,
+        move_task_subtree, move_task_to_list, patch_task, IoErrorWrapper,
     },
     util::{
         authorize_against_project_id, authorize_against_task_list_id, authorize_against_user_id,
@@ -52,7 +61,18 @@ pub fn router() -> Router<Arc<Mutex<AppState>>> {
             "/tasks/:task_id",
             delete(delete_task_from_list).patch(patch_task),
         )
-        .route("/", get(get_task_list_info).delete(delete_task_list));
+        .route(
+            "/tasks/:task_id/subtasks",
+            get(get_subtasks).post(create_subtask),
+        )
+        .route("/tasks/:task_id/parent", put(move_task_subtree))
+        .route("/tasks/:task_id/move", post(move_task_to_list))
+        .route("/tasks/:task_id/history", get(get_task_history))
+        .route("/tasks/:task_id/occurrences", get(get_task_occurrences))
+        .route("/order", put(reorder_task_list))
+        .route("/", get(get_task_list_info).delete(delete_task_list))
+        .merge(board::task_list_router())
+        .merge(time_entry::task_list_router());
 
     Router::new().nest("/:task_list_id", router)
 }
@@ -222,6 +242,7 @@ pub async fn create_task_list_for_project(
     Json(req): Json<CreateTaskListForProjectRequest>,
 ) -> impl IntoResponse {
     let state = state.lock().await;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
 
     if let Some(value) =
         authorize_against_project_id(auth_session, &state.project_repo, &project_id).await
@@ -243,6 +264,10 @@ pub async fn create_task_list_for_project(
         None => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
         Some(task_list) => task_list,
     };
+    let change = ProjectChange::TaskListCreated {
+        task_list: api_task_list.clone(),
+    };
+    state.project_feed.publish(&project_id, &author_id, change);
 
     (
         StatusCode::OK,
@@ -306,6 +331,7 @@ pub async fn delete_task_list(
     Path(task_list_id): Path<String>,
 ) -> impl IntoResponse {
     let ref state = state.lock().await;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
 
     if let Some(value) = authorize_against_task_list_id(
         auth_session,
@@ -327,15 +353,60 @@ pub async fn delete_task_list(
         Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     };
 
-    let task_futures: Vec<_> = tasks
-        .into_iter()
-        .map(|task_id| async move { state.task_repo.delete_task(&task_id).await })
-        .collect();
-
-    let _ = try_join_all(task_futures).await;
+    let _ = state.task_repo.delete_tasks(&tasks).await;
 
+    // the owner is unknown once the list is gone
+    let source = state.task_repo.query_task_list_source(&task_list_id).await;
     match state.task_repo.delete_task_list(&task_list_id).await {
-        Ok(_) => StatusCode::OK.into_response(),
+        Ok(_) => {
+            if let Ok(source) = source {
+                if source.tb == "project" {
+                    let change = ProjectChange::TaskListDeleted { task_list_id };
+                    state.project_feed.publish(&source.id.to_string(), &author_id, change);
+                }
+            }
+            StatusCode::OK.into_response()
+        }
         Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     }
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReorderTaskListRequest {
+    /// Tasks in their new order; tasks left out keep their relative order after these
+    pub tasks: Vec<Id>,
+}
+
+pub async fn reorder_task_list(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(task_list_id): Path<String>,
+    Json(req): Json<ReorderTaskListRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let state = state.lock().await;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+    if let Some(value) = authorize_against_task_list_id(
+        auth_session,
+        &state.project_repo,
+        &state.task_repo,
+        &task_list_id,
+    )
+    .await
+    {
+        return Ok(value);
+    }
+
+    let tasks = req.tasks.into_iter().map(|task| task.id).collect();
+    let order = reorder_tasks(&task_list_id, tasks, &state.task_repo).await?;
+    let change = ProjectChange::TaskListReordered {
+        task_list_id: task_list_id.clone(),
+        tasks: order,
+    };
+    publish_task_list_change(&state, &task_list_id, &author_id, change).await;
+
+    let task_list = state.task_repo.query_task_list_by_id(&task_list_id).await?;
+    Ok(match task_list_db_to_api(task_list) {
+        None => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        Some(task_list) => (StatusCode::OK, Json(GetTaskListInfoResponse { task_list })).into_response(),
+    })
+}
@@ -0,0 +1,371 @@
+use std::{io, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use axum_login::{AuthSession, AuthUser};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+use tokio::sync::Mutex;
+
+use crate::{
+    api::{
+        app::AppState,
+        model::{
+            change::ProjectChange,
+            outgoing_webhook::WebhookEvent,
+            task::Task,
+            template::{Template, TemplateLink, TemplateTask},
+            util::Id,
+        },
+    },
+    db::repository::utils::{get_str_id, DbModelId},
+    usecase::{
+        template::{instantiate_template, snapshot_task_list, validate_template, TemplateTarget},
+        util::auth_backend::AuthBackend,
+    },
+};
+
+use super::{
+    outgoing_webhook::dispatch_task_event,
+    project_feed::publish_task_list_change,
+    task::IoErrorWrapper,
+    util::{
+        authorize_against_project_id, authorize_against_task_list_id, authorize_against_user_id,
+        task_db_to_api, task_list_db_to_api, template_db_to_api, template_links_api_to_db,
+        template_tasks_api_to_db,
+    },
+};
+
+pub fn router() -> Router<Arc<Mutex<AppState>>> {
+    Router::new()
+        .route("/:template_id", get(get_template).delete(delete_template))
+        .route("/:template_id/instantiate", post(instantiate))
+}
+
+pub fn project_router() -> Router<Arc<Mutex<AppState>>> {
+    Router::new().route(
+        "/templates",
+        get(get_templates_for_project).post(create_template_for_project),
+    )
+}
+
+pub fn user_router() -> Router<Arc<Mutex<AppState>>> {
+    Router::new().route(
+        "/templates",
+        get(get_templates_for_user).post(create_template_for_user),
+    )
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetTemplatesResponse {
+    pub templates: Vec<Template>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateTemplateRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tasks: Vec<TemplateTask>,
+    #[serde(default)]
+    pub links: Vec<TemplateLink>,
+    /// Captures the tasks and links of this task list instead of `tasks` and `links`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_task_list: Option<Id>,
+}
+
+pub async fn get_templates_for_project(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(project_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) =
+        authorize_against_project_id(auth_session, &state.project_repo, &project_id).await
+    {
+        return Ok(value);
+    }
+
+    let owner = Thing::from(("project", project_id.as_str()));
+    get_templates(state, owner).await
+}
+
+pub async fn get_templates_for_user(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(user_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    if let Some(value) = authorize_against_user_id(auth_session, &user_id) {
+        return Ok(value);
+    }
+    let ref state = state.lock().await;
+
+    let owner = Thing::from(("user", user_id.as_str()));
+    get_templates(state, owner).await
+}
+
+async fn get_templates(state: &AppState, owner: Thing) -> Result<Response, IoErrorWrapper> {
+    let templates = state
+        .template_repo
+        .query_templates_by_owner(owner)
+        .await?
+        .into_iter()
+        .map(template_db_to_api)
+        .collect();
+
+    Ok((StatusCode::OK, Json(GetTemplatesResponse { templates })).into_response())
+}
+
+pub async fn create_template_for_project(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(project_id): Path<String>,
+    Json(req): Json<CreateTemplateRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) =
+        authorize_against_project_id(auth_session.clone(), &state.project_repo, &project_id).await
+    {
+        return Ok(value);
+    }
+
+    let owner = Thing::from(("project", project_id.as_str()));
+    create_template(auth_session, state, owner, req).await
+}
+
+pub async fn create_template_for_user(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(user_id): Path<String>,
+    Json(req): Json<CreateTemplateRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    if let Some(value) = authorize_against_user_id(auth_session.clone(), &user_id) {
+        return Ok(value);
+    }
+    let ref state = state.lock().await;
+
+    let owner = Thing::from(("user", user_id.as_str()));
+    create_template(auth_session, state, owner, req).await
+}
+
+async fn create_template(
+    auth_session: AuthSession<AuthBackend>,
+    state: &AppState,
+    owner: Thing,
+    req: CreateTemplateRequest,
+) -> Result<Response, IoErrorWrapper> {
+    let mut template =
+        crate::db::model::template::Template::new(req.name, req.description, owner);
+    match req.from_task_list {
+        Some(Id { id: task_list_id }) => {
+            if let Some(value) = authorize_against_task_list_id(
+                auth_session,
+                &state.project_repo,
+                &state.task_repo,
+                &task_list_id,
+            )
+            .await
+            {
+                return Ok(value);
+            }
+            (template.tasks, template.links) =
+                snapshot_task_list(&state.task_repo, &task_list_id).await?;
+        }
+        None => {
+            template.tasks = template_tasks_api_to_db(req.tasks);
+            template.links = template_links_api_to_db(req.links);
+        }
+    }
+    validate_template(&template.tasks, &template.links)?;
+    let template = state.template_repo.insert_template(&template).await?;
+
+    Ok((StatusCode::OK, Json(template_db_to_api(template))).into_response())
+}
+
+/// Authorizes against the project or user owning the template
+async fn authorize_against_template(
+    auth_session: AuthSession<AuthBackend>,
+    state: &AppState,
+    template: &crate::db::model::template::Template,
+) -> Option<Response> {
+    let owner_id = template.owner.id.to_string();
+    match template.owner.tb.as_str() {
+        "project" => {
+            authorize_against_project_id(auth_session, &state.project_repo, &owner_id).await
+        }
+        _ => authorize_against_user_id(auth_session, &owner_id),
+    }
+}
+
+pub async fn get_template(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(template_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    let template = state.template_repo.query_template_by_id(&template_id).await?;
+    if let Some(value) = authorize_against_template(auth_session, state, &template).await {
+        return Ok(value);
+    }
+
+    Ok((StatusCode::OK, Json(template_db_to_api(template))).into_response())
+}
+
+pub async fn delete_template(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(template_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    let template = state.template_repo.query_template_by_id(&template_id).await?;
+    if let Some(value) = authorize_against_template(auth_session, state, &template).await {
+        return Ok(value);
+    }
+    let template = state.template_repo.delete_template(&template_id).await?;
+
+    Ok((StatusCode::OK, Json(template_db_to_api(template))).into_response())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind")]
+#[serde(rename_all = "snake_case")]
+pub enum InstantiateTarget {
+    /// A new task list in the project
+    Project { id: String },
+    /// A new task list in the user's personal space
+    User { id: String },
+    /// An existing task list
+    TaskList { id: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstantiateTemplateRequest {
+    pub target: InstantiateTarget,
+    /// Name of the new task list, defaulting to the template name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Deadline offsets count from here, defaulting to now
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstantiateTemplateResponse {
+    pub task_list: String,
+    pub tasks: Vec<Task>,
+}
+
+/// Users who can see the task lists of a project or user
+async fn query_owner_members(state: &AppState, owner: &Thing) -> Result<Vec<DbModelId>, io::Error> {
+    let owner_id = owner.id.to_string();
+    if owner.tb != "project" {
+        return Ok(vec![owner_id]);
+    }
+    let mut members = vec![state.project_repo.query_admin_by_id(&owner_id).await?.id()];
+    for member in state.project_repo.query_members_by_id(&owner_id).await? {
+        members.push(member.id());
+    }
+    Ok(members)
+}
+
+pub async fn instantiate(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(template_id): Path<String>,
+    Json(req): Json<InstantiateTemplateRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    let author_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+    let template = state.template_repo.query_template_by_id(&template_id).await?;
+    if let Some(value) = authorize_against_template(auth_session.clone(), state, &template).await
+    {
+        return Ok(value);
+    }
+
+    let name = req.name.unwrap_or(template.name.clone());
+    let (task_list_id, owner) = match req.target {
+        InstantiateTarget::Project { id } => {
+            if let Some(value) =
+                authorize_against_project_id(auth_session, &state.project_repo, &id).await
+            {
+                return Ok(value);
+            }
+            let task_list = state.task_repo.insert_task_list_for_project(&id, &name).await?;
+            let task_list_id = get_str_id(&task_list.id);
+            if let Some(task_list) = task_list_db_to_api(task_list) {
+                let change = ProjectChange::TaskListCreated { task_list };
+                state.project_feed.publish(&id, &author_id, change);
+            }
+            (task_list_id, Thing::from(("project", id.as_str())))
+        }
+        InstantiateTarget::User { id } => {
+            if let Some(value) = authorize_against_user_id(auth_session, &id) {
+                return Ok(value);
+            }
+            let task_list = state.task_repo.insert_task_list_for_user(&name, &id).await?;
+            (get_str_id(&task_list.id), Thing::from(("user", id.as_str())))
+        }
+        InstantiateTarget::TaskList { id } => {
+            if let Some(value) = authorize_against_task_list_id(
+                auth_session,
+                &state.project_repo,
+                &state.task_repo,
+                &id,
+            )
+            .await
+            {
+                return Ok(value);
+            }
+            let owner = state.task_repo.query_task_list_source(&id).await?;
+            (id, owner)
+        }
+    };
+    let members = query_owner_members(state, &owner).await?;
+
+    let created = instantiate_template(
+        &state.task_repo,
+        &state.project_repo,
+        &state.user_repo,
+        &state.notif_repo,
+        &template,
+        TemplateTarget {
+            task_list_id: &task_list_id,
+            start: req.start.unwrap_or(Utc::now()),
+            members: &members,
+        },
+    )
+    .await?;
+    // re-read so parents and link-driven statuses are reflected
+    let mut tasks = vec![];
+    for task in created {
+        let task = state.task_repo.query_task_by_id(&get_str_id(&task.id)).await?;
+        let event = WebhookEvent::TaskCreated;
+        let actor = Some(author_id.as_str());
+        if let Err(err) = dispatch_task_event(state, &task_list_id, event, actor, &task).await {
+            tracing::warn!("Sending webhooks failed: {err}");
+        }
+        let task = task_db_to_api(task);
+        let change = ProjectChange::TaskCreated {
+            task_list_id: task_list_id.clone(),
+            task: task.clone(),
+        };
+        publish_task_list_change(state, &task_list_id, &author_id, change).await;
+        tasks.push(task);
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(InstantiateTemplateResponse {
+            task_list: task_list_id,
+            tasks,
+        }),
+    )
+        .into_response())
+}
@@ -0,0 +1,396 @@
+use std::{collections::HashMap, io, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use axum_login::{AuthSession, AuthUser};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use futures::future::try_join_all;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    api::{
+        app::AppState,
+        model::time_entry::{TimeEntry, TimeSummary, TimesheetDay, TimesheetTask},
+    },
+    db::repository::utils::get_str_id,
+    usecase::{
+        board::query_scope_tasks,
+        time_tracking::{
+            manual_entry, minutes_per_day, query_time_totals, start_timer, stop_timer,
+        },
+        util::auth_backend::AuthBackend,
+    },
+};
+
+use super::{
+    task::{check_task_in_list, IoErrorWrapper},
+    util::{
+        authorize_against_project_id, authorize_against_task_list_id, authorize_against_user_id,
+        time_entry_db_to_api, time_totals_to_api,
+    },
+};
+
+pub fn task_list_router() -> Router<Arc<Mutex<AppState>>> {
+    Router::new()
+        .route(
+            "/tasks/:task_id/time",
+            get(get_time_for_task).post(create_time_entry),
+        )
+        .route("/tasks/:task_id/time/start", post(start_timer_for_task))
+        .route("/tasks/:task_id/time/stop", post(stop_timer_for_task))
+        .route("/tasks/:task_id/time/:entry_id", delete(delete_time_entry))
+        .route("/time", get(get_time_for_task_list))
+}
+
+pub fn project_router() -> Router<Arc<Mutex<AppState>>> {
+    Router::new().route("/time", get(get_time_for_project))
+}
+
+pub fn user_router() -> Router<Arc<Mutex<AppState>>> {
+    Router::new().route("/timesheet", get(get_timesheet))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetTimeForTaskResponse {
+    /// Rolled up over the task and its subtasks
+    pub summary: TimeSummary,
+    pub entries: Vec<TimeEntry>,
+}
+
+pub async fn get_time_for_task(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((task_list_id, task_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) = authorize_against_task_list_id(
+        auth_session,
+        &state.project_repo,
+        &state.task_repo,
+        &task_list_id,
+    )
+    .await
+    {
+        return Ok(value);
+    }
+    check_task_in_list(state, &task_list_id, &task_id).await?;
+
+    let mut ids = vec![task_id.clone()];
+    ids.extend(state.task_repo.query_descendants_of_task(&task_id).await?);
+    let tasks = try_join_all(ids.iter().map(|id| state.task_repo.query_task_by_id(id))).await?;
+    let summary = query_time_totals(&state.task_repo, &tasks.iter().collect::<Vec<_>>()).await?;
+
+    let now = Utc::now();
+    let entries = state
+        .task_repo
+        .query_time_entries_of_tasks(&[task_id])
+        .await?
+        .into_iter()
+        .map(|entry| time_entry_db_to_api(entry, now))
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(GetTimeForTaskResponse {
+            summary: time_totals_to_api(summary),
+            entries,
+        }),
+    )
+        .into_response())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateTimeEntryRequest {
+    pub started_at: DateTime<Utc>,
+    /// Either `ended_at` or `minutes` must be given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minutes: Option<u32>,
+    #[serde(default)]
+    pub note: String,
+}
+
+pub async fn create_time_entry(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((task_list_id, task_id)): Path<(String, String)>,
+    Json(req): Json<CreateTimeEntryRequest>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    let user_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+    if let Some(value) = authorize_against_task_list_id(
+        auth_session,
+        &state.project_repo,
+        &state.task_repo,
+        &task_list_id,
+    )
+    .await
+    {
+        return Ok(value);
+    }
+    check_task_in_list(state, &task_list_id, &task_id).await?;
+
+    let entry = manual_entry(
+        &task_id,
+        &user_id,
+        req.started_at,
+        req.ended_at,
+        req.minutes,
+        req.note,
+    )?;
+    let entry = state.task_repo.insert_time_entry(&entry).await?;
+
+    Ok((StatusCode::OK, Json(time_entry_db_to_api(entry, Utc::now()))).into_response())
+}
+
+pub async fn start_timer_for_task(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((task_list_id, task_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    let user_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+    if let Some(value) = authorize_against_task_list_id(
+        auth_session,
+        &state.project_repo,
+        &state.task_repo,
+        &task_list_id,
+    )
+    .await
+    {
+        return Ok(value);
+    }
+    check_task_in_list(state, &task_list_id, &task_id).await?;
+
+    let entry = start_timer(&state.task_repo, &task_id, &user_id).await?;
+
+    Ok((StatusCode::OK, Json(time_entry_db_to_api(entry, Utc::now()))).into_response())
+}
+
+pub async fn stop_timer_for_task(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((task_list_id, task_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    let user_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+    if let Some(value) = authorize_against_task_list_id(
+        auth_session,
+        &state.project_repo,
+        &state.task_repo,
+        &task_list_id,
+    )
+    .await
+    {
+        return Ok(value);
+    }
+    check_task_in_list(state, &task_list_id, &task_id).await?;
+
+    let running = state.task_repo.query_running_time_entry(&user_id).await?;
+    if running.map_or(true, |entry| entry.task != task_id) {
+        return Err(
+            io::Error::new(io::ErrorKind::NotFound, "No timer is running on this task").into(),
+        );
+    }
+    let entry = stop_timer(&state.task_repo, &user_id).await?;
+
+    Ok((StatusCode::OK, Json(time_entry_db_to_api(entry, Utc::now()))).into_response())
+}
+
+pub async fn delete_time_entry(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path((task_list_id, task_id, entry_id)): Path<(String, String, String)>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    let user_id = auth_session.user.as_ref().map(|user| user.id()).unwrap_or_default();
+    if let Some(value) = authorize_against_task_list_id(
+        auth_session,
+        &state.project_repo,
+        &state.task_repo,
+        &task_list_id,
+    )
+    .await
+    {
+        return Ok(value);
+    }
+    check_task_in_list(state, &task_list_id, &task_id).await?;
+
+    let entry = state.task_repo.query_time_entry_by_id(&entry_id).await?;
+    if entry.task != task_id {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "Time entry not found").into());
+    }
+    if entry.user != user_id {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "Only the author can delete a time entry",
+        )
+        .into());
+    }
+    let entry = state.task_repo.delete_time_entry(&entry_id).await?;
+
+    Ok((StatusCode::OK, Json(time_entry_db_to_api(entry, Utc::now()))).into_response())
+}
+
+pub async fn get_time_for_task_list(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(task_list_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) = authorize_against_task_list_id(
+        auth_session,
+        &state.project_repo,
+        &state.task_repo,
+        &task_list_id,
+    )
+    .await
+    {
+        return Ok(value);
+    }
+
+    let tasks = query_scope_tasks(&state.task_repo, &[task_list_id]).await?;
+    let tasks: Vec<_> = tasks.iter().map(|(task, _)| task).collect();
+    let summary = query_time_totals(&state.task_repo, &tasks).await?;
+
+    Ok((StatusCode::OK, Json(time_totals_to_api(summary))).into_response())
+}
+
+pub async fn get_time_for_project(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(project_id): Path<String>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    let ref state = state.lock().await;
+    if let Some(value) =
+        authorize_against_project_id(auth_session, &state.project_repo, &project_id).await
+    {
+        return Ok(value);
+    }
+
+    let task_lists = state.project_repo.query_task_list_by_id(&project_id).await?;
+    let tasks = query_scope_tasks(&state.task_repo, &task_lists).await?;
+    let tasks: Vec<_> = tasks.iter().map(|(task, _)| task).collect();
+    let mut summary = query_time_totals(&state.task_repo, &tasks).await?;
+
+    // anyone with entries is listed already; add the admin and members who tracked nothing yet
+    let mut members = state.project_repo.query_members_by_id(&project_id).await?;
+    members.push(state.project_repo.query_admin_by_id(&project_id).await?);
+    for member in members {
+        summary.per_user.entry(member.id()).or_default();
+    }
+
+    Ok((StatusCode::OK, Json(time_totals_to_api(summary))).into_response())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimesheetQuery {
+    pub from: NaiveDate,
+    /// Inclusive
+    pub to: NaiveDate,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetTimesheetResponse {
+    pub total: i64,
+    pub days: Vec<TimesheetDay>,
+    /// Tasks with time in the range, followed by the open tasks assigned to the user
+    pub tasks: Vec<TimesheetTask>,
+    pub entries: Vec<TimeEntry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub running: Option<TimeEntry>,
+}
+
+pub async fn get_timesheet(
+    auth_session: AuthSession<AuthBackend>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(user_id): Path<String>,
+    Query(query): Query<TimesheetQuery>,
+) -> Result<impl IntoResponse, IoErrorWrapper> {
+    if let Some(value) = authorize_against_user_id(auth_session, &user_id) {
+        return Ok(value);
+    }
+    if query.to < query.from {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "`to` is before `from`").into());
+    }
+    let ref state = state.lock().await;
+
+    let day_start = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let (from, to) = (day_start(query.from), day_start(query.to) + Duration::days(1));
+    let entries = state
+        .task_repo
+        .query_time_entries_of_user(&user_id, from.into(), to.into())
+        .await?;
+    let now = Utc::now();
+
+    let mut minutes_per_task: HashMap<String, i64> = HashMap::new();
+    for entry in &entries {
+        *minutes_per_task.entry(entry.task.clone()).or_default() += entry.minutes(now);
+    }
+
+    let mut tasks = vec![];
+    let mut tracked: Vec<_> = minutes_per_task.keys().cloned().collect();
+    tracked.sort();
+    for task_id in tracked {
+        // entries outlive deleted tasks; they still count towards the totals
+        let Ok(task) = state.task_repo.query_task_by_id(&task_id).await else {
+            continue;
+        };
+        let (task_list, project) = state.task_repo.query_task_path_by_id(&task_id).await?;
+        tasks.push(TimesheetTask {
+            id: task_id.clone(),
+            name: task.name,
+            task_list,
+            project,
+            estimate: task.estimate,
+            minutes: minutes_per_task[&task_id],
+        });
+    }
+    let assigned = state.task_repo.query_assigned_tasks_by_user(&user_id).await?;
+    for (task, task_list, project) in assigned {
+        let id = get_str_id(&task.id);
+        if task.complete || minutes_per_task.contains_key(&id) {
+            continue;
+        }
+        tasks.push(TimesheetTask {
+            id,
+            name: task.name,
+            task_list,
+            project,
+            estimate: task.estimate,
+            minutes: 0,
+        });
+    }
+
+    let days = minutes_per_day(&entries, now)
+        .into_iter()
+        .map(|(date, minutes)| TimesheetDay { date, minutes })
+        .collect();
+    let running = state
+        .task_repo
+        .query_running_time_entry(&user_id)
+        .await?
+        .map(|entry| time_entry_db_to_api(entry, now));
+
+    Ok((
+        StatusCode::OK,
+        Json(GetTimesheetResponse {
+            total: minutes_per_task.values().sum(),
+            days,
+            tasks,
+            entries: entries
+                .into_iter()
+                .map(|entry| time_entry_db_to_api(entry, now))
+                .collect(),
+            running,
+        }),
+    )
+        .into_response())
+}
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use axum::{
     extract::{Path, Request, State},
@@ -10,18 +10,25 @@ use axum::{
 };
 use axum_login::AuthSession;
 use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
 use tokio::sync::Mutex;
 
 use crate::{
     api::{
         app::AppState,
-        model::{status::StatusPool, user::User},
+        model::{
+            status::{Status, StatusPool},
+            user::User,
+        },
+    },
+    usecase::{
+        status_pool::{finish_status_migration, migrate_status_pool},
+        util::auth_backend::AuthBackend,
     },
-    usecase::util::auth_backend::AuthBackend,
 };
 
 use super::{
-    agenda, draft, notification, project::get_projects_for_user, task, task_link, task_list, util::{authorize_against_user_id, user_api_to_db, user_db_to_api}
+    agenda, draft, notification, project::get_projects_for_user, task::IoErrorWrapper, task_link, task_list, template, time_entry, util::{authorize_against_user_id, status_key_api_to_db, user_api_to_db, user_db_to_api}
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -37,6 +44,8 @@ pub fn router() -> Router<Arc<Mutex<AppState>>> {
         .merge(task_link::user_router())
         .merge(agenda::user_router())
         .merge(draft::user_router())
+        .merge(time_entry::user_router())
+        .merge(template::user_router())
         .route("/projects", get(get_projects_for_user))
         .route("/", get(get_user_info).patch(patch_user_info));
 
@@ -68,12 +77,18 @@ pub struct PatchUserInfoRequest {
     pub email: Option<String>,
     pub avatar: Option<String>,
     pub status_pool: Option<StatusPool>,
+    /// Replacement for every incomplete status removed from `status_pool`
+    #[serde(default)]
+    pub status_mapping: HashMap<String, Status>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PatchUserInfoResponse {
     #[serde(flatten)]
     pub user: User,
+    /// Number of personal tasks moved out of each removed status
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub migrated_tasks: HashMap<String, usize>,
 }
 
 pub async fn patch_user_info(
@@ -98,6 +113,8 @@ pub async fn patch_user_info(
     };
 
     let password = user.clone().password;
+    let original_status_pool = user.status_pool.clone();
+    let pool_changed = req.status_pool.is_some();
 
     let user = user_db_to_api(user);
 
@@ -116,6 +133,50 @@ pub async fn patch_user_info(
 
     let db_user = user_api_to_db(user.clone(), &password);
 
+    let mut migrated_tasks = HashMap::new();
+    if pool_changed {
+        let state = state.lock().await;
+        let task_lists = match state
+            .user_repo
+            .query_task_list_by_id_without_from_project(&user_id)
+            .await
+        {
+            Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            Ok(task_lists) => task_lists,
+        };
+        let mapping = req
+            .status_mapping
+            .into_iter()
+            .map(|(removed, target)| (removed, status_key_api_to_db(target)))
+            .collect();
+        let migration = match migrate_status_pool(
+            &state.task_repo,
+            Thing::from(("user", user_id.as_str())),
+            &task_lists,
+            &original_status_pool,
+            &db_user.status_pool,
+            &mapping,
+            &user_id,
+        )
+        .await
+        {
+            Err(err) => return IoErrorWrapper::from(err).into_response(),
+            Ok(migration) => migration,
+        };
+        let finished = finish_status_migration(
+            &state.task_repo,
+            &state.project_repo,
+            &state.user_repo,
+            &state.notif_repo,
+            &migration.completed,
+        )
+        .await;
+        if let Err(err) = finished {
+            return IoErrorWrapper::from(err).into_response();
+        }
+        migrated_tasks = migration.counts;
+    }
+
     let returned_user = state
         .lock()
         .await
@@ -135,5 +196,12 @@ pub async fn patch_user_info(
         Some(user) => user,
     };
 
-    (StatusCode::OK, Json(PatchUserInfoResponse { user })).into_response()
+    (
+        StatusCode::OK,
+        Json(PatchUserInfoResponse {
+            user,
+            migrated_tasks,
+        }),
+    )
+        .into_response()
 }
@@ -2,26 +2,32 @@ use std::io::Error;
 
 use axum::{http::StatusCode, response::IntoResponse, Json};
 use axum_login::{AuthSession, AuthUser};
-use surrealdb::sql::Thing;
+use surrealdb::sql::{Datetime, Thing};
 
-use crate::{api::model::asset::{DraftPath, EventPath, TaskPath}, db::{
+use crate::{api::model::asset::{
+    DeletedPath, DraftPath, EventPath, InvitationPath, ProjectPath, RequirementPath, TaskPath,
+}, db::{
     model::{
-        notification::AssetPath,
         status::{Status, StatusPool},
         task::Task,
     },
     repository::{
         agenda::AgendaRepository, project::ProjectRepository, task::TaskRepository,
-        user::UserRepository, utils::unwrap_thing,
+        user::UserRepository, utils::{get_str_id, unwrap_thing},
     },
 }};
-use crate::usecase::util::auth_backend::AuthBackend;
+use crate::usecase::{recurrence::format_rrule, util::auth_backend::AuthBackend};
 use crate::{
     api::model::{
         agenda::Event,
         asset::Asset,
-        status::{IndexedStatusContent, StatusContent},
-        task::{TaskRelation, TaskRelationType},
+        notification::{
+            DeliveryChannel, NotificationAction, NotificationCategory, NotificationPreference,
+        },
+        outgoing_webhook::{OutgoingWebhook, WebhookDelivery, WebhookEvent, WebhookFormat},
+        project::CustomFieldKind,
+        status::{IndexedStatusContent, RequiredField, StatusContent},
+        task::{FieldValue, Priority, TaskRelation, TaskRelationType},
         util::Id,
     },
     db::{model::task::TaskLink, repository::utils::custom_io_error},
@@ -365,15 +371,55 @@ pub fn status_pool_db_to_api(
                     name: status.name.clone(),
                     description: status.description.clone(),
                 },
+                wip_limit: status.wip_limit,
+                wip_per_assignee: status.wip_per_assignee,
             })
             .collect(),
         complete: crate::api::model::status::StatusContent {
             name: status_pool.complete.name,
             description: status_pool.complete.description,
         },
+        workflow: status_pool
+            .workflow
+            .into_iter()
+            .map(|rule| crate::api::model::status::StatusRule {
+                to: status_key_db_to_api(rule.to),
+                from: rule
+                    .from
+                    .map(|from| from.into_iter().map(status_key_db_to_api).collect()),
+                required_fields: rule
+                    .required_fields
+                    .iter()
+                    .filter_map(|field| match field.as_str() {
+                        "pr" => Some(RequiredField::Pr),
+                        "assignees" => Some(RequiredField::Assignees),
+                        "description" => Some(RequiredField::Description),
+                        "deadline" => Some(RequiredField::Deadline),
+                        _ => None,
+                    })
+                    .collect(),
+                admin_only: rule.admin_only,
+            })
+            .collect(),
+        wip_enforced: status_pool.wip_enforced,
     })
 }
 
+/// Task status strings are either an incomplete status number or `complete`
+fn status_key_db_to_api(key: String) -> crate::api::model::status::Status {
+    match key.as_str() {
+        "complete" => crate::api::model::status::Status::Complete,
+        _ => crate::api::model::status::Status::Incomplete { id: key },
+    }
+}
+
+pub fn status_key_api_to_db(status: crate::api::model::status::Status) -> String {
+    match status {
+        crate::api::model::status::Status::Complete => "complete".to_owned(),
+        crate::api::model::status::Status::Incomplete { id } => id,
+    }
+}
+
 pub fn status_pool_api_to_db(status_pool: crate::api::model::status::StatusPool) -> StatusPool {
     StatusPool {
         incomplete: status_pool
@@ -383,13 +429,42 @@ pub fn status_pool_api_to_db(status_pool: crate::api::model::status::StatusPool)
                 name: indexed.clone().status.name,
                 description: indexed.clone().status.description,
                 number: indexed.id.to_owned(),
+                wip_limit: indexed.wip_limit,
+                wip_per_assignee: indexed.wip_per_assignee,
             })
             .collect(),
         complete: Status {
             name: status_pool.complete.name,
             number: "0".to_owned(),
             description: status_pool.complete.description,
+            wip_limit: None,
+            wip_per_assignee: false,
         },
+        workflow: status_pool
+            .workflow
+            .into_iter()
+            .map(|rule| crate::db::model::status::StatusRule {
+                to: status_key_api_to_db(rule.to),
+                from: rule
+                    .from
+                    .map(|from| from.into_iter().map(status_key_api_to_db).collect()),
+                required_fields: rule
+                    .required_fields
+                    .iter()
+                    .map(|field| {
+                        match field {
+                            RequiredField::Pr => "pr",
+                            RequiredField::Assignees => "assignees",
+                            RequiredField::Description => "description",
+                            RequiredField::Deadline => "deadline",
+                        }
+                        .to_owned()
+                    })
+                    .collect(),
+                admin_only: rule.admin_only,
+            })
+            .collect(),
+        wip_enforced: status_pool.wip_enforced,
     }
 }
 
@@ -421,6 +496,30 @@ pub fn project_db_to_api(
         avatar: project.avatar,
         status_pool: status_pool_db_to_api(project.status_pool),
         github: Some(project.github),
+        labels: project
+            .labels
+            .into_iter()
+            .map(|label| crate::api::model::project::Label {
+                id: label.id,
+                name: label.name,
+                color: label.color,
+            })
+            .collect(),
+        custom_fields: project
+            .custom_fields
+            .into_iter()
+            .map(|def| crate::api::model::project::CustomFieldDef {
+                id: def.id,
+                name: def.name,
+                kind: match def.kind.as_str() {
+                    "number" => CustomFieldKind::Number,
+                    "date" => CustomFieldKind::Date,
+                    "single_select" => CustomFieldKind::SingleSelect { options: def.options },
+                    "multi_select" => CustomFieldKind::MultiSelect { options: def.options },
+                    _ => CustomFieldKind::Text,
+                },
+            })
+            .collect(),
     })
 }
 
@@ -443,9 +542,87 @@ pub fn project_api_to_db(
             Some(status_pool) => status_pool_api_to_db(status_pool),
         },
         github: 0,
+        labels: project
+            .labels
+            .into_iter()
+            .map(|label| crate::db::model::project::Label {
+                id: label.id,
+                name: label.name,
+                color: label.color,
+            })
+            .collect(),
+        custom_fields: project
+            .custom_fields
+            .into_iter()
+            .map(|def| {
+                let (kind, options) = match def.kind {
+                    CustomFieldKind::Text => ("text", vec![]),
+                    CustomFieldKind::Number => ("number", vec![]),
+                    CustomFieldKind::Date => ("date", vec![]),
+                    CustomFieldKind::SingleSelect { options } => ("single_select", options),
+                    CustomFieldKind::MultiSelect { options } => ("multi_select", options),
+                };
+                crate::db::model::project::CustomFieldDef {
+                    id: def.id,
+                    name: def.name,
+                    kind: kind.to_owned(),
+                    options,
+                }
+            })
+            .collect(),
+    }
+}
+
+pub fn priority_db_to_api(priority: &str) -> Option<Priority> {
+    match priority {
+        "low" => Some(Priority::Low),
+        "medium" => Some(Priority::Medium),
+        "high" => Some(Priority::High),
+        "urgent" => Some(Priority::Urgent),
+        _ => None,
+    }
+}
+
+pub fn priority_api_to_db(priority: Priority) -> String {
+    match priority {
+        Priority::Low => "low",
+        Priority::Medium => "medium",
+        Priority::High => "high",
+        Priority::Urgent => "urgent",
+    }
+    .to_owned()
+}
+
+fn field_value_db_to_api(value: crate::db::model::task::FieldValue) -> FieldValue {
+    use crate::db::model::task::FieldValue as DbFieldValue;
+    match value {
+        DbFieldValue::Text(text) => FieldValue::Text(text),
+        DbFieldValue::Number(number) => FieldValue::Number(number),
+        DbFieldValue::Date(date) => FieldValue::Date(date.0),
+        DbFieldValue::SingleSelect(option) => FieldValue::SingleSelect(option),
+        DbFieldValue::MultiSelect(options) => FieldValue::MultiSelect(options),
     }
 }
 
+pub fn custom_field_values_api_to_db(
+    values: Vec<crate::api::model::task::CustomFieldValue>,
+) -> Vec<crate::db::model::task::CustomFieldValue> {
+    use crate::db::model::task::FieldValue as DbFieldValue;
+    values
+        .into_iter()
+        .map(|value| crate::db::model::task::CustomFieldValue {
+            field: value.field,
+            value: match value.value {
+                FieldValue::Text(text) => DbFieldValue::Text(text),
+                FieldValue::Number(number) => DbFieldValue::Number(number),
+                FieldValue::Date(date) => DbFieldValue::Date(Datetime(date)),
+                FieldValue::SingleSelect(option) => DbFieldValue::SingleSelect(option),
+                FieldValue::MultiSelect(options) => DbFieldValue::MultiSelect(options),
+            },
+        })
+        .collect()
+}
+
 use crate::db::model::notification::NotificationSource;
 
 
@@ -453,31 +630,73 @@ pub fn notif_db_to_api(
     notif: crate::db::model::notification::Notification,
     source: NotificationSource,
 ) -> crate::api::model::notification::Notification {
+    let actions = match (&source, notif.handled) {
+        (NotificationSource::Invitation { .. }, false) => {
+            vec![NotificationAction::Accept, NotificationAction::Decline]
+        }
+        _ => vec![],
+    };
     crate::api::model::notification::Notification {
         id: unwrap_thing(notif.id.unwrap()),
+        actions,
         title: notif.title,
         content: notif.content,
         handled: notif.handled,
+        kind: notif.kind,
         asset: match source {
-            NotificationSource::Task(AssetPath(task_id, (task_list_id, project_id))) => {
-                Asset::Task {
-                    path: TaskPath {
-                        task_id,
-                        task_list_id,
-                        project_id,
-                    }
-                }
-            }
-            NotificationSource::Event(AssetPath(event_id, (agenda_id, project_id))) => {
-                Asset::Event {
-                    path: EventPath {
-                        event_id,
-                        agenda_id,
-                        project_id,
-                    }
-                }
-            }
-            NotificationSource::Draft(AssetPath(id, _)) => Asset::Draft { path: DraftPath { id } },
+            NotificationSource::Task {
+                task,
+                task_list,
+                owner,
+            } => Asset::Task {
+                path: TaskPath {
+                    task_id: task,
+                    task_list_id: task_list,
+                    project_id: owner,
+                },
+            },
+            NotificationSource::Event {
+                event,
+                agenda,
+                owner,
+            } => Asset::Event {
+                path: EventPath {
+                    event_id: event,
+                    agenda_id: agenda,
+                    project_id: owner,
+                },
+            },
+            NotificationSource::Draft { draft } => Asset::Draft {
+                path: DraftPath { id: draft },
+            },
+            NotificationSource::Requirement {
+                requirement,
+                project,
+            } => Asset::Requirement {
+                path: RequirementPath {
+                    requirement_id: requirement,
+                    project_id: project,
+                },
+            },
+            NotificationSource::Project { project } => Asset::Project {
+                path: ProjectPath {
+                    project_id: project,
+                },
+            },
+            NotificationSource::Invitation {
+                token,
+                project,
+                inviter,
+            } => Asset::Invitation {
+                path: InvitationPath {
+                    invitation_token: token,
+                    project_id: project,
+                    inviter_id: inviter,
+                },
+            },
+            NotificationSource::Deleted { table, id } => Asset::Deleted {
+                path: DeletedPath { kind: table, id },
+            },
         },
     }
 }
@@ -596,6 +815,52 @@ pub fn task_db_to_api(task: Task) -> crate::api::model::task::Task {
             true => Some(task.pr),
             false => None
         },
+        priority: task.priority.as_deref().and_then(priority_db_to_api),
+        labels: task.labels,
+        custom_fields: task
+            .custom_fields
+            .into_iter()
+            .map(|value| crate::api::model::task::CustomFieldValue {
+                field: value.field,
+                value: field_value_db_to_api(value.value),
+            })
+            .collect(),
+        estimate: task.estimate,
+        recurrence: task.recurrence.as_ref().map(format_rrule),
+        series: task.series.map(|id| Id { id }),
+        occurrence: task.occurrence,
+        rank: task.rank,
+        auto_complete: task.auto_complete,
+        parent: task.parent.map(|id| Id { id }),
+        progress: task.progress.map(|progress| crate::api::model::task::TaskProgress {
+            complete: progress.complete,
+            total: progress.total,
+        }),
+    }
+}
+
+pub fn task_change_db_to_api(
+    change: crate::db::model::task::TaskChange,
+) -> crate::api::model::task::TaskChange {
+    use crate::api::model::task::{FieldChange, TaskChangeSource};
+
+    crate::api::model::task::TaskChange {
+        id: get_str_id(&change.id),
+        source: match (change.source.as_str(), change.actor) {
+            ("github", _) => TaskChangeSource::Github,
+            ("user", Some(actor)) => TaskChangeSource::User { actor: Id { id: actor } },
+            _ => TaskChangeSource::System,
+        },
+        changes: change
+            .changes
+            .into_iter()
+            .map(|change| FieldChange {
+                field: change.field,
+                old: change.old,
+                new: change.new,
+            })
+            .collect(),
+        created_at: change.created_at.0,
     }
 }
 
@@ -631,3 +896,303 @@ pub fn task_relation_category_to_kind<'a>(category: &'a TaskRelationType) -> &'a
         TaskRelationType::Dep => "dep",
     }
 }
+
+pub fn comment_db_to_api(
+    comment: crate::db::model::comment::Comment,
+) -> crate::api::model::comment::Comment {
+    crate::api::model::comment::Comment {
+        id: unwrap_thing(comment.id.unwrap()),
+        author: Id { id: comment.author },
+        content: comment.content,
+        reply_to: comment.reply_to.map(|id| Id { id }),
+        created_at: comment.created_at.0,
+        updated_at: comment.updated_at.map(|time| time.0),
+        deleted: comment.deleted,
+        replies: vec![],
+    }
+}
+
+/// Nests replies under the comment they answer; replies to missing comments stay top level
+pub fn comments_db_to_api_thread(
+    comments: Vec<crate::db::model::comment::Comment>,
+) -> Vec<crate::api::model::comment::Comment> {
+    fn attach(
+        comment: &mut crate::api::model::comment::Comment,
+        replies: &mut Vec<crate::api::model::comment::Comment>,
+    ) {
+        let (mut children, rest): (Vec<_>, Vec<_>) = replies
+            .drain(..)
+            .partition(|reply| reply.reply_to.as_ref().is_some_and(|to| to.id == comment.id));
+        *replies = rest;
+        for child in &mut children {
+            attach(child, replies);
+        }
+        comment.replies = children;
+    }
+
+    let comments: Vec<_> = comments.into_iter().map(comment_db_to_api).collect();
+    let ids: Vec<_> = comments.iter().map(|comment| comment.id.clone()).collect();
+    let (mut roots, mut replies): (Vec<_>, Vec<_>) = comments.into_iter().partition(|comment| {
+        comment
+            .reply_to
+            .as_ref()
+            .map_or(true, |to| !ids.contains(&to.id))
+    });
+    for root in &mut roots {
+        attach(root, &mut replies);
+    }
+    roots
+}
+
+pub fn time_entry_db_to_api(
+    entry: crate::db::model::time_entry::TimeEntry,
+    now: chrono::DateTime<chrono::Utc>,
+) -> crate::api::model::time_entry::TimeEntry {
+    crate::api::model::time_entry::TimeEntry {
+        id: get_str_id(&entry.id),
+        minutes: entry.minutes(now),
+        task: Id { id: entry.task },
+        user: Id { id: entry.user },
+        started_at: entry.started_at.0,
+        ended_at: entry.ended_at.map(|ended_at| ended_at.0),
+        note: entry.note,
+        manual: entry.manual,
+    }
+}
+
+pub fn time_totals_to_api(
+    totals: crate::usecase::time_tracking::TimeTotals,
+) -> crate::api::model::time_entry::TimeSummary {
+    let mut per_user: Vec<_> = totals
+        .per_user
+        .into_iter()
+        .map(|(id, minutes)| crate::api::model::time_entry::UserTime {
+            user: Id { id },
+            minutes,
+        })
+        .collect();
+    per_user.sort_by(|a, b| a.user.id.cmp(&b.user.id));
+    crate::api::model::time_entry::TimeSummary {
+        estimate: totals.estimate,
+        tracked: totals.tracked,
+        per_user,
+    }
+}
+
+pub fn template_db_to_api(
+    template: crate::db::model::template::Template,
+) -> crate::api::model::template::Template {
+    use crate::api::model::template::{TemplateLink, TemplateOwner, TemplateTask};
+
+    crate::api::model::template::Template {
+        id: get_str_id(&template.id),
+        name: template.name,
+        description: template.description,
+        owner: match template.owner.tb.as_str() {
+            "project" => TemplateOwner::Project {
+                id: template.owner.id.to_string(),
+            },
+            _ => TemplateOwner::User {
+                id: template.owner.id.to_string(),
+            },
+        },
+        tasks: template
+            .tasks
+            .into_iter()
+            .map(|task| TemplateTask {
+                key: task.key,
+                name: task.name,
+                description: task.description,
+                deadline_offset: task.deadline_offset,
+                assignees: task.assignees.into_iter().map(|id| Id { id }).collect(),
+                priority: task.priority.as_deref().and_then(priority_db_to_api),
+                labels: task.labels,
+                estimate: task.estimate,
+                auto_complete: task.auto_complete,
+                parent: task.parent,
+            })
+            .collect(),
+        links: template
+            .links
+            .into_iter()
+            .map(|link| TemplateLink {
+                from: link.from,
+                to: link.to,
+                category: match link.kind.as_str() {
+                    "auto" => TaskRelationType::Auto,
+                    _ => TaskRelationType::Dep,
+                },
+            })
+            .collect(),
+        created_at: template.created_at.0,
+    }
+}
+
+pub fn template_tasks_api_to_db(
+    tasks: Vec<crate::api::model::template::TemplateTask>,
+) -> Vec<crate::db::model::template::TemplateTask> {
+    tasks
+        .into_iter()
+        .map(|task| crate::db::model::template::TemplateTask {
+            key: task.key,
+            name: task.name,
+            description: task.description,
+            deadline_offset: task.deadline_offset,
+            assignees: task.assignees.into_iter().map(|id| id.id).collect(),
+            priority: task.priority.map(priority_api_to_db),
+            labels: task.labels,
+            estimate: task.estimate,
+            auto_complete: task.auto_complete,
+            parent: task.parent,
+        })
+        .collect()
+}
+
+pub fn template_links_api_to_db(
+    links: Vec<crate::api::model::template::TemplateLink>,
+) -> Vec<crate::db::model::template::TemplateLink> {
+    links
+        .into_iter()
+        .map(|link| crate::db::model::template::TemplateLink {
+            kind: task_relation_category_to_kind(&link.category).to_owned(),
+            from: link.from,
+            to: link.to,
+        })
+        .collect()
+}
+
+pub fn notif_category_db_to_api(category: &str) -> Option<NotificationCategory> {
+    match category {
+        "task_assignment" => Some(NotificationCategory::TaskAssignment),
+        "event_assignment" => Some(NotificationCategory::EventAssignment),
+        "mention" => Some(NotificationCategory::Mention),
+        "comment" => Some(NotificationCategory::Comment),
+        "deadline" => Some(NotificationCategory::Deadline),
+        "pr_merged" => Some(NotificationCategory::PrMerged),
+        "dependency" => Some(NotificationCategory::Dependency),
+        _ => None,
+    }
+}
+
+pub fn notif_category_api_to_db(category: NotificationCategory) -> String {
+    match category {
+        NotificationCategory::TaskAssignment => "task_assignment",
+        NotificationCategory::EventAssignment => "event_assignment",
+        NotificationCategory::Mention => "mention",
+        NotificationCategory::Comment => "comment",
+        NotificationCategory::Deadline => "deadline",
+        NotificationCategory::PrMerged => "pr_merged",
+        NotificationCategory::Dependency => "dependency",
+    }
+    .to_owned()
+}
+
+pub fn notif_preference_db_to_api(
+    preference: crate::db::model::notification::NotificationPreference,
+) -> NotificationPreference {
+    NotificationPreference {
+        disabled: preference
+            .disabled
+            .iter()
+            .filter_map(|category| notif_category_db_to_api(category))
+            .collect(),
+        muted_projects: preference.muted_projects.into_iter().map(|id| Id { id }).collect(),
+        channels: preference
+            .channels
+            .iter()
+            .filter_map(|channel| match channel.as_str() {
+                "in_app" => Some(DeliveryChannel::InApp),
+                "email" => Some(DeliveryChannel::Email),
+                "email_digest" => Some(DeliveryChannel::EmailDigest),
+                _ => None,
+            })
+            .collect(),
+    }
+}
+
+pub fn notif_preference_api_to_db(
+    preference: NotificationPreference,
+) -> crate::db::model::notification::NotificationPreference {
+    crate::db::model::notification::NotificationPreference {
+        id: None,
+        disabled: preference.disabled.into_iter().map(notif_category_api_to_db).collect(),
+        muted_projects: preference.muted_projects.into_iter().map(|id| id.id).collect(),
+        channels: preference
+            .channels
+            .into_iter()
+            .map(|channel| match channel {
+                DeliveryChannel::InApp => "in_app",
+                DeliveryChannel::Email => "email",
+                DeliveryChannel::EmailDigest => "email_digest",
+            })
+            .map(str::to_owned)
+            .collect(),
+    }
+}
+
+pub fn webhook_event_db_to_api(event: &str) -> Option<WebhookEvent> {
+    match event {
+        "task_created" => Some(WebhookEvent::TaskCreated),
+        "task_completed" => Some(WebhookEvent::TaskCompleted),
+        "member_joined" => Some(WebhookEvent::MemberJoined),
+        "pr_linked" => Some(WebhookEvent::PrLinked),
+        "event_scheduled" => Some(WebhookEvent::EventScheduled),
+        _ => None,
+    }
+}
+
+pub fn webhook_event_api_to_db(event: WebhookEvent) -> String {
+    match event {
+        WebhookEvent::TaskCreated => "task_created",
+        WebhookEvent::TaskCompleted => "task_completed",
+        WebhookEvent::MemberJoined => "member_joined",
+        WebhookEvent::PrLinked => "pr_linked",
+        WebhookEvent::EventScheduled => "event_scheduled",
+    }
+    .to_owned()
+}
+
+pub fn webhook_format_api_to_db(format: WebhookFormat) -> String {
+    match format {
+        WebhookFormat::Json => "json",
+        WebhookFormat::Slack => "slack",
+        WebhookFormat::Discord => "discord",
+    }
+    .to_owned()
+}
+
+pub fn webhook_db_to_api(
+    webhook: crate::db::model::outgoing_webhook::OutgoingWebhook,
+) -> OutgoingWebhook {
+    OutgoingWebhook {
+        id: unwrap_thing(webhook.id.unwrap()),
+        url: webhook.url,
+        events: webhook
+            .events
+            .iter()
+            .filter_map(|event| webhook_event_db_to_api(event))
+            .collect(),
+        format: match webhook.format.as_str() {
+            "slack" => WebhookFormat::Slack,
+            "discord" => WebhookFormat::Discord,
+            _ => WebhookFormat::Json,
+        },
+        active: webhook.active,
+        secret: None,
+        created_at: webhook.created_at.0,
+    }
+}
+
+pub fn webhook_delivery_db_to_api(
+    delivery: crate::db::model::outgoing_webhook::WebhookDelivery,
+) -> Option<WebhookDelivery> {
+    Some(WebhookDelivery {
+        id: unwrap_thing(delivery.id.unwrap()),
+        event: webhook_event_db_to_api(&delivery.event)?,
+        attempts: delivery.attempts,
+        status: delivery.status,
+        error: delivery.error,
+        delivered: delivery.delivered,
+        created_at: delivery.created_at.0,
+    })
+}
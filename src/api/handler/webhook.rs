@@ -9,9 +9,26 @@ use axum::{
 use octocrate_webhooks::{WebhookPullRequestClosed, WebhookPullRequestClosedPullRequest};
 use tokio::sync::Mutex;
 
-use crate::{api::{app::AppState, model::pr::PullRequest}, db::repository::utils::unwrap_thing};
+use crate::{
+    api::{
+        app::AppState,
+        model::{change::ProjectChange, outgoing_webhook::WebhookEvent},
+    },
+    db::repository::utils::unwrap_thing,
+    usecase::{
+        notification::deliver_notif,
+        recurrence::spawn_next_occurrence,
+        task_history::{record_task_change, SOURCE_GITHUB},
+        task_stream::refresh_task_status_entry,
+        task_tree::refresh_auto_complete,
+        util::notification::pr_merged_to_notif,
+    },
+};
 
-use super::task::IoErrorWrapper;
+use super::{
+    outgoing_webhook::dispatch_task_event, project_feed::publish_task_list_change,
+    task::IoErrorWrapper, util::task_db_to_api,
+};
 
 pub async fn filter_github_webhook_requests(
     header: HeaderMap,
@@ -47,11 +64,45 @@ pub async fn handle_pull_request_event(
             if task.pr.repo != req.repository.name {
                 continue;
             }
+            let old = task.clone();
             task.complete = true;
-            let _ = state
-                .task_repo
-                .update_task_by_id(&unwrap_thing(task.id.clone().unwrap()), &task)
-                .await?;
+            let task_id = unwrap_thing(task.id.clone().unwrap());
+            let _ = state.task_repo.update_task_by_id(&task_id, &task).await?;
+            record_task_change(&state.task_repo, &task_id, &old, &task, SOURCE_GITHUB, None).await?;
+            spawn_next_occurrence(
+                &state.task_repo,
+                &state.project_repo,
+                &state.user_repo,
+                &state.notif_repo,
+                &task_id,
+                &old,
+                &task,
+            )
+            .await?;
+            let task_list = state.task_repo.query_task_list_id_by_task(&task_id).await?;
+            if !old.complete {
+                for assignee in task.assignees.clone().unwrap_or_default() {
+                    let notif = pr_merged_to_notif(task.clone());
+                    let _ =
+                        deliver_notif(&state.notif_repo, &assignee, &task_id, "task", notif).await?;
+                }
+                refresh_task_status_entry(&task_id, &state.task_repo, &state.notif_repo).await?;
+                let event = WebhookEvent::TaskCompleted;
+                let dispatched = dispatch_task_event(&state, &task_list, event, None, &task);
+                if let Err(err) = dispatched.await {
+                    tracing::warn!("Sending webhooks failed: {err}");
+                }
+            }
+            // tasks selected by pr number carry no hierarchy, so look the parent up
+            let task = state.task_repo.query_task_by_id(&task_id).await?;
+            if let Some(parent) = &task.parent {
+                refresh_auto_complete(parent, &state.task_repo, &state.notif_repo).await?;
+            }
+            let change = ProjectChange::TaskUpdated {
+                task_list_id: task_list.clone(),
+                task: task_db_to_api(task),
+            };
+            publish_task_list_change(&state, &task_list, "", change).await;
         }
 
         Ok(StatusCode::OK.into_response())
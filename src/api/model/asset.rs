@@ -7,6 +7,11 @@ pub enum Asset {
     Task { path: TaskPath },
     Draft { path: DraftPath },
     Event { path: EventPath },
+    Requirement { path: RequirementPath },
+    Project { path: ProjectPath },
+    Invitation { path: InvitationPath },
+    /// What the asset pointed at was deleted
+    Deleted { path: DeletedPath },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -27,3 +32,30 @@ pub struct EventPath {
     pub agenda_id: String,
     pub project_id: String,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RequirementPath {
+    pub requirement_id: String,
+    pub project_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ProjectPath {
+    pub project_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct InvitationPath {
+    pub invitation_token: String,
+    pub project_id: String,
+    pub inviter_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DeletedPath {
+    /// What kind of asset it was, e.g. `task`, when still known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use super::{status::Status, task::Task, util::Id};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ColumnSummary {
+    pub status: Status,
+    pub name: String,
+    pub count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wip_limit: Option<u32>,
+    /// True when the column, or one assignee for per-assignee limits, exceeds `wip_limit`
+    pub over_limit: bool,
+    pub per_assignee: Vec<AssigneeCount>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AssigneeCount {
+    #[serde(flatten)]
+    pub user: Id,
+    pub count: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BoardColumn {
+    pub status: Status,
+    pub name: String,
+    pub count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wip_limit: Option<u32>,
+    pub cards: Vec<BoardCard>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BoardCard {
+    #[serde(flatten)]
+    pub task: Task,
+    pub task_list_id: String,
+    pub assignee_profiles: Vec<BoardAssignee>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BoardAssignee {
+    pub id: String,
+    pub username: String,
+    pub avatar: String,
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    agenda::Event,
+    requirement::Requirement,
+    task::{Task, TaskList, TaskRelation},
+};
+
+/// Change to a project pushed to the members watching it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ProjectChangeEvent {
+    /// User who made the change, empty for changes made by the server or GitHub
+    pub actor: String,
+    #[serde(flatten)]
+    pub change: ProjectChange,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectChange {
+    TaskCreated { task_list_id: String, task: Task },
+    TaskUpdated { task_list_id: String, task: Task },
+    TaskDeleted { task_list_id: String, task_id: String },
+    TaskListCreated { task_list: TaskList },
+    TaskListDeleted { task_list_id: String },
+    TaskListReordered { task_list_id: String, tasks: Vec<String> },
+    LinkCreated { link: TaskRelation },
+    LinkUpdated { link: TaskRelation },
+    LinkDeleted { link_id: String },
+    RequirementCreated { requirement: Requirement },
+    RequirementUpdated { requirement: Requirement },
+    RequirementDeleted { requirement_id: String },
+    EventCreated { agenda_id: String, event: Event },
+    EventUpdated { agenda_id: String, event: Event },
+    EventDeleted { agenda_id: String, event_id: String },
+}
@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::util::Id;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Comment {
+    pub id: String,
+    pub author: Id,
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<Id>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Deleted by its author, kept for the replies below it
+    #[serde(default)]
+    pub deleted: bool,
+    pub replies: Vec<Comment>,
+}
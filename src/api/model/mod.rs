@@ -1,11 +1,18 @@
 pub mod agenda;
 pub mod asset;
+pub mod board;
+pub mod change;
+pub mod comment;
 pub mod draft;
 pub mod notification;
+pub mod outgoing_webhook;
 pub mod project;
 pub mod requirement;
+pub mod search;
 pub mod status;
 pub mod task;
+pub mod template;
+pub mod time_entry;
 pub mod user;
 pub mod util;
 pub mod pr;
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use super::asset::Asset;
+use super::{asset::Asset, util::Id};
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Notification {
@@ -9,4 +9,49 @@ pub struct Notification {
     pub content: String,
     pub asset: Asset,
     pub handled: bool,
+    pub kind: String,
+    /// What can be done right from the notification
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<NotificationAction>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationAction {
+    /// `POST /api/invitation/accept` with the token of the asset
+    Accept,
+    /// `POST /api/invitation/decline` with the token of the asset
+    Decline,
+}
+
+/// Groups of notification kinds a user can opt out of
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationCategory {
+    /// Tasks assigned to or taken off the user
+    TaskAssignment,
+    EventAssignment,
+    Mention,
+    Comment,
+    /// Upcoming and missed deadlines
+    Deadline,
+    PrMerged,
+    /// Tasks blocked or unblocked by their predecessors
+    Dependency,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryChannel {
+    InApp,
+    /// Important notifications by email right away, the others in the digest
+    Email,
+    EmailDigest,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NotificationPreference {
+    pub disabled: Vec<NotificationCategory>,
+    pub muted_projects: Vec<Id>,
+    pub channels: Vec<DeliveryChannel>,
 }
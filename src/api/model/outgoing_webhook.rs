@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    TaskCreated,
+    TaskCompleted,
+    MemberJoined,
+    PrLinked,
+    /// An event was added to a project agenda
+    EventScheduled,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// The full payload, for CI and custom receivers
+    #[default]
+    Json,
+    /// `{"text": ...}`, also understood by Mattermost
+    Slack,
+    /// `{"content": ...}`
+    Discord,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OutgoingWebhook {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub format: WebhookFormat,
+    pub active: bool,
+    /// Only returned when the webhook is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub event: WebhookEvent,
+    pub attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub delivered: bool,
+    pub created_at: DateTime<Utc>,
+}
@@ -13,6 +13,41 @@ pub struct Project {
     pub status_pool: Option<StatusPool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub github: Option<i64>,
+    #[serde(default)]
+    pub labels: Vec<Label>,
+    #[serde(default)]
+    pub custom_fields: Vec<CustomFieldDef>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Label {
+    /// Assigned by the server when left empty
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// CSS colour, e.g. `#ff0000`
+    pub color: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CustomFieldDef {
+    /// Assigned by the server when left empty
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: CustomFieldKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind")]
+#[serde(rename_all = "snake_case")]
+pub enum CustomFieldKind {
+    Text,
+    Number,
+    Date,
+    SingleSelect { options: Vec<String> },
+    MultiSelect { options: Vec<String> },
 }
 
 
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+use super::asset::Asset;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub asset: Asset,
+    pub name: String,
+    pub snippet: String,
+    pub score: u32,
+}
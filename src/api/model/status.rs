@@ -4,6 +4,32 @@ use serde::{Deserialize, Serialize};
 pub struct StatusPool {
     pub incomplete: Vec<IndexedStatusContent>,
     pub complete: StatusContent,
+    #[serde(default)]
+    pub workflow: Vec<StatusRule>,
+    /// Reject changes exceeding a WIP limit instead of only warning
+    #[serde(default)]
+    pub wip_enforced: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StatusRule {
+    pub to: Status,
+    /// Statuses a task may enter `to` from, any when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Vec<Status>>,
+    #[serde(default)]
+    pub required_fields: Vec<RequiredField>,
+    #[serde(default)]
+    pub admin_only: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequiredField {
+    Pr,
+    Assignees,
+    Description,
+    Deadline,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -16,6 +42,10 @@ pub struct StatusContent {
 pub struct IndexedStatusContent {
     pub id: String,
     pub status: StatusContent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wip_limit: Option<u32>,
+    #[serde(default)]
+    pub wip_per_assignee: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
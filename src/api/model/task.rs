@@ -13,6 +13,48 @@ pub struct Task {
     pub deadline: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pr: Option<PullRequest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// Ids of project labels
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub custom_fields: Vec<CustomFieldValue>,
+    /// Estimated effort in minutes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<u32>,
+    /// RRULE, e.g. `FREQ=WEEKLY;BYDAY=MO`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<String>,
+    /// First task of the recurring series this task belongs to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series: Option<Id>,
+    /// Number of earlier occurrences in the series
+    #[serde(default)]
+    pub occurrence: u32,
+    #[serde(default)]
+    pub rank: i64,
+    #[serde(default)]
+    pub auto_complete: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Id>,
+    /// Only present when the task has subtasks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<TaskProgress>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TaskProgress {
+    pub complete: u32,
+    pub total: u32,
+}
+
+/// A task with its subtasks, recursively
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TaskNode {
+    #[serde(flatten)]
+    pub task: Task,
+    pub subtasks: Vec<TaskNode>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -38,3 +80,54 @@ pub struct TaskList {
     pub name: String,
     pub tasks: Vec<Id>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TaskChange {
+    pub id: String,
+    #[serde(flatten)]
+    pub source: TaskChangeSource,
+    pub changes: Vec<FieldChange>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "source")]
+#[serde(rename_all = "snake_case")]
+pub enum TaskChangeSource {
+    User { actor: Id },
+    System,
+    Github,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+    Urgent,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CustomFieldValue {
+    pub field: String,
+    #[serde(flatten)]
+    pub value: FieldValue,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum FieldValue {
+    Text(String),
+    Number(f64),
+    Date(DateTime<Utc>),
+    SingleSelect(String),
+    MultiSelect(Vec<String>),
+}
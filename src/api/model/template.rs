@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    task::{Priority, TaskRelationType},
+    util::Id,
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Template {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub owner: TemplateOwner,
+    pub tasks: Vec<TemplateTask>,
+    pub links: Vec<TemplateLink>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind")]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateOwner {
+    Project { id: String },
+    User { id: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TemplateTask {
+    /// Identifies the task inside the template, for links and parents
+    pub key: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Minutes from instantiation to the deadline
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline_offset: Option<i64>,
+    /// Default assignees, kept when they can see the target task list
+    #[serde(default)]
+    pub assignees: Vec<Id>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<u32>,
+    #[serde(default)]
+    pub auto_complete: bool,
+    /// Key of the parent task
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TemplateLink {
+    /// Task keys
+    pub from: String,
+    pub to: String,
+    #[serde(flatten)]
+    pub category: TaskRelationType,
+}
@@ -0,0 +1,53 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::util::Id;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TimeEntry {
+    pub id: String,
+    pub task: Id,
+    pub user: Id,
+    pub started_at: DateTime<Utc>,
+    /// Absent while the timer is running
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Tracked so far for a running timer
+    pub minutes: i64,
+    pub note: String,
+    pub manual: bool,
+}
+
+/// Estimated and tracked minutes, rolled up over a task, task list or project
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TimeSummary {
+    pub estimate: u32,
+    pub tracked: i64,
+    pub per_user: Vec<UserTime>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct UserTime {
+    #[serde(flatten)]
+    pub user: Id,
+    pub minutes: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TimesheetDay {
+    pub date: NaiveDate,
+    pub minutes: i64,
+}
+
+/// A task on a timesheet with the time the user tracked on it in the range
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TimesheetTask {
+    pub id: String,
+    pub name: String,
+    pub task_list: String,
+    /// Project, or the user for personal task lists
+    pub project: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<u32>,
+    pub minutes: i64,
+}
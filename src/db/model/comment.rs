@@ -0,0 +1,34 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::{Datetime, Thing};
+
+use crate::db::repository::utils::DbModelId;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Comment {
+    pub id: Option<Thing>,
+    pub author: DbModelId,
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<DbModelId>,
+    pub created_at: Datetime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<Datetime>,
+    /// Deleted while it had replies, so only the content is gone
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+impl Comment {
+    pub fn new(author: DbModelId, content: String, reply_to: Option<DbModelId>) -> Self {
+        Comment {
+            id: None,
+            author,
+            content,
+            reply_to,
+            created_at: Datetime(Utc::now()),
+            updated_at: None,
+            deleted: false,
+        }
+    }
+}
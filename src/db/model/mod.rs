@@ -1,8 +1,13 @@
 pub mod user;
+pub mod outgoing_webhook;
 pub mod project;
+pub mod reminder;
 pub mod status;
 pub mod task;
+pub mod template;
+pub mod time_entry;
 pub mod notification;
 pub mod requirement;
 pub mod agenda;
-pub mod draft;
\ No newline at end of file
+pub mod draft;
+pub mod comment;
\ No newline at end of file
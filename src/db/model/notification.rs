@@ -1,15 +1,59 @@
 use serde::{Deserialize, Serialize};
-use surrealdb::sql::Thing;
+use surrealdb::sql::{Datetime, Thing};
 
 use crate::db::repository::utils::DbModelId;
 
-
-pub struct AssetPath(pub String, pub(String, String));
-
+/// What a notification is about, with the ids needed to open it
+#[derive(Clone, Debug, PartialEq)]
 pub enum NotificationSource {
-    Task(AssetPath),
-    Event(AssetPath),
-    Draft(AssetPath),
+    /// `owner` is the project or user owning the task list
+    Task {
+        task: DbModelId,
+        task_list: DbModelId,
+        owner: DbModelId,
+    },
+    Event {
+        event: DbModelId,
+        agenda: DbModelId,
+        owner: DbModelId,
+    },
+    Draft {
+        draft: DbModelId,
+    },
+    Requirement {
+        requirement: DbModelId,
+        project: DbModelId,
+    },
+    Project {
+        project: DbModelId,
+    },
+    /// A pending invitation, identified by its token
+    Invitation {
+        token: String,
+        project: DbModelId,
+        inviter: DbModelId,
+    },
+    /// The target no longer exists; its table and id when still known
+    Deleted {
+        table: Option<String>,
+        id: Option<DbModelId>,
+    },
+}
+
+/// Target of a notification as stored, with the records on the path to it.
+/// Path fields not matching the target's table are `None`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct NotificationTarget {
+    /// The notification
+    pub id: Option<Thing>,
+    pub target: Option<Thing>,
+    /// Set while the target record exists
+    pub live: Option<Thing>,
+    pub task_list: Option<Thing>,
+    pub task_list_owner: Option<Thing>,
+    pub agenda: Option<Thing>,
+    pub agenda_owner: Option<Thing>,
+    pub requirement_project: Option<Thing>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -18,6 +62,12 @@ pub struct Notification {
     pub title: String,
     pub content: String,
     pub handled: bool,
+    /// What triggered the notification, e.g. `task_assigned` or `mention`
+    #[serde(default)]
+    pub kind: String,
+    /// Set on insertion; missing on notifications that predate it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<Datetime>,
 }
 
 impl Notification {
@@ -27,6 +77,71 @@ impl Notification {
             title,
             content,
             handled: false,
+            kind: String::new(),
+            created_at: None,
         }
     }
-}
\ No newline at end of file
+}
+
+fn default_channels() -> Vec<String> {
+    vec!["in_app".to_owned()]
+}
+
+/// What a user wants to hear about and where. Stored under the user's id; users
+/// without a record get everything in-app.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct NotificationPreference {
+    pub id: Option<Thing>,
+    /// Categories the user opted out of, e.g. `mention` or `deadline`
+    #[serde(default)]
+    pub disabled: Vec<String>,
+    /// Projects nothing is delivered from
+    #[serde(default)]
+    pub muted_projects: Vec<DbModelId>,
+    /// Any of `in_app`, `email` and `email_digest`
+    #[serde(default = "default_channels")]
+    pub channels: Vec<String>,
+}
+
+impl Default for NotificationPreference {
+    fn default() -> Self {
+        NotificationPreference {
+            id: None,
+            disabled: vec![],
+            muted_projects: vec![],
+            channels: default_channels(),
+        }
+    }
+}
+
+/// A notification waiting to be emailed, right away or with the next digest
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutgoingMail {
+    pub id: Option<Thing>,
+    pub user: DbModelId,
+    pub title: String,
+    pub content: String,
+    pub kind: String,
+    /// The in-app copy; digests skip mails whose copy was handled meanwhile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notification: Option<Thing>,
+    /// Sent on its own instead of with the digest
+    #[serde(default)]
+    pub immediate: bool,
+    pub created_at: Datetime,
+}
+
+impl OutgoingMail {
+    pub fn new(user_id: &str, notif: &Notification, immediate: bool) -> Self {
+        OutgoingMail {
+            id: None,
+            user: user_id.to_owned(),
+            title: notif.title.clone(),
+            content: notif.content.clone(),
+            kind: notif.kind.clone(),
+            notification: notif.id.clone(),
+            immediate,
+            created_at: Datetime(chrono::Utc::now()),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::{Datetime, Thing};
+
+use crate::db::repository::utils::DbModelId;
+
+/// Endpoint outside the app that hears about activity in a project
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OutgoingWebhook {
+    pub id: Option<Thing>,
+    pub project: DbModelId,
+    pub url: String,
+    /// Key payloads are signed with
+    pub secret: String,
+    /// Event types sent, e.g. `task_created`
+    pub events: Vec<String>,
+    /// `json`, `slack` (also Mattermost) or `discord`
+    pub format: String,
+    pub active: bool,
+    pub created_at: Datetime,
+}
+
+/// One payload sent to a webhook, with the outcome of its last attempt
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WebhookDelivery {
+    pub id: Option<Thing>,
+    pub webhook: DbModelId,
+    pub event: String,
+    pub body: String,
+    pub attempts: u32,
+    /// HTTP status of the last attempt, if the endpoint answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub delivered: bool,
+    pub created_at: Datetime,
+}
+
+impl WebhookDelivery {
+    pub fn new(webhook: DbModelId, event: String, body: String) -> Self {
+        WebhookDelivery {
+            id: None,
+            webhook,
+            event,
+            body,
+            attempts: 0,
+            status: None,
+            error: None,
+            delivered: false,
+            created_at: Datetime(Utc::now()),
+        }
+    }
+}
@@ -11,6 +11,28 @@ pub struct Project {
     pub avatar: Option<String>,
     pub status_pool: StatusPool,
     pub github: i64,
+    #[serde(default)]
+    pub labels: Vec<Label>,
+    #[serde(default)]
+    pub custom_fields: Vec<CustomFieldDef>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Label {
+    pub id: String,
+    pub name: String,
+    pub color: String,
+}
+
+/// Custom task field defined by a project. `kind` is one of `text`, `number`, `date`,
+/// `single_select` or `multi_select`; `options` only apply to the select kinds.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CustomFieldDef {
+    pub id: String,
+    pub name: String,
+    pub kind: String,
+    #[serde(default)]
+    pub options: Vec<String>,
 }
 
 
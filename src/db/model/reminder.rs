@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::{Datetime, Thing};
+
+use crate::db::repository::utils::DbModelId;
+
+/// Record of a reminder the scheduler already sent, so each user hears about a
+/// deadline once per offset. A changed deadline gets reminders of its own.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SentReminder {
+    pub id: Option<Thing>,
+    /// Task or event the reminder is about
+    pub about: Thing,
+    pub user: DbModelId,
+    /// `reminder` or `overdue`
+    pub kind: String,
+    /// Minutes before `due`; 0 for overdue notices
+    pub offset: i64,
+    pub due: Datetime,
+}
@@ -5,12 +5,38 @@ pub struct Status {
     pub name: String,
     pub description: String,
     pub number: String,
+    /// Maximum number of tasks in this status, only meaningful for incomplete statuses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wip_limit: Option<u32>,
+    /// Apply `wip_limit` to each assignee instead of the whole pool
+    #[serde(default)]
+    pub wip_per_assignee: bool,
 }
 
 #[derive(Deserialize, Clone, Serialize, Debug, Default)]
 pub struct StatusPool {
     pub incomplete: Vec<Status>,
     pub complete: Status,
+    /// Transition rules; statuses without a rule can be entered from anywhere
+    #[serde(default)]
+    pub workflow: Vec<StatusRule>,
+    /// Reject changes exceeding a WIP limit instead of only warning
+    #[serde(default)]
+    pub wip_enforced: bool,
+}
+
+/// Conditions for entering the status `to`, a status number or `complete`
+#[derive(Deserialize, Clone, Serialize, Debug, Default, PartialEq)]
+pub struct StatusRule {
+    pub to: String,
+    /// Statuses the task may come from, any when `None`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Vec<String>>,
+    /// Any of `pr`, `assignees`, `description`, `deadline`
+    #[serde(default)]
+    pub required_fields: Vec<String>,
+    #[serde(default)]
+    pub admin_only: bool,
 }
 
 impl Status {
@@ -19,6 +45,8 @@ impl Status {
             name: "complete".to_string(),
             description: "description".to_owned(),
             number: String::new(),
+            wip_limit: None,
+            wip_per_assignee: false,
         }
     }
 }
@@ -28,6 +56,8 @@ impl StatusPool {
         StatusPool {
             incomplete: vec![Status::new()],
             complete: Status::new(),
+            workflow: vec![],
+            wip_enforced: false,
         }
     }
 }
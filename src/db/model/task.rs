@@ -1,3 +1,4 @@
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use surrealdb::sql::{Datetime, Thing};
 
@@ -20,6 +21,80 @@ pub struct Task {
     pub pr: PullRequest, 
     pub pr_number: i64,
     pub pr_assigned: bool,
+    /// One of `low`, `medium`, `high`, `urgent`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    /// Ids of project labels
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub custom_fields: Vec<CustomFieldValue>,
+    /// Estimated effort in minutes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<u32>,
+    /// Completing the task spawns its next occurrence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<Recurrence>,
+    /// First task of the recurring series this task was spawned in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub series: Option<DbModelId>,
+    /// Number of earlier occurrences in the series
+    #[serde(default)]
+    pub occurrence: u32,
+    /// Manual position inside the task list, ascending
+    #[serde(default)]
+    pub rank: i64,
+    /// Complete the task once all of its subtasks are complete
+    #[serde(default)]
+    pub auto_complete: bool,
+    /// Filled from the `subtask` edge on query, never stored
+    #[serde(skip_serializing, default)]
+    pub parent: Option<DbModelId>,
+    #[serde(skip_serializing, default)]
+    pub progress: Option<TaskProgress>,
+}
+
+/// Subset of an RFC 5545 RRULE
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Default)]
+pub struct Recurrence {
+    /// One of `daily`, `weekly`, `monthly`
+    pub freq: String,
+    pub interval: u32,
+    /// Weekdays of weekly rules, `MO` to `SU`
+    #[serde(default)]
+    pub by_day: Vec<String>,
+    /// Day of month of monthly rules
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by_month_day: Option<u32>,
+    /// Total number of occurrences
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<Datetime>,
+}
+
+/// Value of a project defined custom field on a task
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CustomFieldValue {
+    pub field: String,
+    pub value: FieldValue,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum FieldValue {
+    Text(String),
+    Number(f64),
+    Date(Datetime),
+    SingleSelect(String),
+    MultiSelect(Vec<String>),
+}
+
+/// Rollup of the direct subtasks of a task
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq)]
+pub struct TaskProgress {
+    pub complete: u32,
+    pub total: u32,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -33,6 +108,24 @@ pub struct TaskLink {
     pub kind: String,
 }
 
+/// Append-only history entry of a task. `source` is `user` (with `actor`), `system` or `github`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TaskChange {
+    pub id: Option<Thing>,
+    pub task: DbModelId,
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actor: Option<DbModelId>,
+    pub changes: Vec<FieldChange>,
+    pub created_at: Datetime,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: String,
+    pub new: String,
+}
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TaskList {
@@ -55,6 +148,17 @@ impl Task {
             pr_number: 0,
             pr_assigned: false,
             pr: PullRequest::default(),
+            priority: None,
+            labels: vec![],
+            custom_fields: vec![],
+            estimate: None,
+            recurrence: None,
+            series: None,
+            occurrence: 0,
+            rank: 0,
+            auto_complete: false,
+            parent: None,
+            progress: None,
         }
     }
 }
@@ -79,3 +183,16 @@ impl TaskList {
     }
 }
 
+
+impl TaskChange {
+    pub fn new(task: DbModelId, source: &str, actor: Option<DbModelId>, changes: Vec<FieldChange>) -> Self {
+        Self {
+            id: None,
+            task,
+            source: source.to_owned(),
+            actor,
+            changes,
+            created_at: Datetime(Utc::now()),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::{Datetime, Thing};
+
+use crate::db::repository::utils::DbModelId;
+
+/// Reusable snapshot of a task list with its tasks and links, owned by a project or user.
+/// Tasks and links refer to each other by template-local keys.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Template {
+    pub id: Option<Thing>,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub owner: Thing,
+    pub tasks: Vec<TemplateTask>,
+    #[serde(default)]
+    pub links: Vec<TemplateLink>,
+    pub created_at: Datetime,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Default)]
+pub struct TemplateTask {
+    pub key: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Minutes from instantiation to the deadline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline_offset: Option<i64>,
+    #[serde(default)]
+    pub assignees: Vec<DbModelId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<u32>,
+    #[serde(default)]
+    pub auto_complete: bool,
+    /// Key of the parent task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+/// Link between two template tasks; `kind` is `auto` or `dep`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct TemplateLink {
+    pub from: String,
+    pub to: String,
+    pub kind: String,
+}
+
+impl Template {
+    pub fn new(name: String, description: String, owner: Thing) -> Self {
+        Self {
+            id: None,
+            name,
+            description,
+            owner,
+            tasks: vec![],
+            links: vec![],
+            created_at: Datetime(Utc::now()),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::{Datetime, Thing};
+
+use crate::db::repository::utils::DbModelId;
+
+/// Time a user spent on a task. `ended_at` is empty while the timer is running.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct TimeEntry {
+    pub id: Option<Thing>,
+    pub task: DbModelId,
+    pub user: DbModelId,
+    pub started_at: Datetime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<Datetime>,
+    #[serde(default)]
+    pub note: String,
+    /// Entered by hand rather than with the timer
+    #[serde(default)]
+    pub manual: bool,
+}
+
+impl TimeEntry {
+    pub fn start(task: DbModelId, user: DbModelId) -> Self {
+        Self {
+            id: None,
+            task,
+            user,
+            started_at: Datetime(Utc::now()),
+            ended_at: None,
+            note: String::new(),
+            manual: false,
+        }
+    }
+
+    /// Whole minutes tracked, counting a running timer up to `now`
+    pub fn minutes(&self, now: DateTime<Utc>) -> i64 {
+        let end = self.ended_at.as_ref().map_or(now, |end| end.0);
+        (end - self.started_at.0).num_minutes().max(0)
+    }
+}
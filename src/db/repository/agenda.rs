@@ -1,6 +1,8 @@
 use std::{f64::consts::E, io};
 
-use surrealdb::sql::{Id, Thing};
+use futures::future::try_join_all;
+use serde::Deserialize;
+use surrealdb::sql::{Datetime, Id, Thing};
 
 use crate::db::{
     db_context::DbContext,
@@ -10,6 +12,12 @@ use crate::db::{
 
 use crate::db::repository::utils::*;
 
+#[derive(Deserialize)]
+struct EventInAgenda {
+    id: Thing,
+    agenda: Thing,
+}
+
 #[derive(Clone)]
 pub struct AgendaRepository {
     pub context: DbContext,
@@ -41,19 +49,6 @@ impl AgendaRepository {
         Ok(events)
     }
 
-    pub async fn query_agenda_by_event_id(&self, event_id: &str) -> Result<DbModelId, io::Error> {
-        let mut response = exec_query(
-            &self.context, 
-            format!("(SELECT <-plan<-agenda as event FROM agenda WHERE id == event:{}).event", event_id))
-            .await?;
-        let agenda = response
-            .take::<Vec<Thing>>(0)
-            .map_err(get_io_error)?
-            .pop()
-            .ok_or(custom_io_error("Agenda is not found"))?;
-        Ok(agenda.id.to_string())
-    }
-
     pub async fn query_agenda_source_by_id(&self, agenda_id: &str) -> Result<DbModelId, io::Error> {
         let mut response = exec_query(
             &self.context, 
@@ -67,12 +62,6 @@ impl AgendaRepository {
         Ok(source.id.to_string())
     }
 
-    pub async fn query_event_path_by_id(&self, event_id: &str) -> Result<(DbModelId, DbModelId), io::Error> {
-        let task_list = self.query_agenda_by_event_id(event_id).await?;
-        let source_id = self.query_agenda_source_by_id(&task_list).await?;
-        Ok((task_list, source_id))
-    }
-
     pub async fn query_event_id_by_agenda_id(
         &self,
         agenda_id: &str,
@@ -268,4 +257,84 @@ impl AgendaRepository {
             .map_err(|e| get_io_error(e))?;
         Ok(event)
     }
+
+    /// Events of project agendas starting between `from` and `until`
+    pub async fn query_project_events_starting_between(
+        &self,
+        from: Datetime,
+        until: Datetime,
+    ) -> Result<Vec<Event>, io::Error> {
+        let mut response = self
+            .context
+            .db
+            .query(
+                "SELECT * FROM event WHERE start_time >= $from AND start_time <= $until \
+                AND count(<-plan<-agenda<-own<-project) > 0",
+            )
+            .bind(("from", from))
+            .bind(("until", until))
+            .await
+            .map_err(get_io_error)?;
+        response.take::<Vec<Event>>(0).map_err(get_io_error)
+    }
+
+    /// Ids of the events among `events` that are assignees' copies of a project event
+    pub async fn query_event_copies(
+        &self,
+        events: &[DbModelId],
+    ) -> Result<Vec<DbModelId>, io::Error> {
+        let events: Vec<_> = events
+            .iter()
+            .map(|id| Thing::from(("event", id.as_str())))
+            .collect();
+        let mut response = self
+            .context
+            .db
+            .query(
+                "SELECT VALUE id FROM event \
+                WHERE id INSIDE $events AND count(->event_follow) > 0",
+            )
+            .bind(("events", events))
+            .await
+            .map_err(get_io_error)?;
+        let copies = response.take::<Vec<Thing>>(0).map_err(get_io_error)?;
+        Ok(unwrap_things(copies))
+    }
+
+    /// Events of the given agendas whose name or description contains `keyword`,
+    /// paired with the id of the agenda planning them.
+    pub async fn search_events_in_agendas(
+        &self,
+        agendas: &[DbModelId],
+        keyword: &str,
+    ) -> Result<Vec<(Event, DbModelId)>, io::Error> {
+        let agendas: Vec<_> = agendas
+            .iter()
+            .map(|id| Thing::from(("agenda", id.as_str())))
+            .collect();
+        let mut response = self
+            .context
+            .db
+            .query(
+                "SELECT id, (<-plan<-agenda)[0] AS agenda FROM event \
+                WHERE (<-plan<-agenda)[0] INSIDE $agendas \
+                AND (string::contains(string::lowercase(name), $keyword) \
+                OR string::contains(string::lowercase(description), $keyword))",
+            )
+            .bind(("agendas", agendas))
+            .bind(("keyword", keyword.to_lowercase()))
+            .await
+            .map_err(get_io_error)?;
+        let found = response
+            .take::<Vec<EventInAgenda>>(0)
+            .map_err(get_io_error)?;
+        let futures = found
+            .into_iter()
+            .map(|found| async move {
+                let event = self.query_event_by_id(&unwrap_thing(found.id)).await?;
+                Ok::<_, io::Error>((event, unwrap_thing(found.agenda)))
+            })
+            .collect::<Vec<_>>();
+        try_join_all(futures).await
+    }
 }
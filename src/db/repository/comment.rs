@@ -0,0 +1,101 @@
+use std::io;
+
+use surrealdb::sql::Thing;
+
+use crate::db::{db_context::DbContext, model::comment::Comment};
+
+use super::utils::{
+    create_resource, delete_resource, exec_query, get_io_error, get_str_id, select_resourse,
+    unwrap_thing, update_resource, DbModelId,
+};
+
+#[derive(Clone)]
+pub struct CommentRepository {
+    pub context: DbContext,
+}
+
+impl CommentRepository {
+    pub async fn new() -> Self {
+        Self {
+            context: DbContext::new().await,
+        }
+    }
+
+    pub async fn query_comment_by_id(&self, comment_id: &str) -> Result<Comment, io::Error> {
+        select_resourse(&self.context, comment_id, "comment").await
+    }
+
+    /// All comments of a task, replies included, oldest first
+    pub async fn query_comments_by_task_id(&self, task_id: &str) -> Result<Vec<Comment>, io::Error> {
+        let mut response = exec_query(
+            &self.context,
+            format!("SELECT * FROM comment WHERE <-discuss<-task CONTAINS task:{task_id} ORDER BY created_at ASC"),
+        )
+        .await?;
+        response.take::<Vec<Comment>>(0).map_err(get_io_error)
+    }
+
+    pub async fn query_task_id_by_comment(&self, comment_id: &str) -> Result<DbModelId, io::Error> {
+        let mut response = exec_query(
+            &self.context,
+            format!("SELECT <-discuss<-task as tasks FROM comment WHERE id == comment:{comment_id}"),
+        )
+        .await?;
+        let mut tasks = response
+            .take::<Option<Vec<Thing>>>("tasks")
+            .map_err(get_io_error)?
+            .unwrap_or_default();
+        // a missing comment has no parent task either
+        let task = tasks
+            .pop()
+            .ok_or(io::Error::new(io::ErrorKind::NotFound, "Comment is not found"))?;
+        Ok(unwrap_thing(task))
+    }
+
+    pub async fn insert_comment_for_task(
+        &self,
+        task_id: &str,
+        comment: &Comment,
+    ) -> Result<Comment, io::Error> {
+        let comment = create_resource(&self.context, comment, "comment").await?;
+        let _ = exec_query(
+            &self.context,
+            format!(
+                "relate task:{task_id} -> discuss -> comment:{}",
+                get_str_id(&comment.id)
+            ),
+        )
+        .await?;
+        Ok(comment)
+    }
+
+    pub async fn update_comment(
+        &self,
+        comment_id: &str,
+        comment: &Comment,
+    ) -> Result<Comment, io::Error> {
+        update_resource(&self.context, comment_id, comment, "comment").await
+    }
+
+    /// Deletes a comment. One with replies is blanked instead, keeping the replies of
+    /// other users in place.
+    pub async fn delete_comment(&self, comment_id: &str) -> Result<Comment, io::Error> {
+        let mut response = self
+            .context
+            .db
+            .query("SELECT VALUE id FROM comment WHERE reply_to == $comment LIMIT 1")
+            .bind(("comment", comment_id))
+            .await
+            .map_err(get_io_error)?;
+        let replies = response.take::<Vec<Thing>>(0).map_err(get_io_error)?;
+        if replies.is_empty() {
+            return delete_resource(&self.context, comment_id, "comment").await;
+        }
+        let comment = Comment {
+            content: String::new(),
+            deleted: true,
+            ..self.query_comment_by_id(comment_id).await?
+        };
+        self.update_comment(comment_id, &comment).await
+    }
+}
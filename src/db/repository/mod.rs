@@ -1,9 +1,12 @@
 pub mod agenda;
+pub mod comment;
 pub mod draft;
 pub mod notification;
+pub mod outgoing_webhook;
 pub mod project;
 pub mod requirement;
 pub mod task;
+pub mod template;
 pub mod user;
 pub mod utils;
 
@@ -16,6 +19,7 @@ mod test_user {
 
     use crate::db::model::notification::Notification;
     use crate::usecase::notification::query_notif_by_id;
+    use crate::usecase::invitation_token::InvitationTokenRepository;
     use crate::usecase::user::insert_user;
     use crate::db::{
             model::{
@@ -25,12 +29,12 @@ mod test_user {
             repository::{
                 agenda::AgendaRepository,
                 draft::DraftRepository,
-                notification::NotificationRepository,
+                notification::{NotificationQuery, NotificationRepository},
                 project::ProjectRepository,
                 requirement::RequirementRepository,
                 task::TaskRepository,
                 user::UserRepository,
-                utils::unwrap_thing,
+                utils::{get_str_id, unwrap_thing},
             },
         };
 
@@ -122,6 +126,8 @@ mod test_user {
                 avatar: None,
                 status_pool: StatusPool::new(),
                 github: 0,
+                labels: vec![],
+                custom_fields: vec![],
             })
             .await
             .unwrap();
@@ -217,6 +223,8 @@ mod test_user {
             avatar: None,
             status_pool: StatusPool::default(),
             github: 0,
+            labels: vec![],
+            custom_fields: vec![],
         };
         let result = repo.update_project(&project, "test").await.unwrap();
         assert_eq!(result.name, "xiwen");
@@ -360,9 +368,8 @@ mod test_user {
     #[tokio::test]
     async fn test_query_notif_by_id() {
         let notif_repo = NotificationRepository::new().await;
-        let task_repo = TaskRepository::new().await;
-        let agenda_repo = AgendaRepository::new().await;
-        let (notif, _) = query_notif_by_id(&notif_repo, &task_repo, &agenda_repo, "xiwen").await.unwrap();
+        let invitations = InvitationTokenRepository::default();
+        let (notif, _) = query_notif_by_id(&notif_repo, &invitations, "xiwen").await.unwrap();
         assert_eq!(notif.title, "xiwen");
     }
 
@@ -374,9 +381,13 @@ mod test_user {
     }
 
     #[tokio::test]
-    async fn test_query_notif_by_user_id() {
-        let repo = UserRepository::new().await;
-        let result = repo.query_notif_by_user_id("xiwen").await.unwrap();
+    async fn test_query_notifs_of_user() {
+        let repo = NotificationRepository::new().await;
+        let query = NotificationQuery {
+            limit: 100,
+            ..Default::default()
+        };
+        let result = repo.query_notifs_of_user("xiwen", &query).await.unwrap();
         assert!(result.contains(&"qqvxafo5l6vkp7etig3b".to_owned()));
     }
 
@@ -464,6 +475,50 @@ mod test_user {
         assert_eq!(result.name, "insert task list test")
     }
 
+    #[tokio::test]
+    async fn test_set_parent_of_task() {
+        let repo = TaskRepository::new().await;
+        let parent = repo
+            .insert_task_for_task_list(&Task::new("parent".to_string()), "xiwen")
+            .await
+            .unwrap();
+        let child = repo
+            .insert_task_for_task_list(&Task::new("child".to_string()), "xiwen")
+            .await
+            .unwrap();
+        let (parent_id, child_id) = (get_str_id(&parent.id), get_str_id(&child.id));
+        repo.set_parent_of_task(&child_id, &parent_id).await.unwrap();
+
+        let child = repo.query_task_by_id(&child_id).await.unwrap();
+        assert_eq!(child.parent, Some(parent_id.clone()));
+        let parent = repo.query_task_by_id(&parent_id).await.unwrap();
+        assert_eq!(parent.progress.unwrap().total, 1);
+        assert_eq!(repo.query_descendants_of_task(&parent_id).await.unwrap(), vec![child_id]);
+    }
+
+    #[tokio::test]
+    async fn test_update_task_ranks() {
+        let repo = TaskRepository::new().await;
+        let list = repo
+            .insert_task_list_for_project("xiwen", "rank test")
+            .await
+            .unwrap();
+        let list_id = get_str_id(&list.id);
+        let mut tasks = vec![];
+        for name in ["first", "second"] {
+            let task = repo
+                .insert_task_for_task_list(&Task::new(name.to_string()), &list_id)
+                .await
+                .unwrap();
+            tasks.push(get_str_id(&task.id));
+        }
+        assert_eq!(repo.query_all_tasks_of_task_list(&list_id).await.unwrap(), tasks);
+
+        tasks.reverse();
+        repo.update_task_ranks(&tasks).await.unwrap();
+        assert_eq!(repo.query_all_tasks_of_task_list(&list_id).await.unwrap(), tasks);
+    }
+
     #[tokio::test]
     async fn test_query_task_is_following() {
         let repo = TaskRepository::new().await;
@@ -1,23 +1,86 @@
-use std::io;
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, Mutex},
+};
 
-use surrealdb::sql::Thing;
+use chrono::Utc;
+use surrealdb::sql::{Datetime, Thing};
+use tokio::sync::broadcast;
 
 use crate::db::{
     db_context::DbContext,
-    model::notification::{AssetPath, Notification, NotificationSource},
+    model::{
+        notification::{
+            Notification, NotificationPreference, NotificationTarget, OutgoingMail,
+        },
+        reminder::SentReminder,
+    },
+};
+
+use super::utils::{
+    create_resource, exec_query, get_io_error, get_str_id, select_resourse,
+    unwrap_thing, unwrap_things, update_resource, DbModelId,
 };
 
-use super::utils::{create_resource, custom_io_error, exec_query, get_io_error, unwrap_thing};
+/// What the push channel of a user hears about
+#[derive(Clone, Debug)]
+pub enum NotificationEvent {
+    Inserted(DbModelId),
+    /// Notifications were handled or removed, so the unread count moved
+    Updated,
+}
+
+/// Fans notification events out to the push channels of logged-in users
+#[derive(Clone, Default)]
+pub struct NotificationHub {
+    channels: Arc<Mutex<HashMap<DbModelId, broadcast::Sender<NotificationEvent>>>>,
+}
+
+impl NotificationHub {
+    pub fn subscribe(&self, user_id: &str) -> broadcast::Receiver<NotificationEvent> {
+        let mut channels = self.channels.lock().unwrap();
+        channels
+            .entry(user_id.to_owned())
+            .or_insert_with(|| broadcast::channel(64).0)
+            .subscribe()
+    }
+
+    pub fn publish(&self, user_id: &str, event: NotificationEvent) {
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(sender) = channels.get(user_id) {
+            // nobody listens any more
+            if sender.send(event).is_err() {
+                channels.remove(user_id);
+            }
+        }
+    }
+}
+
+/// One page of a user's notifications, newest first
+#[derive(Clone, Debug, Default)]
+pub struct NotificationQuery {
+    /// Id of the last notification of the previous page
+    pub cursor: Option<DbModelId>,
+    pub limit: usize,
+    pub handled: Option<bool>,
+    /// Table of what the notification is about: `task`, `event`, `draft`, `requirement`,
+    /// `project` or `invitation`
+    pub source: Option<String>,
+    pub project: Option<DbModelId>,
+}
 
 #[derive(Clone)]
 pub struct NotificationRepository {
     pub context: DbContext,
+    pub hub: NotificationHub,
 }
 
 impl NotificationRepository {
     pub async fn new() -> Self {
         Self {
             context: DbContext::new().await,
+            hub: NotificationHub::default(),
         }
     }
 
@@ -31,7 +94,55 @@ impl NotificationRepository {
             .select(("notification", id))
             .await
             .map_err(get_io_error)?;
-        Ok(notif.ok_or(custom_io_error("Notification find failed"))?)
+        notif.ok_or(io::Error::new(io::ErrorKind::NotFound, "Notification is not found"))
+    }
+
+    /// The notifications with their targets, in one round trip and in the order of `ids`.
+    /// Notifications that no longer exist are left out.
+    pub async fn query_notifs_with_targets(
+        &self,
+        ids: &[DbModelId],
+    ) -> Result<Vec<(Notification, NotificationTarget)>, io::Error> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let notifs = ids
+            .iter()
+            .map(|id| format!("notification:{id}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut response = exec_query(
+            &self.context,
+            format!(
+                "SELECT * FROM {notifs}; \
+                SELECT id, \
+                    (->about.out)[0] AS target, \
+                    (->about.out.id)[0] AS live, \
+                    (->about->task<-have<-task_list)[0] AS task_list, \
+                    (->about->task<-have<-task_list<-own<-?)[0] AS task_list_owner, \
+                    (->about->event<-plan<-agenda)[0] AS agenda, \
+                    (->about->event<-plan<-agenda<-own<-?)[0] AS agenda_owner, \
+                    (->about->requirement<-require<-project)[0] AS requirement_project \
+                FROM {notifs};"
+            ),
+        )
+        .await?;
+        let found = response.take::<Vec<Notification>>(0).map_err(get_io_error)?;
+        let mut targets = response.take::<Vec<NotificationTarget>>(1).map_err(get_io_error)?;
+
+        let mut result = vec![];
+        for id in ids {
+            let Some(notif) = found.iter().find(|notif| get_str_id(&notif.id) == *id) else {
+                continue;
+            };
+            let target = targets
+                .iter()
+                .position(|target| get_str_id(&target.id) == *id)
+                .map(|i| targets.swap_remove(i))
+                .unwrap_or_default();
+            result.push((notif.clone(), target));
+        }
+        Ok(result)
     }
 
     pub async fn handle_notif_by_id(&self, id: &str) -> Result<Notification, io::Error> {
@@ -57,6 +168,10 @@ impl NotificationRepository {
         about_table: &str, 
         notif: Notification,
     ) -> Result<Notification, io::Error> {
+        let notif = Notification {
+            created_at: Some(Datetime(Utc::now())),
+            ..notif
+        };
         let notif = create_resource(
             &self.context,
             &notif,
@@ -81,6 +196,324 @@ impl NotificationRepository {
             ),
         )
         .await?;
+        self.hub.publish(
+            user_id,
+            NotificationEvent::Inserted(unwrap_thing(notif.id.clone().unwrap())),
+        );
         Ok(notif)
     }
+
+    pub async fn count_unhandled_by_user(&self, user_id: &str) -> Result<usize, io::Error> {
+        let mut response = exec_query(
+            &self.context,
+            format!(
+                "SELECT VALUE count(->notified_by->notification[WHERE handled == false]) \
+                FROM user:{user_id}"
+            ),
+        )
+        .await?;
+        let count = response.take::<Vec<usize>>(0).map_err(get_io_error)?;
+        Ok(count.first().copied().unwrap_or_default())
+    }
+
+    /// Notifications of the user created after `since`, oldest first
+    pub async fn query_notifs_of_user_since(
+        &self,
+        user_id: &str,
+        since: Datetime,
+    ) -> Result<Vec<DbModelId>, io::Error> {
+        let mut response = self
+            .context
+            .db
+            .query(format!(
+                "SELECT id, created_at FROM notification \
+                WHERE created_at > $since AND <-notified_by<-user CONTAINS user:{user_id} \
+                ORDER BY created_at"
+            ))
+            .bind(("since", since))
+            .await
+            .map_err(get_io_error)?;
+        let notifs = response.take::<Vec<Thing>>((0, "id")).map_err(get_io_error)?;
+        Ok(unwrap_things(notifs))
+    }
+
+    pub async fn is_reminder_sent(&self, reminder: &SentReminder) -> Result<bool, io::Error> {
+        let mut response = self
+            .context
+            .db
+            .query(
+                "SELECT * FROM reminder WHERE about == $about AND user == $user \
+                AND kind == $kind AND offset == $offset AND due == $due LIMIT 1",
+            )
+            .bind(("about", reminder.about.clone()))
+            .bind(("user", reminder.user.clone()))
+            .bind(("kind", reminder.kind.clone()))
+            .bind(("offset", reminder.offset))
+            .bind(("due", reminder.due.clone()))
+            .await
+            .map_err(get_io_error)?;
+        let sent = response.take::<Vec<SentReminder>>(0).map_err(get_io_error)?;
+        Ok(!sent.is_empty())
+    }
+
+    pub async fn insert_sent_reminder(
+        &self,
+        reminder: &SentReminder,
+    ) -> Result<SentReminder, io::Error> {
+        create_resource(&self.context, reminder, "reminder").await
+    }
+
+    /// Preferences of the user, or the defaults if they never set any
+    pub async fn query_preference(
+        &self,
+        user_id: &str,
+    ) -> Result<NotificationPreference, io::Error> {
+        match select_resourse(&self.context, user_id, "notification_preference").await {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Default::default()),
+            result => result,
+        }
+    }
+
+    pub async fn update_preference(
+        &self,
+        user_id: &str,
+        preference: &NotificationPreference,
+    ) -> Result<NotificationPreference, io::Error> {
+        let preference = NotificationPreference {
+            id: None,
+            ..preference.clone()
+        };
+        update_resource(&self.context, user_id, &preference, "notification_preference").await
+    }
+
+    /// Project the task, event or requirement belongs to; `None` for personal ones
+    pub async fn query_project_of_about(
+        &self,
+        about_table: &str,
+        about_id: &str,
+    ) -> Result<Option<DbModelId>, io::Error> {
+        let path = match about_table {
+            "task" => "<-have<-task_list<-own<-project",
+            "event" => "<-plan<-agenda<-own<-project",
+            "requirement" => "<-require<-project",
+            _ => return Ok(None),
+        };
+        let mut response = exec_query(
+            &self.context,
+            format!("SELECT {path} as projects FROM {about_table}:{about_id}"),
+        )
+        .await?;
+        let projects = response
+            .take::<Option<Vec<Thing>>>((0, "projects"))
+            .map_err(get_io_error)?
+            .unwrap_or_default();
+        Ok(projects.into_iter().next().map(unwrap_thing))
+    }
+
+    pub async fn insert_outgoing_mail(
+        &self,
+        mail: &OutgoingMail,
+    ) -> Result<OutgoingMail, io::Error> {
+        create_resource(&self.context, mail, "outgoing_mail").await
+    }
+
+    /// When digests last went out, `None` before the first ones
+    pub async fn query_last_digest(&self) -> Result<Option<Datetime>, io::Error> {
+        let mut response = self
+            .context
+            .db
+            .query("SELECT VALUE sent_at FROM mail_digest:last")
+            .await
+            .map_err(get_io_error)?;
+        response.take::<Option<Datetime>>(0).map_err(get_io_error)
+    }
+
+    pub async fn update_last_digest(&self, sent_at: Datetime) -> Result<(), io::Error> {
+        self.context
+            .db
+            .query("UPDATE mail_digest:last SET sent_at = $sent_at")
+            .bind(("sent_at", sent_at))
+            .await
+            .map_err(get_io_error)?
+            .check()
+            .map_err(get_io_error)?;
+        Ok(())
+    }
+
+    /// Queued mails, oldest first
+    pub async fn query_outgoing_mails(
+        &self,
+        immediate: bool,
+    ) -> Result<Vec<OutgoingMail>, io::Error> {
+        let mut response = self
+            .context
+            .db
+            .query("SELECT * FROM outgoing_mail WHERE immediate == $immediate ORDER BY created_at")
+            .bind(("immediate", immediate))
+            .await
+            .map_err(get_io_error)?;
+        response.take::<Vec<OutgoingMail>>(0).map_err(get_io_error)
+    }
+
+    pub async fn delete_outgoing_mails(&self, mails: &[OutgoingMail]) -> Result<(), io::Error> {
+        let ids: Vec<Thing> = mails.iter().filter_map(|mail| mail.id.clone()).collect();
+        self.context
+            .db
+            .query("DELETE outgoing_mail WHERE id INSIDE $ids")
+            .bind(("ids", ids))
+            .await
+            .map_err(get_io_error)?;
+        Ok(())
+    }
+
+    pub async fn query_notifs_of_user(
+        &self,
+        user_id: &str,
+        query: &NotificationQuery,
+    ) -> Result<Vec<DbModelId>, io::Error> {
+        let mut conditions = vec![format!("<-notified_by<-user CONTAINS user:{user_id}")];
+        let mut cursor = None;
+        if let Some(cursor_id) = &query.cursor {
+            let last = self._query_notif_by_id(cursor_id).await.map_err(|err| {
+                match err.kind() {
+                    // the page boundary was deleted, so the client has to start over
+                    io::ErrorKind::NotFound => io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Cursor notification \"{cursor_id}\" no longer exists"),
+                    ),
+                    _ => err,
+                }
+            })?;
+            conditions.push(
+                "(created_at < $before OR (created_at == $before AND id < $cursor))".to_owned(),
+            );
+            cursor = Some((last.created_at, last.id));
+        }
+        if let Some(handled) = query.handled {
+            conditions.push(format!("handled == {handled}"));
+        }
+        if let Some(source) = &query.source {
+            match source.as_str() {
+                "task" | "event" | "draft" | "requirement" | "project" | "invitation" => {
+                    conditions.push(format!("count(->about->{source}) > 0"))
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Unknown notification source \"{source}\""),
+                    ))
+                }
+            }
+        }
+        if query.project.is_some() {
+            conditions.push(
+                "(->about->task<-have<-task_list<-own<-project CONTAINS $project \
+                OR ->about->event<-plan<-agenda<-own<-project CONTAINS $project \
+                OR ->about->requirement<-require<-project CONTAINS $project \
+                OR ->about->draft<-own<-project CONTAINS $project \
+                OR ->about->project CONTAINS $project)"
+                    .to_owned(),
+            );
+        }
+        let (before, cursor) = cursor.unwrap_or_default();
+        let project = query
+            .project
+            .as_ref()
+            .map(|project| Thing::from(("project", project.as_str())));
+        let mut response = self
+            .context
+            .db
+            .query(format!(
+                "SELECT id, created_at FROM notification WHERE {} \
+                ORDER BY created_at DESC, id DESC LIMIT {}",
+                conditions.join(" AND "),
+                query.limit
+            ))
+            .bind(("before", before))
+            .bind(("cursor", cursor))
+            .bind(("project", project))
+            .await
+            .map_err(get_io_error)?;
+        let notifs = response.take::<Vec<Thing>>((0, "id")).map_err(get_io_error)?;
+        Ok(unwrap_things(notifs))
+    }
+
+    /// Restricts a statement to notifications of the user, and to `ids` if given
+    fn owned_by(user_id: &str, ids: Option<&[DbModelId]>) -> (String, Vec<Thing>) {
+        let mut condition = format!("<-notified_by<-user CONTAINS user:{user_id}");
+        if ids.is_some() {
+            condition.push_str(" AND id INSIDE $ids");
+        }
+        let ids = ids
+            .unwrap_or_default()
+            .iter()
+            .map(|id| Thing::from(("notification", id.as_str())))
+            .collect();
+        (condition, ids)
+    }
+
+    /// Marks the user's notifications handled, all of them without `ids`.
+    /// Returns how many changed.
+    pub async fn handle_notifs_of_user(
+        &self,
+        user_id: &str,
+        ids: Option<&[DbModelId]>,
+    ) -> Result<usize, io::Error> {
+        let (condition, ids) = Self::owned_by(user_id, ids);
+        let mut response = self
+            .context
+            .db
+            .query(format!(
+                "UPDATE notification SET handled = true WHERE handled == false AND {condition}"
+            ))
+            .bind(("ids", ids))
+            .await
+            .map_err(get_io_error)?;
+        let notifs = response.take::<Vec<Notification>>(0).map_err(get_io_error)?;
+        Ok(notifs.len())
+    }
+
+    /// Deletes the user's notifications in `ids`, or all handled ones without `ids`.
+    /// Returns how many were deleted.
+    pub async fn delete_notifs_of_user(
+        &self,
+        user_id: &str,
+        ids: Option<&[DbModelId]>,
+    ) -> Result<usize, io::Error> {
+        let (mut condition, ids_bound) = Self::owned_by(user_id, ids);
+        if ids.is_none() {
+            condition.push_str(" AND handled == true");
+        }
+        let mut response = self
+            .context
+            .db
+            .query(format!("DELETE notification WHERE {condition} RETURN BEFORE"))
+            .bind(("ids", ids_bound))
+            .await
+            .map_err(get_io_error)?;
+        let notifs = response.take::<Vec<Notification>>(0).map_err(get_io_error)?;
+        Ok(notifs.len())
+    }
+
+    /// Marks every notification about the record handled, e.g. once an invitation is
+    /// answered. Returns the users whose notifications changed.
+    pub async fn handle_notifs_about(
+        &self,
+        about_table: &str,
+        about_id: &str,
+    ) -> Result<Vec<DbModelId>, io::Error> {
+        let mut response = self
+            .context
+            .db
+            .query(
+                "UPDATE notification SET handled = true \
+                WHERE handled == false AND ->about.out CONTAINS $about \
+                RETURN VALUE (<-notified_by<-user)[0]",
+            )
+            .bind(("about", Thing::from((about_table, about_id))))
+            .await
+            .map_err(get_io_error)?;
+        let users = response.take::<Vec<Thing>>(0).map_err(get_io_error)?;
+        Ok(unwrap_things(users))
+    }
 }
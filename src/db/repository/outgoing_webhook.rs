@@ -0,0 +1,112 @@
+use std::io;
+
+use crate::db::{
+    db_context::DbContext,
+    model::outgoing_webhook::{OutgoingWebhook, WebhookDelivery},
+};
+
+use super::utils::{
+    create_resource, delete_resource, get_io_error, select_resourse, update_resource,
+};
+
+#[derive(Clone)]
+pub struct OutgoingWebhookRepository {
+    pub context: DbContext,
+}
+
+impl OutgoingWebhookRepository {
+    pub async fn new() -> Self {
+        Self {
+            context: DbContext::new().await,
+        }
+    }
+
+    pub async fn insert_webhook(
+        &self,
+        webhook: &OutgoingWebhook,
+    ) -> Result<OutgoingWebhook, io::Error> {
+        create_resource(&self.context, webhook, "outgoing_webhook").await
+    }
+
+    pub async fn query_webhook_by_id(
+        &self,
+        webhook_id: &str,
+    ) -> Result<OutgoingWebhook, io::Error> {
+        select_resourse(&self.context, webhook_id, "outgoing_webhook").await
+    }
+
+    pub async fn query_webhooks_by_project(
+        &self,
+        project_id: &str,
+    ) -> Result<Vec<OutgoingWebhook>, io::Error> {
+        let mut response = self
+            .context
+            .db
+            .query("SELECT * FROM outgoing_webhook WHERE project == $project ORDER BY created_at")
+            .bind(("project", project_id.to_owned()))
+            .await
+            .map_err(get_io_error)?;
+        response.take::<Vec<OutgoingWebhook>>(0).map_err(get_io_error)
+    }
+
+    pub async fn update_webhook(
+        &self,
+        webhook_id: &str,
+        webhook: &OutgoingWebhook,
+    ) -> Result<OutgoingWebhook, io::Error> {
+        let webhook = OutgoingWebhook {
+            id: None,
+            ..webhook.clone()
+        };
+        update_resource(&self.context, webhook_id, &webhook, "outgoing_webhook").await
+    }
+
+    /// Deletes the webhook together with its delivery log
+    pub async fn delete_webhook(&self, webhook_id: &str) -> Result<OutgoingWebhook, io::Error> {
+        self.context
+            .db
+            .query("DELETE webhook_delivery WHERE webhook == $webhook")
+            .bind(("webhook", webhook_id.to_owned()))
+            .await
+            .map_err(get_io_error)?;
+        delete_resource(&self.context, webhook_id, "outgoing_webhook").await
+    }
+
+    pub async fn insert_delivery(
+        &self,
+        delivery: &WebhookDelivery,
+    ) -> Result<WebhookDelivery, io::Error> {
+        create_resource(&self.context, delivery, "webhook_delivery").await
+    }
+
+    pub async fn update_delivery(
+        &self,
+        delivery_id: &str,
+        delivery: &WebhookDelivery,
+    ) -> Result<WebhookDelivery, io::Error> {
+        let delivery = WebhookDelivery {
+            id: None,
+            ..delivery.clone()
+        };
+        update_resource(&self.context, delivery_id, &delivery, "webhook_delivery").await
+    }
+
+    /// Latest deliveries of the webhook, newest first
+    pub async fn query_deliveries_by_webhook(
+        &self,
+        webhook_id: &str,
+        limit: usize,
+    ) -> Result<Vec<WebhookDelivery>, io::Error> {
+        let mut response = self
+            .context
+            .db
+            .query(format!(
+                "SELECT * FROM webhook_delivery WHERE webhook == $webhook \
+                ORDER BY created_at DESC LIMIT {limit}"
+            ))
+            .bind(("webhook", webhook_id.to_owned()))
+            .await
+            .map_err(get_io_error)?;
+        response.take::<Vec<WebhookDelivery>>(0).map_err(get_io_error)
+    }
+}
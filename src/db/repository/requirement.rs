@@ -1,13 +1,22 @@
 use std::io;
 
+use futures::future::try_join_all;
+use serde::Deserialize;
+use surrealdb::sql::Thing;
 
 use crate::db::{db_context::DbContext, model::requirement::Requirement};
 
 use super::utils::{
-    create_resource, delete_resource, exec_query, get_io_error,
-    unwrap_thing, update_resource,
+    create_resource, delete_resource, exec_query, get_io_error, unwrap_thing, update_resource,
+    DbModelId,
 };
 
+#[derive(Deserialize)]
+struct RequirementInProject {
+    id: Thing,
+    project: Thing,
+}
+
 #[derive(Clone)]
 pub struct RequirementRepository {
     pub context: DbContext,
@@ -63,4 +72,41 @@ impl RequirementRepository {
     ) -> Result<Requirement, io::Error> {
         update_resource(&self.context, requ_id, requ, "requirement").await
     }
+
+    /// Requirements of the given projects whose name or content contains `keyword`,
+    /// paired with the id of the project requiring them.
+    pub async fn search_requs_in_projects(
+        &self,
+        projects: &[DbModelId],
+        keyword: &str,
+    ) -> Result<Vec<(Requirement, DbModelId)>, io::Error> {
+        let projects: Vec<_> = projects
+            .iter()
+            .map(|id| Thing::from(("project", id.as_str())))
+            .collect();
+        let mut response = self
+            .context
+            .db
+            .query(
+                "SELECT id, (<-require<-project)[0] AS project FROM requirement \
+                WHERE (<-require<-project)[0] INSIDE $projects \
+                AND (string::contains(string::lowercase(name), $keyword) \
+                OR string::contains(string::lowercase(description), $keyword))",
+            )
+            .bind(("projects", projects))
+            .bind(("keyword", keyword.to_lowercase()))
+            .await
+            .map_err(get_io_error)?;
+        let found = response
+            .take::<Vec<RequirementInProject>>(0)
+            .map_err(get_io_error)?;
+        let futures = found
+            .into_iter()
+            .map(|found| async move {
+                let requ = self.query_requ_by_id(&unwrap_thing(found.id)).await?;
+                Ok::<_, io::Error>((requ, unwrap_thing(found.project)))
+            })
+            .collect::<Vec<_>>();
+        try_join_all(futures).await
+    }
 }
@@ -1,21 +1,104 @@
 use std::io;
 
 use futures::future::try_join_all;
-use surrealdb::sql::Thing;
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::{Datetime, Thing};
+use tokio::sync::broadcast;
 
 use crate::db::{
     db_context::DbContext,
     model::{
         status::StatusPool,
-        task::{Task, TaskLink, TaskList},
+        task::{Task, TaskChange, TaskLink, TaskList, TaskProgress},
+        time_entry::TimeEntry,
     },
 };
 
 use super::utils::*;
 
+/// Task writes the server makes on its own, which no handler is around to report
+#[derive(Clone, Debug)]
+pub enum TaskEvent {
+    /// E.g. the next occurrence of a recurring task
+    Created(DbModelId),
+    /// E.g. completed or reopened by its predecessors or subtasks
+    Updated(DbModelId),
+}
+
+/// Fans task events out to whoever mirrors them to project feeds and webhooks
+#[derive(Clone)]
+pub struct TaskEventHub {
+    sender: broadcast::Sender<TaskEvent>,
+}
+
+impl Default for TaskEventHub {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(256).0,
+        }
+    }
+}
+
+impl TaskEventHub {
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(&self, event: TaskEvent) {
+        // nobody may be listening
+        let _ = self.sender.send(event);
+    }
+}
+
 #[derive(Clone)]
 pub struct TaskRepository {
     pub context: DbContext,
+    pub events: TaskEventHub,
+}
+
+#[derive(Serialize)]
+struct StatusUpdate {
+    task: Thing,
+    status: String,
+    complete: bool,
+}
+
+/// Everything a bulk patch writes for one task
+#[derive(Serialize)]
+pub struct BulkTaskWrite {
+    pub task: Thing,
+    pub content: Task,
+    pub assign: Vec<Thing>,
+    pub deassign: Vec<Thing>,
+    pub change: Option<TaskChange>,
+}
+
+/// Tasks a bulk patch moves into `task_list` and the ranks they end up with there
+#[derive(Serialize)]
+pub struct BulkTaskMove {
+    pub task_list: Thing,
+    /// Moved tasks together with their subtasks
+    pub tasks: Vec<Thing>,
+    /// Moved tasks that leave their parent behind
+    pub detached: Vec<Thing>,
+    pub ranks: Vec<TaskRank>,
+}
+
+#[derive(Serialize)]
+pub struct TaskRank {
+    pub task: Thing,
+    pub rank: i64,
+}
+
+#[derive(Deserialize)]
+struct RankedTask {
+    id: Thing,
+}
+
+#[derive(Deserialize)]
+struct TaskInList {
+    id: Thing,
+    task_list: Thing,
 }
 
 pub enum Entity {
@@ -27,6 +110,7 @@ impl TaskRepository {
     pub async fn new() -> Self {
         Self {
             context: DbContext::new().await,
+            events: TaskEventHub::default(),
         }
     }
 
@@ -89,15 +173,106 @@ impl TaskRepository {
             .unwrap_or_default();
 
         task.assignees = Some(unwrap_things(assignees));
+
+        let mut response = exec_query(
+            &self.context,
+            format!(
+                "SELECT (<-subtask<-task)[0] as parent, ->subtask->task.complete as children \
+                FROM task where id == task:{id}"
+            ),
+        )
+        .await?;
+        task.parent = response
+            .take::<Option<Thing>>((0, "parent"))
+            .map_err(get_io_error)?
+            .map(unwrap_thing);
+        let children = response
+            .take::<Option<Vec<bool>>>((0, "children"))
+            .map_err(get_io_error)?
+            .unwrap_or_default();
+        task.progress = match children.len() {
+            0 => None,
+            total => Some(TaskProgress {
+                complete: children.iter().filter(|complete| **complete).count() as u32,
+                total: total as u32,
+            }),
+        };
         Ok(task)
     }
 
+    pub async fn query_subtasks_of_task(&self, task_id: &str) -> Result<Vec<DbModelId>, io::Error> {
+        let mut response = exec_query(
+            &self.context,
+            format!("SELECT ->subtask->task as subtasks FROM task where id == task:{task_id}"),
+        )
+        .await?;
+        let subtasks = response
+            .take::<Option<Vec<Thing>>>((0, "subtasks"))
+            .map_err(get_io_error)?
+            .unwrap_or_default();
+        Ok(unwrap_things(subtasks))
+    }
+
+    /// Ids of every task below `task_id` in the hierarchy, parents before children
+    pub async fn query_descendants_of_task(
+        &self,
+        task_id: &str,
+    ) -> Result<Vec<DbModelId>, io::Error> {
+        let mut descendants = self.query_subtasks_of_task(task_id).await?;
+        let mut i = 0;
+        while i < descendants.len() {
+            let subtasks = self.query_subtasks_of_task(&descendants[i]).await?;
+            descendants.extend(subtasks);
+            i += 1;
+        }
+        Ok(descendants)
+    }
+
+    /// Makes `child_id` a subtask of `parent_id`, replacing its previous parent if any
+    pub async fn set_parent_of_task(&self, child_id: &str, parent_id: &str) -> Result<(), io::Error> {
+        let _ = exec_double_query(
+            &self.context,
+            format!("DELETE subtask WHERE out == task:{child_id}"),
+            format!("RELATE task:{parent_id} -> subtask -> task:{child_id}"),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn remove_parent_of_task(&self, child_id: &str) -> Result<(), io::Error> {
+        let _ = exec_query(
+            &self.context,
+            format!("DELETE subtask WHERE out == task:{child_id}"),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Moves the task into another task list, keeping its links and subtask edges
+    pub async fn move_task_to_task_list(
+        &self,
+        task_id: &str,
+        task_list_id: &str,
+    ) -> Result<(), io::Error> {
+        let _ = exec_double_query(
+            &self.context,
+            format!("DELETE have WHERE out == task:{task_id}"),
+            format!("RELATE task_list:{task_list_id} -> have -> task:{task_id}"),
+        )
+        .await?;
+        Ok(())
+    }
+
     pub async fn insert_task_for_task_list(
         &self,
         task: &Task,
         task_list_id: &str,
     ) -> Result<Task, io::Error> {
-        let task = create_resource(&self.context, task, "task").await?;
+        let task = Task {
+            rank: self.query_next_rank(task_list_id).await?,
+            ..task.clone()
+        };
+        let task = create_resource(&self.context, &task, "task").await?;
         let _ = exec_query(
             &self.context,
             format!(
@@ -194,20 +369,7 @@ impl TaskRepository {
     pub async fn query_task_list_by_id(&self, id: &str) -> Result<TaskList, io::Error> {
         let mut task_list: TaskList = select_resourse(&self.context, id, "task_list").await?;
 
-        let mut response = exec_query(
-            &self.context,
-            format!(
-                "SELECT ->have->task as tasks FROM task_list where id == task_list:{}",
-                id
-            ),
-        )
-        .await?;
-        let tasks = response
-            .take::<Option<Vec<Thing>>>((0, "tasks"))
-            .unwrap()
-            .unwrap_or_default();
-
-        task_list.tasks = Some(unwrap_things(tasks));
+        task_list.tasks = Some(self.query_all_tasks_of_task_list(id).await?);
         Ok(task_list)
     }
 
@@ -410,17 +572,50 @@ impl TaskRepository {
     ) -> Result<Vec<DbModelId>, io::Error> {
         let mut response = exec_query(
             &self.context,
-            format!(
-                "SELECT ->have->task as tasks FROM task_list where id == task_list:{}",
-                task_list
-            ),
+            format!("SELECT id, rank FROM task_list:{task_list}->have->task ORDER BY rank"),
         )
         .await?;
         let tasks = response
-            .take::<Option<Vec<Thing>>>("tasks")
+            .take::<Vec<RankedTask>>(0)
             .map_err(get_io_error)?
-            .unwrap_or_default();
-        Ok(unwrap_things(tasks))
+            .into_iter()
+            .map(|task| unwrap_thing(task.id))
+            .collect();
+        Ok(tasks)
+    }
+
+    /// Rank that places a task after every task already in the list
+    pub async fn query_next_rank(&self, task_list_id: &str) -> Result<i64, io::Error> {
+        // tasks created before ranking existed have no rank at all
+        let mut response = exec_query(
+            &self.context,
+            format!("SELECT VALUE rank FROM task_list:{task_list_id}->have->task"),
+        )
+        .await?;
+        let ranks = response.take::<Vec<Option<i64>>>(0).map_err(get_io_error)?;
+        Ok(ranks.into_iter().flatten().max().map_or(0, |max| max + 1))
+    }
+
+    /// Ranks the given tasks by their position in `tasks`
+    pub async fn update_task_ranks(&self, tasks: &[DbModelId]) -> Result<(), io::Error> {
+        if tasks.is_empty() {
+            return Ok(());
+        }
+        let updates = (0..tasks.len())
+            .map(|rank| format!("UPDATE $task_{rank} SET rank = {rank}; "))
+            .collect::<String>();
+        let query = format!("BEGIN TRANSACTION; {updates}COMMIT TRANSACTION;");
+        tasks
+            .iter()
+            .enumerate()
+            .fold(self.context.db.query(query), |query, (rank, task_id)| {
+                query.bind((format!("task_{rank}"), Thing::from(("task", task_id.as_str()))))
+            })
+            .await
+            .map_err(get_io_error)?
+            .check()
+            .map_err(get_io_error)?;
+        Ok(())
     }
 
     pub async fn query_task_list_id_by_task(&self, task_id: &str) -> Result<DbModelId, io::Error> {
@@ -478,11 +673,6 @@ impl TaskRepository {
         Ok((task_list, source.id.to_string()))
     }
 
-    pub async fn delete_task(&self, task_id: &str) -> Result<Task, io::Error> {
-        let task: Task = delete_resource(&self.context, task_id, "task").await?;
-        Ok(task)
-    }
-
     pub async fn task_links_to_tasks(
         &self,
         task_links: Vec<TaskLink>,
@@ -519,4 +709,246 @@ impl TaskRepository {
         .await?;
         response.take::<Vec<Task>>(0).map_err(get_io_error)
     }
+
+    /// Tasks of the given task lists whose name or description contains `keyword`,
+    /// paired with the id of the task list holding them.
+    pub async fn search_tasks_in_task_lists(
+        &self,
+        task_lists: &[DbModelId],
+        keyword: &str,
+    ) -> Result<Vec<(Task, DbModelId)>, io::Error> {
+        let task_lists: Vec<_> = task_lists
+            .iter()
+            .map(|id| Thing::from(("task_list", id.as_str())))
+            .collect();
+        let mut response = self
+            .context
+            .db
+            .query(
+                "SELECT id, (<-have<-task_list)[0] AS task_list FROM task \
+                WHERE (<-have<-task_list)[0] INSIDE $task_lists \
+                AND (string::contains(string::lowercase(name), $keyword) \
+                OR string::contains(string::lowercase(description), $keyword))",
+            )
+            .bind(("task_lists", task_lists))
+            .bind(("keyword", keyword.to_lowercase()))
+            .await
+            .map_err(get_io_error)?;
+        let found = response
+            .take::<Vec<TaskInList>>(0)
+            .map_err(get_io_error)?;
+        let futures = found
+            .into_iter()
+            .map(|found| async move {
+                let task = self.query_task_by_id(&unwrap_thing(found.id)).await?;
+                Ok::<_, io::Error>((task, unwrap_thing(found.task_list)))
+            })
+            .collect::<Vec<_>>();
+        try_join_all(futures).await
+    }
+
+    pub async fn insert_task_change(&self, change: &TaskChange) -> Result<TaskChange, io::Error> {
+        create_resource(&self.context, change, "task_change").await
+    }
+
+    /// History of the task, oldest first
+    pub async fn query_task_changes(&self, task_id: &str) -> Result<Vec<TaskChange>, io::Error> {
+        let mut response = exec_query(
+            &self.context,
+            format!("SELECT * FROM task_change WHERE task == '{task_id}' ORDER BY created_at"),
+        )
+        .await?;
+        response.take::<Vec<TaskChange>>(0).map_err(get_io_error)
+    }
+
+    /// Every occurrence of a recurring series, including its first task, in order
+    pub async fn query_tasks_of_series(&self, series: &str) -> Result<Vec<Task>, io::Error> {
+        let mut response = self
+            .context
+            .db
+            .query(
+                "SELECT * FROM task WHERE series == $series \
+                OR id == type::thing('task', $series) ORDER BY occurrence",
+            )
+            .bind(("series", series))
+            .await
+            .map_err(get_io_error)?;
+        response.take::<Vec<Task>>(0).map_err(get_io_error)
+    }
+
+    pub async fn insert_time_entry(&self, entry: &TimeEntry) -> Result<TimeEntry, io::Error> {
+        create_resource(&self.context, entry, "time_entry").await
+    }
+
+    pub async fn query_time_entry_by_id(&self, entry_id: &str) -> Result<TimeEntry, io::Error> {
+        select_resourse(&self.context, entry_id, "time_entry").await
+    }
+
+    pub async fn update_time_entry(
+        &self,
+        entry_id: &str,
+        entry: &TimeEntry,
+    ) -> Result<TimeEntry, io::Error> {
+        update_resource(&self.context, entry_id, entry, "time_entry").await
+    }
+
+    pub async fn delete_time_entry(&self, entry_id: &str) -> Result<TimeEntry, io::Error> {
+        delete_resource(&self.context, entry_id, "time_entry").await
+    }
+
+    /// Time entries of the given tasks, oldest first
+    pub async fn query_time_entries_of_tasks(
+        &self,
+        task_ids: &[DbModelId],
+    ) -> Result<Vec<TimeEntry>, io::Error> {
+        let mut response = self
+            .context
+            .db
+            .query("SELECT * FROM time_entry WHERE task INSIDE $tasks ORDER BY started_at")
+            .bind(("tasks", task_ids))
+            .await
+            .map_err(get_io_error)?;
+        response.take::<Vec<TimeEntry>>(0).map_err(get_io_error)
+    }
+
+    /// The timer `user_id` currently has running, if any
+    pub async fn query_running_time_entry(
+        &self,
+        user_id: &str,
+    ) -> Result<Option<TimeEntry>, io::Error> {
+        let mut response = exec_query(
+            &self.context,
+            format!("SELECT * FROM time_entry WHERE user == '{user_id}' AND ended_at == NONE"),
+        )
+        .await?;
+        Ok(response
+            .take::<Vec<TimeEntry>>(0)
+            .map_err(get_io_error)?
+            .pop())
+    }
+
+    /// Time entries of `user_id` started within `[from, to)`, oldest first
+    pub async fn query_time_entries_of_user(
+        &self,
+        user_id: &str,
+        from: Datetime,
+        to: Datetime,
+    ) -> Result<Vec<TimeEntry>, io::Error> {
+        let mut response = self
+            .context
+            .db
+            .query(
+                "SELECT * FROM time_entry WHERE user == $user \
+                AND started_at >= $from AND started_at < $to ORDER BY started_at",
+            )
+            .bind(("user", user_id))
+            .bind(("from", from))
+            .bind(("to", to))
+            .await
+            .map_err(get_io_error)?;
+        response.take::<Vec<TimeEntry>>(0).map_err(get_io_error)
+    }
+
+    /// Sets the status pool of `owner` and the `(task, status, complete)` updates
+    /// in a single transaction
+    pub async fn replace_status_pool(
+        &self,
+        owner: Thing,
+        status_pool: &StatusPool,
+        updates: &[(DbModelId, String, bool)],
+    ) -> Result<(), io::Error> {
+        let updates: Vec<_> = updates
+            .iter()
+            .map(|(task, status, complete)| StatusUpdate {
+                task: Thing::from(("task", task.as_str())),
+                status: status.clone(),
+                complete: *complete,
+            })
+            .collect();
+        self.context
+            .db
+            .query(
+                "BEGIN TRANSACTION; \
+                FOR $update IN $updates { \
+                    UPDATE type::thing($update.task) \
+                    SET status = $update.status, complete = $update.complete; \
+                }; \
+                UPDATE $owner SET status_pool = $pool; \
+                COMMIT TRANSACTION;",
+            )
+            .bind(("updates", updates))
+            .bind(("owner", owner))
+            .bind(("pool", status_pool.clone()))
+            .await
+            .map_err(get_io_error)?
+            .check()
+            .map_err(get_io_error)?;
+        Ok(())
+    }
+
+    /// Writes the tasks of a bulk patch, and moves them if asked to, in a single
+    /// transaction, so either all of them change or none does
+    pub async fn apply_bulk_writes(
+        &self,
+        writes: Vec<BulkTaskWrite>,
+        bulk_move: Option<BulkTaskMove>,
+    ) -> Result<(), io::Error> {
+        self.context
+            .db
+            .query(
+                "BEGIN TRANSACTION; \
+                FOR $write IN $writes { \
+                    LET $task = $write.task; \
+                    DELETE assign WHERE in == $task AND out INSIDE $write.deassign; \
+                    FOR $user IN $write.assign { \
+                        RELATE $task->assign->$user; \
+                    }; \
+                    UPDATE $task CONTENT $write.content; \
+                    IF $write.change { \
+                        CREATE task_change CONTENT $write.change; \
+                    }; \
+                }; \
+                IF $move { \
+                    LET $task_list = $move.task_list; \
+                    DELETE subtask WHERE out INSIDE $move.detached; \
+                    DELETE have WHERE out INSIDE $move.tasks; \
+                    FOR $task IN $move.tasks { \
+                        RELATE $task_list->have->$task; \
+                    }; \
+                    FOR $rank IN $move.ranks { \
+                        UPDATE $rank.task SET rank = $rank.rank; \
+                    }; \
+                }; \
+                COMMIT TRANSACTION;",
+            )
+            .bind(("writes", writes))
+            .bind(("move", bulk_move))
+            .await
+            .map_err(get_io_error)?
+            .check()
+            .map_err(get_io_error)?;
+        Ok(())
+    }
+
+    /// Deletes the tasks together with their comments in a single transaction
+    pub async fn delete_tasks(&self, task_ids: &[DbModelId]) -> Result<(), io::Error> {
+        let tasks: Vec<_> = task_ids
+            .iter()
+            .map(|task| Thing::from(("task", task.as_str())))
+            .collect();
+        self.context
+            .db
+            .query(
+                "BEGIN TRANSACTION; \
+                DELETE comment WHERE <-discuss<-task CONTAINSANY $tasks; \
+                DELETE $tasks; \
+                COMMIT TRANSACTION;",
+            )
+            .bind(("tasks", tasks))
+            .await
+            .map_err(get_io_error)?
+            .check()
+            .map_err(get_io_error)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,44 @@
+use std::io;
+
+use surrealdb::sql::Thing;
+
+use crate::db::{db_context::DbContext, model::template::Template};
+
+use super::utils::{create_resource, delete_resource, get_io_error, select_resourse};
+
+#[derive(Clone)]
+pub struct TemplateRepository {
+    pub context: DbContext,
+}
+
+impl TemplateRepository {
+    pub async fn new() -> Self {
+        Self {
+            context: DbContext::new().await,
+        }
+    }
+
+    pub async fn insert_template(&self, template: &Template) -> Result<Template, io::Error> {
+        create_resource(&self.context, template, "template").await
+    }
+
+    pub async fn query_template_by_id(&self, template_id: &str) -> Result<Template, io::Error> {
+        select_resourse(&self.context, template_id, "template").await
+    }
+
+    /// Templates of a project or user, newest first
+    pub async fn query_templates_by_owner(&self, owner: Thing) -> Result<Vec<Template>, io::Error> {
+        let mut response = self
+            .context
+            .db
+            .query("SELECT * FROM template WHERE owner == $owner ORDER BY created_at DESC")
+            .bind(("owner", owner))
+            .await
+            .map_err(get_io_error)?;
+        response.take::<Vec<Template>>(0).map_err(get_io_error)
+    }
+
+    pub async fn delete_template(&self, template_id: &str) -> Result<Template, io::Error> {
+        delete_resource(&self.context, template_id, "template").await
+    }
+}
@@ -47,6 +47,12 @@ impl UserRepository {
         user.ok_or(io::Error::new(io::ErrorKind::NotFound, "User not found"))
     }
 
+    pub async fn query_all_user_ids(&self) -> Result<Vec<DbModelId>, io::Error> {
+        let mut response = exec_query(&self.context, "SELECT VALUE id FROM user".to_owned()).await?;
+        let users = response.take::<Vec<Thing>>(0).map_err(get_io_error)?;
+        Ok(unwrap_things(users))
+    }
+
     pub async fn update_user(&self, user_id: &str, user: &User) -> Result<User, io::Error> {
         let result: Option<User> = self
             .context
@@ -122,6 +128,19 @@ impl UserRepository {
         Ok(unwrap_things(agendas))
     }
 
+    pub async fn query_agenda_by_id_without_from_project(&self, user_id: &str) -> Result<Vec<DbModelId>, io::Error> {
+        let mut response = exec_query(
+            &self.context,
+            format!("select ->own->agenda as agendas from user where id == user:{user_id}")
+        )
+        .await?;
+        let agendas = response
+            .take::<Option<Vec<Thing>>>((0, "agendas"))
+            .map_err(get_io_error)?
+            .unwrap_or_default();
+        Ok(unwrap_things(agendas))
+    }
+
     pub async fn query_task_list_by_id_without_from_project(&self, user_id: &str) -> Result<Vec<DbModelId>, io::Error> {
         let mut response = exec_query(
             &self.context,
@@ -155,20 +174,4 @@ impl UserRepository {
 
         Ok(unwrap_things(task_lists))
     }
-
-    pub async fn query_notif_by_user_id(&self, user_id: &str) -> Result<Vec<DbModelId>, io::Error> {
-        let mut response = exec_query(
-            &self.context,
-            format!(
-                "SELECT ->notified_by->notification as notifs FROM user where id == user:{}",
-                user_id
-            ),
-        )
-        .await?;
-        let notifs = response
-            .take::<Option<Vec<Thing>>>((0, "notifs"))
-            .map_err(get_io_error)?
-            .unwrap_or_default();
-        Ok(unwrap_things(notifs))
-    }
 }
@@ -0,0 +1,217 @@
+use std::{collections::HashMap, io};
+
+use futures::future::try_join_all;
+
+use crate::db::{
+    model::{status::StatusPool, task::Task},
+    repository::{
+        project::ProjectRepository, task::TaskRepository, user::UserRepository,
+        utils::{get_str_id, DbModelId},
+    },
+};
+
+use super::workflow::status_key;
+
+/// Status pool together with the task lists it governs: all lists of a project,
+/// or the personal lists of a user
+pub struct PoolScope {
+    pub status_pool: StatusPool,
+    pub task_lists: Vec<DbModelId>,
+}
+
+pub async fn query_project_scope(
+    project_repo: &ProjectRepository,
+    project_id: &str,
+) -> Result<PoolScope, io::Error> {
+    let project = project_repo.query_project_by_id(project_id).await?;
+    Ok(PoolScope {
+        status_pool: project.status_pool,
+        task_lists: project_repo.query_task_list_by_id(project_id).await?,
+    })
+}
+
+/// Scope of the pool governing `task_list_id`
+pub async fn query_task_list_scope(
+    task_repo: &TaskRepository,
+    project_repo: &ProjectRepository,
+    user_repo: &UserRepository,
+    task_list_id: &str,
+) -> Result<PoolScope, io::Error> {
+    let source = task_repo.query_task_list_source(task_list_id).await?;
+    let source_id = source.id.to_string();
+    match source.tb.as_str() {
+        "project" => query_project_scope(project_repo, &source_id).await,
+        _ => Ok(PoolScope {
+            status_pool: user_repo.query_user_by_id(&source_id).await?.status_pool,
+            task_lists: user_repo
+                .query_task_list_by_id_without_from_project(&source_id)
+                .await?,
+        }),
+    }
+}
+
+/// Every task of the given lists, in list order then rank order
+pub async fn query_scope_tasks(
+    task_repo: &TaskRepository,
+    task_lists: &[DbModelId],
+) -> Result<Vec<(Task, DbModelId)>, io::Error> {
+    let mut tasks = vec![];
+    for list in task_lists {
+        let ids = task_repo.query_all_tasks_of_task_list(list).await?;
+        let list_tasks =
+            try_join_all(ids.iter().map(|id| task_repo.query_task_by_id(id))).await?;
+        tasks.extend(list_tasks.into_iter().map(|task| (task, list.clone())));
+    }
+    Ok(tasks)
+}
+
+/// WIP limits `task` would exceed by entering `status`. `others` must not contain the task itself.
+pub fn wip_violations(
+    pool: &StatusPool,
+    status: &str,
+    assignees: &[DbModelId],
+    others: &[&Task],
+) -> Vec<String> {
+    let Some(column) = pool.incomplete.iter().find(|s| s.number == status) else {
+        return vec![];
+    };
+    let Some(limit) = column.wip_limit else {
+        return vec![];
+    };
+    let in_status: Vec<_> = others
+        .iter()
+        .filter(|task| status_key(task) == status)
+        .collect();
+
+    if !column.wip_per_assignee {
+        if in_status.len() as u32 >= limit {
+            return vec![format!(
+                "\"{}\" is at its WIP limit of {limit}",
+                column.name
+            )];
+        }
+        return vec![];
+    }
+    assignees
+        .iter()
+        .filter(|assignee| {
+            let count = in_status
+                .iter()
+                .filter(|task| task.assignees.as_ref().is_some_and(|a| a.contains(assignee)))
+                .count();
+            count as u32 >= limit
+        })
+        .map(|assignee| {
+            format!(
+                "{assignee} is at the WIP limit of {limit} in \"{}\"",
+                column.name
+            )
+        })
+        .collect()
+}
+
+/// Checks `task` entering `status` against the WIP limits of the pool governing
+/// `task_list_id`. Returns warnings, or fails when the pool enforces its limits.
+pub async fn check_wip_limits(
+    task_repo: &TaskRepository,
+    project_repo: &ProjectRepository,
+    user_repo: &UserRepository,
+    task_list_id: &str,
+    task_id: Option<&str>,
+    status: &str,
+    assignees: &[DbModelId],
+) -> Result<Vec<String>, io::Error> {
+    let scope = query_task_list_scope(task_repo, project_repo, user_repo, task_list_id).await?;
+    if scope.status_pool.incomplete.iter().all(|s| s.wip_limit.is_none()) {
+        return Ok(vec![]);
+    }
+    let tasks = query_scope_tasks(task_repo, &scope.task_lists).await?;
+    let others: Vec<_> = tasks
+        .iter()
+        .map(|(task, _)| task)
+        .filter(|task| task_id.map_or(true, |id| get_str_id(&task.id) != id))
+        .collect();
+
+    let violations = wip_violations(&scope.status_pool, status, assignees, &others);
+    if scope.status_pool.wip_enforced && !violations.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            violations.join("; "),
+        ));
+    }
+    Ok(violations)
+}
+
+/// Task count of a status, overall and per assignee
+pub struct StatusCount {
+    pub status: String,
+    pub count: usize,
+    pub per_assignee: HashMap<DbModelId, usize>,
+}
+
+/// Counts for each status of the pool, incomplete statuses first then `complete`
+pub fn count_by_status(pool: &StatusPool, tasks: &[&Task]) -> Vec<StatusCount> {
+    pool.incomplete
+        .iter()
+        .map(|status| status.number.clone())
+        .chain(std::iter::once("complete".to_owned()))
+        .map(|status| {
+            let mut count = 0;
+            let mut per_assignee = HashMap::new();
+            for task in tasks.iter().filter(|task| status_key(task) == status) {
+                count += 1;
+                for assignee in task.assignees.iter().flatten() {
+                    *per_assignee.entry(assignee.clone()).or_default() += 1;
+                }
+            }
+            StatusCount {
+                status,
+                count,
+                per_assignee,
+            }
+        })
+        .collect()
+}
+
+/// Splits tasks into the columns of the pool, incomplete statuses first then `complete`.
+/// Cards keep their rank order; tasks in statuses missing from the pool are left out.
+pub fn group_by_status<T>(
+    pool: &StatusPool,
+    tasks: Vec<(Task, T)>,
+) -> Vec<(String, Vec<(Task, T)>)> {
+    let mut columns: Vec<(String, Vec<(Task, T)>)> = pool
+        .incomplete
+        .iter()
+        .map(|status| (status.number.clone(), vec![]))
+        .chain(std::iter::once(("complete".to_owned(), vec![])))
+        .collect();
+    for (task, extra) in tasks {
+        let key = status_key(&task);
+        if let Some((_, cards)) = columns.iter_mut().find(|(status, _)| *status == key) {
+            cards.push((task, extra));
+        }
+    }
+    for (_, cards) in &mut columns {
+        cards.sort_by_key(|(task, _)| task.rank);
+    }
+    columns
+}
+
+/// Position in the task list order (without the moved task) that puts a card at
+/// `position` inside its column. Cards of a column are spread over the whole list,
+/// so the card lands right before the column card currently at `position`, or right
+/// after the last card of the column.
+pub fn list_position_for_card(
+    list_order: &[DbModelId],
+    column: &[DbModelId],
+    position: Option<usize>,
+) -> usize {
+    let index_of = |id: &DbModelId| list_order.iter().position(|other| other == id);
+    match position.and_then(|position| column.get(position)) {
+        Some(before) => index_of(before).unwrap_or(list_order.len()),
+        None => column
+            .last()
+            .and_then(index_of)
+            .map_or(list_order.len(), |last| last + 1),
+    }
+}
@@ -0,0 +1,437 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
+
+use chrono::Duration;
+use surrealdb::sql::{Datetime, Thing};
+
+use crate::db::{
+    model::task::Task,
+    repository::{
+        notification::NotificationRepository,
+        project::ProjectRepository,
+        task::{BulkTaskMove, BulkTaskWrite, TaskRank, TaskRepository},
+        user::UserRepository,
+        utils::{unwrap_thing, DbModelId},
+    },
+};
+
+use super::{
+    board::{query_scope_tasks, query_task_list_scope, wip_violations, PoolScope},
+    notification::deliver_notif,
+    recurrence::spawn_next_occurrence,
+    task_history::{task_change, SOURCE_USER},
+    task_stream::{check_task_switch_complete_planned, refresh_task_status, TaskSwitchable},
+    task_tree::refresh_auto_complete,
+    util::notification::{
+        assigned_task_to_notif, bulk_assignment_to_notif, deassign_task_to_notif,
+    },
+    workflow::{check_transition, query_workflow_of_task, status_key},
+};
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Changes applied to every task of a bulk operation
+#[derive(Clone, Debug, Default)]
+pub struct BulkPatch {
+    /// `complete` or the number of an incomplete status
+    pub status: Option<String>,
+    pub add_assignees: Vec<DbModelId>,
+    pub remove_assignees: Vec<DbModelId>,
+    pub shift_deadline: Option<Duration>,
+    pub task_list: Option<DbModelId>,
+    /// Deletes the tasks with their subtasks; cannot be combined with other changes
+    pub delete: bool,
+}
+
+impl BulkPatch {
+    fn is_empty(&self) -> bool {
+        self.status.is_none()
+            && self.add_assignees.is_empty()
+            && self.remove_assignees.is_empty()
+            && self.shift_deadline.is_none()
+            && self.task_list.is_none()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BulkOutcome {
+    pub tasks: Vec<Task>,
+    /// Tasks the patch completed
+    pub completed: Vec<DbModelId>,
+    pub deleted: Vec<DbModelId>,
+    /// Exceeded WIP limits that were not enforced
+    pub warnings: Vec<String>,
+}
+
+/// Orders tasks so that the source of every link among them comes before its target.
+/// Tasks caught in a cycle keep their relative order at the end.
+pub fn order_by_links(task_ids: &[DbModelId], links: &[(DbModelId, DbModelId)]) -> Vec<DbModelId> {
+    let mut pending: Vec<_> = task_ids.to_vec();
+    let mut ordered = vec![];
+    while !pending.is_empty() {
+        let ready: Vec<_> = pending
+            .iter()
+            .filter(|id| !links.iter().any(|(from, to)| to == *id && pending.contains(from)))
+            .cloned()
+            .collect();
+        if ready.is_empty() {
+            ordered.append(&mut pending);
+            break;
+        }
+        pending.retain(|id| !ready.contains(id));
+        ordered.extend(ready);
+    }
+    ordered
+}
+
+struct PlannedTask {
+    id: DbModelId,
+    old: Task,
+    new: Task,
+    assignees: Vec<DbModelId>,
+    /// Project or user owning the task list
+    source: String,
+}
+
+/// Applies `patch` to all tasks or to none: every task is checked against workflow rules,
+/// WIP limits and list ownership, then all writes go out in one transaction. Dependency
+/// propagation and auto-completion run once for the whole batch afterwards, and each user
+/// gets a single notification about their assignment changes.
+pub async fn apply_bulk_patch(
+    task_repo: &TaskRepository,
+    project_repo: &ProjectRepository,
+    user_repo: &UserRepository,
+    notif_repo: &NotificationRepository,
+    task_ids: &[DbModelId],
+    patch: &BulkPatch,
+    actor_id: &str,
+) -> Result<BulkOutcome, io::Error> {
+    let mut ids: Vec<DbModelId> = vec![];
+    for id in task_ids {
+        if !ids.contains(id) {
+            ids.push(id.clone());
+        }
+    }
+    if ids.is_empty() {
+        return Err(invalid("No tasks given".to_owned()));
+    }
+    if patch.delete && !patch.is_empty() {
+        return Err(invalid("Deleting cannot be combined with other changes".to_owned()));
+    }
+    if !patch.delete && patch.is_empty() {
+        return Err(invalid("Nothing to change".to_owned()));
+    }
+    if patch.add_assignees.iter().any(|id| patch.remove_assignees.contains(id)) {
+        return Err(invalid("A user cannot be both added and removed".to_owned()));
+    }
+
+    if patch.delete {
+        return delete_tasks(task_repo, notif_repo, &ids).await;
+    }
+
+    // plan every change before writing anything
+    let mut links = vec![];
+    for id in &ids {
+        for link in task_repo.query_task_incoming_links_by_task_id(id).await? {
+            let from = unwrap_thing(link.incoming.unwrap());
+            if ids.contains(&from) {
+                links.push((from, id.clone()));
+            }
+        }
+    }
+    let mut scopes: HashMap<String, (PoolScope, Vec<Task>)> = HashMap::new();
+    let mut plan: Vec<PlannedTask> = vec![];
+    let mut warnings = vec![];
+    for id in order_by_links(&ids, &links) {
+        let old = task_repo.query_task_by_id(&id).await?;
+        let task_list = task_repo.query_task_list_id_by_task(&id).await?;
+        let source = task_repo.query_task_list_source(&task_list).await?.to_string();
+        if let Some(target) = &patch.task_list {
+            if task_repo.query_task_list_source(target).await?.to_string() != source {
+                return Err(invalid(
+                    "Tasks can only move between task lists of the same owner".to_owned(),
+                ));
+            }
+        }
+
+        let mut assignees = old.assignees.clone().unwrap_or_default();
+        assignees.retain(|assignee| !patch.remove_assignees.contains(assignee));
+        for assignee in &patch.add_assignees {
+            if !assignees.contains(assignee) {
+                assignees.push(assignee.clone());
+            }
+        }
+        let mut new = Task {
+            id: None,
+            ddl: match (&old.ddl, patch.shift_deadline) {
+                (Some(ddl), Some(shift)) => Some(Datetime(ddl.0 + shift)),
+                (ddl, _) => ddl.clone(),
+            },
+            assignees: Some(assignees.clone()),
+            ..old.clone()
+        };
+        if let Some(status) = &patch.status {
+            let (pool, is_admin) =
+                query_workflow_of_task(task_repo, project_repo, user_repo, &id, actor_id).await?;
+            let mut preview = new.clone();
+            (preview.complete, preview.status) = match status.as_str() {
+                "complete" => (true, "complete".to_owned()),
+                number => (false, number.to_owned()),
+            };
+            check_transition(&pool, &status_key(&old), status, &preview, is_admin)
+                .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", old.name)))?;
+            (new.complete, new.status) = (preview.complete, preview.status);
+        }
+
+        if !new.complete
+            && (status_key(&new) != status_key(&old) || Some(&assignees) != old.assignees.as_ref())
+        {
+            if !scopes.contains_key(&source) {
+                let scope =
+                    query_task_list_scope(task_repo, project_repo, user_repo, &task_list).await?;
+                let others = query_scope_tasks(task_repo, &scope.task_lists)
+                    .await?
+                    .into_iter()
+                    .map(|(task, _)| task)
+                    .filter(|task| !task.id.clone().is_some_and(|t| ids.contains(&unwrap_thing(t))))
+                    .collect();
+                scopes.insert(source.clone(), (scope, others));
+            }
+            let (scope, others) = &scopes[&source];
+            // tasks planned earlier in the batch count towards the limits
+            let mut counted: Vec<&Task> = others.iter().collect();
+            counted.extend(plan.iter().filter(|p| p.source == source).map(|p| &p.new));
+            let violations = wip_violations(&scope.status_pool, &new.status, &assignees, &counted);
+            if scope.status_pool.wip_enforced && !violations.is_empty() {
+                return Err(invalid(format!("{}: {}", old.name, violations.join("; "))));
+            }
+            for violation in violations {
+                if !warnings.contains(&violation) {
+                    warnings.push(violation);
+                }
+            }
+        }
+
+        plan.push(PlannedTask {
+            id,
+            old,
+            new,
+            assignees,
+            source,
+        });
+    }
+
+    // check in link order so successors see their predecessors' planned completion
+    let mut planned_complete = HashMap::new();
+    let mut writes = vec![];
+    let mut changed_completion = vec![];
+    let mut parents = HashSet::new();
+    for planned in &mut plan {
+        let id = &planned.id;
+        if patch.status.is_some() {
+            let switchable =
+                check_task_switch_complete_planned(id, task_repo, &planned_complete).await?;
+            // same outcome as patching the task alone
+            match (planned.new.complete, switchable) {
+                (true, TaskSwitchable::False) => {
+                    (planned.new.complete, planned.new.status) = (false, "incomplete".to_owned())
+                }
+                (false, TaskSwitchable::True) => planned.new.complete = true,
+                _ => {}
+            }
+        }
+        planned_complete.insert(id.clone(), planned.new.complete);
+
+        let before = planned.old.assignees.clone().unwrap_or_default();
+        let user = |user: &DbModelId| Thing::from(("user", user.as_str()));
+        writes.push(BulkTaskWrite {
+            task: Thing::from(("task", id.as_str())),
+            content: planned.new.clone(),
+            assign: planned.assignees.iter().filter(|a| !before.contains(a)).map(user).collect(),
+            deassign: before.iter().filter(|a| !planned.assignees.contains(a)).map(user).collect(),
+            change: task_change(id, &planned.old, &planned.new, SOURCE_USER, Some(actor_id)),
+        });
+        if planned.old.complete != planned.new.complete {
+            changed_completion.push(id.clone());
+        }
+        if let Some(parent) = &planned.old.parent {
+            parents.insert(parent.clone());
+        }
+    }
+    let bulk_move = match &patch.task_list {
+        Some(target) => Some(plan_bulk_move(task_repo, target, &plan).await?),
+        None => None,
+    };
+    task_repo.apply_bulk_writes(writes, bulk_move).await?;
+
+    // propagate once over the union of affected successors and parents
+    let mut successors = vec![];
+    for id in &changed_completion {
+        for link in task_repo.query_task_outgoing_links_by_task_id(id).await? {
+            let to = unwrap_thing(link.outgoing.unwrap());
+            if !successors.contains(&to) {
+                successors.push(to);
+            }
+        }
+    }
+    for successor in &successors {
+        refresh_task_status(successor, task_repo, notif_repo, &changed_completion).await?;
+    }
+    for parent in parents.iter().filter(|parent| !ids.contains(parent)) {
+        refresh_auto_complete(parent, task_repo, notif_repo).await?;
+    }
+
+    notify_assignment_changes(notif_repo, &plan).await?;
+
+    let (mut tasks, mut completed) = (vec![], vec![]);
+    for planned in &plan {
+        refresh_auto_complete(&planned.id, task_repo, notif_repo).await?;
+        let task = task_repo.query_task_by_id(&planned.id).await?;
+        spawn_next_occurrence(
+            task_repo,
+            project_repo,
+            user_repo,
+            notif_repo,
+            &planned.id,
+            &planned.old,
+            &task,
+        )
+        .await?;
+        if !planned.old.complete && task.complete {
+            completed.push(planned.id.clone());
+        }
+        tasks.push(task);
+    }
+    Ok(BulkOutcome {
+        tasks,
+        completed,
+        deleted: vec![],
+        warnings,
+    })
+}
+
+/// Where moved tasks end up in `target`: after the tasks already there, in batch order,
+/// each followed by the subtasks it brings along. Tasks leave parents in other lists.
+async fn plan_bulk_move(
+    task_repo: &TaskRepository,
+    target: &str,
+    plan: &[PlannedTask],
+) -> Result<BulkTaskMove, io::Error> {
+    let thing = |id: &DbModelId| Thing::from(("task", id.as_str()));
+    let (mut appended, mut moved, mut detached) = (vec![], vec![], vec![]);
+    for planned in plan {
+        // already moved along with an ancestor
+        if appended.contains(&planned.id) {
+            continue;
+        }
+        appended.push(planned.id.clone());
+        if task_repo.query_task_list_id_by_task(&planned.id).await? == target {
+            continue;
+        }
+        if planned.old.parent.is_some() {
+            detached.push(thing(&planned.id));
+        }
+        moved.push(thing(&planned.id));
+        for descendant in task_repo.query_descendants_of_task(&planned.id).await? {
+            if !appended.contains(&descendant) {
+                moved.push(thing(&descendant));
+                appended.push(descendant);
+            }
+        }
+    }
+
+    let mut order = task_repo.query_all_tasks_of_task_list(target).await?;
+    order.retain(|id| !appended.contains(id));
+    order.extend(appended);
+    Ok(BulkTaskMove {
+        task_list: Thing::from(("task_list", target)),
+        tasks: moved,
+        detached,
+        ranks: order
+            .iter()
+            .enumerate()
+            .map(|(rank, id)| TaskRank {
+                task: thing(id),
+                rank: rank as i64,
+            })
+            .collect(),
+    })
+}
+
+/// One notification per user whose assignments changed, about the first task concerned
+async fn notify_assignment_changes(
+    notif_repo: &NotificationRepository,
+    plan: &[PlannedTask],
+) -> Result<(), io::Error> {
+    let mut changes: HashMap<&str, (Vec<&PlannedTask>, Vec<&PlannedTask>)> = HashMap::new();
+    for planned in plan {
+        let before = planned.old.assignees.as_deref().unwrap_or_default();
+        for assignee in planned.assignees.iter().filter(|a| !before.contains(a)) {
+            changes.entry(assignee).or_default().0.push(planned);
+        }
+        for assignee in before.iter().filter(|a| !planned.assignees.contains(a)) {
+            changes.entry(assignee).or_default().1.push(planned);
+        }
+    }
+
+    let mut users: Vec<_> = changes.keys().copied().collect();
+    users.sort();
+    for user in users {
+        let (added, removed) = &changes[user];
+        let (about, notif) = match (added.as_slice(), removed.as_slice()) {
+            ([task], []) => (task, assigned_task_to_notif(task.new.clone())),
+            ([], [task]) => (task, deassign_task_to_notif(task.old.clone())),
+            _ => {
+                let names = |tasks: &[&PlannedTask]| -> Vec<String> {
+                    tasks.iter().map(|task| task.new.name.clone()).collect()
+                };
+                let notif = bulk_assignment_to_notif(&names(added), &names(removed));
+                (added.first().or(removed.first()).unwrap(), notif)
+            }
+        };
+        deliver_notif(notif_repo, user, &about.id, "task", notif).await?;
+    }
+    Ok(())
+}
+
+/// Deletes the tasks with their subtrees in one transaction, skipping tasks already
+/// inside another deleted subtree, then refreshes the surviving parents once
+async fn delete_tasks(
+    task_repo: &TaskRepository,
+    notif_repo: &NotificationRepository,
+    ids: &[DbModelId],
+) -> Result<BulkOutcome, io::Error> {
+    let mut subtrees = vec![];
+    for id in ids {
+        let task = task_repo.query_task_by_id(id).await?;
+        let descendants = task_repo.query_descendants_of_task(id).await?;
+        subtrees.push((id, task.parent, descendants));
+    }
+    let nested: HashSet<_> = subtrees
+        .iter()
+        .flat_map(|(_, _, descendants)| descendants.iter().cloned())
+        .collect();
+
+    let mut deleted = vec![];
+    let mut parents = HashSet::new();
+    for (id, parent, descendants) in subtrees {
+        if nested.contains(id) {
+            continue;
+        }
+        deleted.extend(descendants.into_iter().rev());
+        deleted.push(id.clone());
+        parents.extend(parent);
+    }
+    task_repo.delete_tasks(&deleted).await?;
+    for parent in parents.iter().filter(|parent| !deleted.contains(parent)) {
+        refresh_auto_complete(parent, task_repo, notif_repo).await?;
+    }
+    Ok(BulkOutcome {
+        deleted,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,158 @@
+use std::io;
+
+use chrono::Utc;
+
+use crate::db::{
+    model::{
+        project::Project,
+        task::{CustomFieldValue, FieldValue, Task},
+    },
+    repository::{project::ProjectRepository, task::TaskRepository},
+};
+
+use super::reminder::is_overdue;
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Project owning the task list, whose labels and custom fields apply to its tasks.
+/// `None` for personal task lists.
+pub async fn query_project_of_task_list(
+    task_repo: &TaskRepository,
+    project_repo: &ProjectRepository,
+    task_list_id: &str,
+) -> Result<Option<Project>, io::Error> {
+    let source = task_repo.query_task_list_source(task_list_id).await?;
+    match source.tb.as_str() {
+        "project" => Ok(Some(project_repo.query_project_by_id(&source.id.to_string()).await?)),
+        _ => Ok(None),
+    }
+}
+
+/// Gives new labels and custom fields an id and rejects duplicate ids or select
+/// fields without options
+pub fn prepare_definitions(
+    project: &mut Project,
+    mut new_id: impl FnMut() -> String,
+) -> Result<(), io::Error> {
+    let mut label_ids: Vec<String> = vec![];
+    for label in &mut project.labels {
+        if label.id.is_empty() {
+            label.id = new_id();
+        }
+        if label_ids.contains(&label.id) {
+            return Err(invalid(format!("Label id \"{}\" is used more than once", label.id)));
+        }
+        label_ids.push(label.id.clone());
+    }
+
+    let mut field_ids: Vec<String> = vec![];
+    for def in &mut project.custom_fields {
+        if def.id.is_empty() {
+            def.id = new_id();
+        }
+        if field_ids.contains(&def.id) {
+            return Err(invalid(format!("Field id \"{}\" is used more than once", def.id)));
+        }
+        if def.kind.ends_with("select") && def.options.is_empty() {
+            return Err(invalid(format!("Select field \"{}\" needs options", def.name)));
+        }
+        field_ids.push(def.id.clone());
+    }
+    Ok(())
+}
+
+/// Checks labels and custom field values of a task against the project definitions
+pub fn validate_task_fields(
+    project: Option<&Project>,
+    labels: &[String],
+    values: &[CustomFieldValue],
+) -> Result<(), io::Error> {
+    let Some(project) = project else {
+        if labels.is_empty() && values.is_empty() {
+            return Ok(());
+        }
+        return Err(invalid(
+            "Labels and custom fields are only available in project task lists".to_owned(),
+        ));
+    };
+
+    if let Some(label) = labels
+        .iter()
+        .find(|label| !project.labels.iter().any(|defined| defined.id == **label))
+    {
+        return Err(invalid(format!("Unknown label \"{label}\"")));
+    }
+
+    for (i, value) in values.iter().enumerate() {
+        if values[..i].iter().any(|other| other.field == value.field) {
+            return Err(invalid(format!("Field \"{}\" is set twice", value.field)));
+        }
+        let def = project
+            .custom_fields
+            .iter()
+            .find(|def| def.id == value.field)
+            .ok_or_else(|| invalid(format!("Unknown custom field \"{}\"", value.field)))?;
+        let valid = match (&value.value, def.kind.as_str()) {
+            (FieldValue::Text(_), "text")
+            | (FieldValue::Number(_), "number")
+            | (FieldValue::Date(_), "date") => true,
+            (FieldValue::SingleSelect(option), "single_select") => def.options.contains(option),
+            (FieldValue::MultiSelect(options), "multi_select") => {
+                options.iter().all(|option| def.options.contains(option))
+            }
+            _ => false,
+        };
+        if !valid {
+            return Err(invalid(format!(
+                "Invalid value for {} field \"{}\"",
+                def.kind, def.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Filters for the task listing endpoints; empty criteria match everything
+#[derive(Default)]
+pub struct TaskFilter {
+    pub priority: Option<String>,
+    /// The task must carry at least one of these labels
+    pub labels: Vec<String>,
+    /// Custom field id and, optionally, the value it must match
+    pub field: Option<(String, Option<String>)>,
+    /// Whether the task must be past its deadline while incomplete
+    pub overdue: Option<bool>,
+}
+
+impl TaskFilter {
+    pub fn matches(&self, task: &Task) -> bool {
+        if self.priority.is_some() && task.priority != self.priority {
+            return false;
+        }
+        if self.overdue.is_some_and(|overdue| overdue != is_overdue(task, Utc::now())) {
+            return false;
+        }
+        if !self.labels.is_empty() && !task.labels.iter().any(|label| self.labels.contains(label))
+        {
+            return false;
+        }
+        let Some((field, expected)) = &self.field else {
+            return true;
+        };
+        let Some(value) = task.custom_fields.iter().find(|value| value.field == *field) else {
+            return false;
+        };
+        let Some(expected) = expected else {
+            return true;
+        };
+        match &value.value {
+            FieldValue::Text(text) => text.to_lowercase().contains(&expected.to_lowercase()),
+            FieldValue::Number(number) => expected.parse::<f64>().is_ok_and(|e| e == *number),
+            FieldValue::Date(date) => date.0.to_rfc3339().starts_with(expected.as_str()),
+            FieldValue::SingleSelect(option) => option == expected,
+            FieldValue::MultiSelect(options) => options.contains(expected),
+        }
+    }
+}
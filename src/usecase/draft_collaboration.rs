@@ -1,7 +1,9 @@
 use axum_ycrdt_websocket::{broadcast::BroadcastGroup, AwarenessRef};
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::RwLock;
-use yrs::{sync::Awareness, updates::decoder::Decode, Doc, Options, Transact, Update};
+use yrs::{
+    sync::Awareness, updates::decoder::Decode, Doc, GetString, Options, ReadTxn, Transact, Update,
+};
 
 use crate::db::{model::draft::DraftPayload, repository::draft::DraftRepository};
 
@@ -51,3 +53,49 @@ async fn new_room_for_draft_payload(draft_payload: &DraftPayload) -> Arc<Broadca
     };
     Arc::new(BroadcastGroup::new(awareness.clone(), 1024).await)
 }
+
+/// Plain text of a draft, read from every root of its Yjs document as an xml fragment
+/// (the shape the editor stores) with markup stripped.
+pub fn extract_draft_text(content: &[u8]) -> String {
+    let doc = Doc::with_options(Options {
+        skip_gc: true,
+        ..Options::default()
+    });
+    let update = match Update::decode_v1(content) {
+        Ok(update) => update,
+        Err(_) => return String::new(),
+    };
+    doc.transact_mut().apply_update(update);
+
+    let roots: Vec<String> = doc
+        .transact()
+        .root_refs()
+        .map(|(name, _)| name.to_owned())
+        .collect();
+    let fragments: Vec<_> = roots
+        .into_iter()
+        .map(|name| doc.get_or_insert_xml_fragment(name))
+        .collect();
+
+    let txn = doc.transact();
+    let markup = fragments
+        .iter()
+        .map(|fragment| fragment.get_string(&txn))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut text = String::with_capacity(markup.len());
+    let mut in_tag = false;
+    for c in markup.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                text.push(' ');
+            }
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -1,7 +1,19 @@
 use std::collections::HashMap;
 
+use nanoid::nanoid;
 use serde::{Deserialize, Serialize};
 
+const TOKEN_ALPHABET: [char; 36] = [
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r',
+    's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
+];
+
+/// New invitation token. Tokens double as record ids that notifications point at,
+/// so they stay within letters and digits.
+pub fn gen_token() -> String {
+    nanoid!(21, &TOKEN_ALPHABET)
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct InvitationTokenRepository {
     pub tokens: HashMap<String, InvitationInfo>,
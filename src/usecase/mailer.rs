@@ -0,0 +1,253 @@
+use std::{collections::BTreeMap, env, io, time::Duration as StdDuration};
+
+use chrono::{DateTime, Duration, Utc};
+
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::{authentication::Credentials, Error as SmtpError},
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use surrealdb::sql::Datetime;
+
+use crate::db::{
+    model::notification::OutgoingMail,
+    repository::{
+        notification::NotificationRepository,
+        user::UserRepository,
+        utils::{get_io_error, unwrap_thing},
+    },
+};
+
+const SUBJECT_PREFIX: &str = "[just-dev]";
+
+/// SMTP settings read from `JUST_DEV_SMTP_HOST`, `JUST_DEV_SMTP_PORT`, `JUST_DEV_SMTP_TLS`,
+/// `JUST_DEV_SMTP_USERNAME`, `JUST_DEV_SMTP_PASSWORD` and `JUST_DEV_SMTP_FROM`. Turning TLS
+/// off talks plain SMTP, e.g. to a local sink on port 1025.
+#[derive(Clone, Debug)]
+pub struct MailerConfig {
+    pub host: String,
+    /// Defaults to the submission port for TLS and 25 otherwise
+    pub port: Option<u16>,
+    pub tls: bool,
+    pub credentials: Option<(String, String)>,
+    pub from: String,
+    /// Seconds between sends of immediate mails, `JUST_DEV_MAIL_INTERVAL`
+    pub interval: StdDuration,
+    /// Seconds between digests, `JUST_DEV_DIGEST_INTERVAL`, daily by default. Counted from
+    /// the last digests sent, which survives restarts.
+    pub digest_interval: StdDuration,
+}
+
+impl MailerConfig {
+    /// `None` when no SMTP host is configured
+    pub fn from_env() -> Option<Self> {
+        let host = env::var("JUST_DEV_SMTP_HOST").ok().filter(|host| !host.is_empty())?;
+        let secs = |name: &str, default: u64| {
+            let secs = env::var(name).ok().and_then(|secs| secs.parse::<u64>().ok());
+            StdDuration::from_secs(secs.filter(|secs| *secs > 0).unwrap_or(default))
+        };
+        let credentials = env::var("JUST_DEV_SMTP_USERNAME")
+            .ok()
+            .map(|username| (username, env::var("JUST_DEV_SMTP_PASSWORD").unwrap_or_default()));
+        Some(Self {
+            host,
+            port: env::var("JUST_DEV_SMTP_PORT").ok().and_then(|port| port.parse().ok()),
+            tls: env::var("JUST_DEV_SMTP_TLS").ok().is_none_or(|tls| tls != "false" && tls != "0"),
+            credentials,
+            from: env::var("JUST_DEV_SMTP_FROM")
+                .unwrap_or("just-dev <noreply@localhost>".to_owned()),
+            interval: secs("JUST_DEV_MAIL_INTERVAL", 30),
+            digest_interval: secs("JUST_DEV_DIGEST_INTERVAL", 24 * 60 * 60),
+        })
+    }
+}
+
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn new(config: &MailerConfig) -> Result<Self, io::Error> {
+        let mut builder = match config.tls {
+            true => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)
+                .map_err(get_io_error)?,
+            false => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+        };
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let Some((username, password)) = &config.credentials {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        Ok(Self {
+            transport: builder.build(),
+            from: config.from.parse().map_err(get_io_error)?,
+        })
+    }
+
+    /// Transient SMTP failures come back as `Interrupted`, worth trying again later
+    pub async fn send(&self, to: Mailbox, subject: &str, body: String) -> Result<(), io::Error> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)
+            .map_err(get_io_error)?;
+        self.transport.send(message).await.map_err(smtp_io_error)?;
+        Ok(())
+    }
+}
+
+fn smtp_io_error(err: SmtpError) -> io::Error {
+    let kind = match err.is_transient() {
+        true => io::ErrorKind::Interrupted,
+        false => io::ErrorKind::Other,
+    };
+    io::Error::new(kind, err)
+}
+
+/// Subject and body of a mail sent on its own; the subject is the notification title
+pub fn immediate_mail(mail: &OutgoingMail) -> (String, String) {
+    (
+        format!("{SUBJECT_PREFIX} {}", mail.title),
+        format!("{}\n\n{}\n", mail.title, mail.content),
+    )
+}
+
+/// Subject and body of a digest listing the titles and contents of `mails`
+pub fn digest_mail(mails: &[OutgoingMail]) -> (String, String) {
+    let subject = match mails.len() {
+        1 => format!("{SUBJECT_PREFIX} 1 unread notification"),
+        count => format!("{SUBJECT_PREFIX} {count} unread notifications"),
+    };
+    let body = mails
+        .iter()
+        .map(|mail| format!("- {}\n  {}\n", mail.title, mail.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+    (subject, body)
+}
+
+/// Address of the user, `None` if they have none or it does not parse
+async fn query_mailbox(user_repo: &UserRepository, user_id: &str) -> Option<Mailbox> {
+    let user = user_repo.query_user_by_id(user_id).await.ok()?;
+    let mailbox = format!("{} <{}>", user.username, user.email);
+    match mailbox.parse() {
+        Ok(mailbox) => Some(mailbox),
+        Err(_) => {
+            tracing::warn!("User {user_id} has no usable email address");
+            None
+        }
+    }
+}
+
+/// Whether a mail that failed to send is dropped rather than tried again later
+fn give_up(user_id: &str, err: &io::Error) -> bool {
+    tracing::warn!("Mailing user {user_id} failed: {err}");
+    err.kind() != io::ErrorKind::Interrupted
+}
+
+/// Sends the queued immediate mails. Transient failures stay queued for the next run.
+pub async fn send_immediate_mails(
+    mailer: &Mailer,
+    notif_repo: &NotificationRepository,
+    user_repo: &UserRepository,
+) -> Result<(), io::Error> {
+    let mut done = vec![];
+    for mail in notif_repo.query_outgoing_mails(true).await? {
+        let Some(to) = query_mailbox(user_repo, &mail.user).await else {
+            done.push(mail);
+            continue;
+        };
+        let (subject, body) = immediate_mail(&mail);
+        match mailer.send(to, &subject, body).await {
+            Err(err) if !give_up(&mail.user, &err) => {}
+            _ => done.push(mail),
+        }
+    }
+    notif_repo.delete_outgoing_mails(&done).await
+}
+
+/// Sends every user one digest of their queued mails, leaving out the ones already
+/// handled in the app
+pub async fn send_digests(
+    mailer: &Mailer,
+    notif_repo: &NotificationRepository,
+    user_repo: &UserRepository,
+) -> Result<(), io::Error> {
+    let mut by_user: BTreeMap<String, Vec<OutgoingMail>> = BTreeMap::new();
+    for mail in notif_repo.query_outgoing_mails(false).await? {
+        by_user.entry(mail.user.clone()).or_default().push(mail);
+    }
+
+    for (user_id, mails) in by_user {
+        let mut unhandled = vec![];
+        for mail in &mails {
+            let handled = match &mail.notification {
+                Some(notif) => notif_repo
+                    ._query_notif_by_id(&unwrap_thing(notif.clone()))
+                    .await
+                    .map_or_else(|_| true, |notif| notif.handled),
+                None => false,
+            };
+            if !handled {
+                unhandled.push(mail.clone());
+            }
+        }
+        if let (false, Some(to)) = (unhandled.is_empty(), query_mailbox(user_repo, &user_id).await)
+        {
+            let (subject, body) = digest_mail(&unhandled);
+            if let Err(err) = mailer.send(to, &subject, body).await {
+                if !give_up(&user_id, &err) {
+                    continue;
+                }
+            }
+        }
+        notif_repo.delete_outgoing_mails(&mails).await?;
+    }
+    Ok(())
+}
+
+/// Whether digests are due at `now`, `interval` after the last ones went out
+pub fn digest_due(last: Option<DateTime<Utc>>, interval: Duration, now: DateTime<Utc>) -> bool {
+    last.is_none_or(|last| now >= last + interval)
+}
+
+async fn send_due_digests(
+    mailer: &Mailer,
+    notif_repo: &NotificationRepository,
+    user_repo: &UserRepository,
+    digest_interval: StdDuration,
+    now: DateTime<Utc>,
+) -> Result<(), io::Error> {
+    let last = notif_repo.query_last_digest().await?.map(|last| last.0);
+    let interval = Duration::from_std(digest_interval).map_err(get_io_error)?;
+    if !digest_due(last, interval, now) {
+        return Ok(());
+    }
+    send_digests(mailer, notif_repo, user_repo).await?;
+    notif_repo.update_last_digest(Datetime(now)).await
+}
+
+pub async fn run_mailer(
+    notif_repo: NotificationRepository,
+    user_repo: UserRepository,
+    mailer: Mailer,
+    config: MailerConfig,
+) {
+    let mut interval = tokio::time::interval(config.interval);
+    loop {
+        interval.tick().await;
+        if let Err(err) = send_immediate_mails(&mailer, &notif_repo, &user_repo).await {
+            tracing::warn!("Sending mails failed: {err}");
+        }
+        let digests =
+            send_due_digests(&mailer, &notif_repo, &user_repo, config.digest_interval, Utc::now());
+        if let Err(err) = digests.await {
+            tracing::warn!("Sending digests failed: {err}");
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::io;
+
+use axum_login::AuthUser;
+
+use crate::db::repository::{
+    agenda::AgendaRepository, notification::NotificationRepository, project::ProjectRepository,
+    task::TaskRepository,
+};
+
+use super::{notification::deliver_notif, util::notification::mentioned_to_notif};
+
+/// Where a mention was written, used to build the notification and its source
+pub struct MentionTarget<'a> {
+    /// Shown in the notification title, e.g. `Task`
+    pub subject: &'a str,
+    pub name: &'a str,
+    pub about_id: &'a str,
+    pub about_table: &'a str,
+}
+
+/// An edit of a text that may mention users, by `author_id`
+pub struct MentionEdit<'a> {
+    pub author_id: &'a str,
+    pub old_text: &'a str,
+    pub new_text: &'a str,
+}
+
+/// Usernames mentioned as `@username`, deduplicated and in order of appearance.
+/// An `@` glued to a preceding word (as in an email address) is not a mention.
+pub fn parse_mentions(text: &str) -> Vec<String> {
+    let mut mentions: Vec<String> = vec![];
+    let mut prev: Option<char> = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '@' && !prev.is_some_and(|p| p.is_alphanumeric() || p == '_') {
+            let start = i + 1;
+            let mut end = start;
+            while let Some(&(j, n)) = chars.peek() {
+                if n.is_alphanumeric() || matches!(n, '_' | '-' | '.') {
+                    end = j + n.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let username = text[start..end].trim_end_matches('.');
+            if !username.is_empty() && !mentions.iter().any(|m| m == username) {
+                mentions.push(username.to_owned());
+            }
+            prev = text[..end].chars().last();
+            continue;
+        }
+        prev = Some(c);
+    }
+    mentions
+}
+
+/// Mentions present in `new_text` but not in `old_text`
+pub fn new_mentions(old_text: &str, new_text: &str) -> Vec<String> {
+    let old = parse_mentions(old_text);
+    parse_mentions(new_text)
+        .into_iter()
+        .filter(|mention| !old.contains(mention))
+        .collect()
+}
+
+/// Notifies project members newly mentioned by the edit.
+/// The author is never notified about mentioning themselves.
+pub async fn notify_new_mentions(
+    project_repo: &ProjectRepository,
+    notif_repo: &NotificationRepository,
+    project_id: &str,
+    edit: MentionEdit<'_>,
+    target: MentionTarget<'_>,
+) -> Result<(), io::Error> {
+    let mentions = new_mentions(edit.old_text, edit.new_text);
+    if mentions.is_empty() {
+        return Ok(());
+    }
+
+    let mut members = project_repo.query_members_by_id(project_id).await?;
+    members.push(project_repo.query_admin_by_id(project_id).await?);
+
+    for member in members {
+        if !mentions.contains(&member.username) || member.id() == edit.author_id {
+            continue;
+        }
+        let _ = deliver_notif(
+            notif_repo,
+            &member.id(),
+            target.about_id,
+            target.about_table,
+            mentioned_to_notif(target.subject, target.name, edit.new_text),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Project owning the task, or `None` for tasks in a personal task list
+pub async fn query_project_of_task(
+    task_repo: &TaskRepository,
+    task_id: &str,
+) -> Result<Option<String>, io::Error> {
+    let task_list = task_repo.query_task_list_id_by_task(task_id).await?;
+    let source = task_repo.query_task_list_source(&task_list).await?;
+    Ok(match source.tb.as_str() {
+        "project" => Some(source.id.to_string()),
+        _ => None,
+    })
+}
+
+/// Project owning the agenda, or `None` for personal agendas
+pub async fn query_project_of_agenda(agenda_repo: &AgendaRepository, agenda_id: &str) -> Option<String> {
+    agenda_repo.query_agenda_source_by_id(agenda_id).await.ok()
+}
+
+/// Mention notifications for a task description or a comment under the task.
+/// Tasks in personal task lists have no one to mention.
+pub async fn notify_task_mentions(
+    project_repo: &ProjectRepository,
+    task_repo: &TaskRepository,
+    notif_repo: &NotificationRepository,
+    task_id: &str,
+    subject: &str,
+    edit: MentionEdit<'_>,
+) -> Result<(), io::Error> {
+    let project_id = match query_project_of_task(task_repo, task_id).await? {
+        Some(project_id) => project_id,
+        None => return Ok(()),
+    };
+    let task = task_repo.query_task_by_id(task_id).await?;
+    notify_new_mentions(
+        project_repo,
+        notif_repo,
+        &project_id,
+        edit,
+        MentionTarget {
+            subject,
+            name: &task.name,
+            about_id: task_id,
+            about_table: "task",
+        },
+    )
+    .await
+}
+
+/// Mention notifications for an event description. Events of personal agendas are skipped.
+pub async fn notify_event_mentions(
+    project_repo: &ProjectRepository,
+    agenda_repo: &AgendaRepository,
+    notif_repo: &NotificationRepository,
+    agenda_id: &str,
+    event_id: &str,
+    event_name: &str,
+    edit: MentionEdit<'_>,
+) -> Result<(), io::Error> {
+    let project_id = match query_project_of_agenda(agenda_repo, agenda_id).await {
+        Some(project_id) => project_id,
+        None => return Ok(()),
+    };
+    notify_new_mentions(
+        project_repo,
+        notif_repo,
+        &project_id,
+        edit,
+        MentionTarget {
+            subject: "Event",
+            name: event_name,
+            about_id: event_id,
+            about_table: "event",
+        },
+    )
+    .await
+}
@@ -1,18 +1,70 @@
+pub mod board;
+pub mod bulk_task;
+pub mod custom_field;
 pub mod draft_collaboration;
 pub mod invitation_token;
+pub mod mailer;
+pub mod mention;
 pub mod task_stream;
+pub mod task_history;
+pub mod task_order;
+pub mod task_tree;
+pub mod template;
+pub mod time_tracking;
 pub mod user;
 pub mod notification;
+pub mod outgoing_webhook;
+pub mod recurrence;
+pub mod reminder;
+pub mod search;
+pub mod status_pool;
 pub mod util;
+pub mod workflow;
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
+
+    use chrono::{Duration, TimeZone, Utc};
+
     use crate::{
         db::{
-            model::{status::StatusPool, user::User},
+            model::{
+                notification::{
+                    Notification, NotificationPreference, NotificationSource, NotificationTarget,
+                    OutgoingMail,
+                },
+                status::{Status, StatusPool, StatusRule},
+                task::{CustomFieldValue, FieldValue, Task, TaskLink},
+                template::TemplateLink,
+                time_entry::TimeEntry,
+                user::User,
+            },
             repository::{agenda::AgendaRepository, task::TaskRepository, user::UserRepository},
         },
-        usecase::user::insert_user,
+        usecase::{
+            board::{list_position_for_card, wip_violations},
+            bulk_task::order_by_links,
+            custom_field::TaskFilter,
+            mailer::{digest_due, digest_mail, immediate_mail},
+            mention::{new_mentions, parse_mentions},
+            invitation_token::{gen_token, InvitationInfo, InvitationTokenRepository},
+            notification::{notif_source, plan_delivery, Delivery},
+            outgoing_webhook::{
+                is_internal_ip, render_body, retry_delay, should_retry, sign, WebhookPayload,
+            },
+            search::{self, match_score, snippet},
+            status_pool::plan_status_migration,
+            recurrence::{format_rrule, next_occurrence, parse_rrule},
+            reminder::{due_reminder, format_offset},
+            task_history::diff_tasks,
+            task_stream::blocking_change,
+            template::{template_from_tasks, validate_template},
+            time_tracking::{manual_entry, sum_time},
+            workflow::check_transition,
+            user::insert_user,
+            util::notification::{invitation_answered_to_notif, invitation_to_notif},
+        },
     };
 
     fn create_user() -> User {
@@ -37,4 +89,503 @@ mod test {
             .unwrap();
         assert_eq!(user.username, "test_insert_user");
     }
+
+    #[test]
+    fn test_search_match_score() {
+        assert_eq!(match_score("report", "Report", ""), Some(100));
+        assert!(match_score("report", "report weekly", "") > match_score("report", "weekly report", ""));
+        assert!(match_score("report", "todo", "write the report") > Some(0));
+        assert_eq!(match_score("report", "todo", "nothing"), None);
+    }
+
+    #[test]
+    fn test_search_snippet() {
+        let body = format!("{}needle{}", "a".repeat(60), "b".repeat(60));
+        let snippet = snippet("needle", &body);
+        assert!(snippet.starts_with("...") && snippet.ends_with("..."));
+        assert!(snippet.contains("needle"));
+        assert_eq!(search::snippet("x", "short"), "short");
+    }
+
+    #[test]
+    fn test_parse_mentions() {
+        assert_eq!(
+            parse_mentions("ping @alice and @bob.smith, cc @alice."),
+            vec!["alice", "bob.smith"]
+        );
+        assert!(parse_mentions("mail me at alice@example.com").is_empty());
+        assert!(parse_mentions("just an @ sign").is_empty());
+        assert_eq!(new_mentions("@alice", "@alice @bob"), vec!["bob"]);
+    }
+
+    #[test]
+    fn test_diff_tasks() {
+        let old = Task::new("old".to_string());
+        let mut new = old.clone();
+        assert!(diff_tasks(&old, &new).is_empty());
+
+        new.name = "new".to_string();
+        new.complete = true;
+        new.assignees = Some(vec!["b".to_string(), "a".to_string()]);
+        let changes = diff_tasks(&old, &new);
+        let fields: Vec<_> = changes.iter().map(|change| change.field.as_str()).collect();
+        assert_eq!(fields, vec!["name", "complete", "assignees"]);
+        assert_eq!(changes[2].new, "a, b");
+
+        let mut edited = old.clone();
+        edited.custom_fields = vec![CustomFieldValue {
+            field: "f1".to_string(),
+            value: FieldValue::MultiSelect(vec!["x".to_string(), "y".to_string()]),
+        }];
+        let changes = diff_tasks(&old, &edited);
+        assert_eq!(changes[0].field, "custom_fields");
+        assert_eq!(changes[0].new, "f1=[x, y]");
+    }
+
+    #[test]
+    fn test_check_transition() {
+        let mut pool = StatusPool::new();
+        pool.incomplete = vec![
+            Status { name: "Todo".to_string(), number: "1".to_string(), ..Default::default() },
+            Status { name: "In review".to_string(), number: "2".to_string(), ..Default::default() },
+        ];
+        pool.workflow = vec![StatusRule {
+            to: "2".to_string(),
+            from: Some(vec!["1".to_string()]),
+            required_fields: vec!["pr".to_string()],
+            admin_only: false,
+        }];
+        let mut task = Task::new("task".to_string());
+        task.status = "1".to_string();
+
+        let err = check_transition(&pool, "1", "2", &task, false).unwrap_err();
+        assert!(err.to_string().contains("pr"));
+        task.pr_assigned = true;
+        assert!(check_transition(&pool, "1", "2", &task, false).is_ok());
+        assert!(check_transition(&pool, "complete", "2", &task, false).is_err());
+        assert!(check_transition(&pool, "2", "1", &task, false).is_ok());
+    }
+
+    #[test]
+    fn test_plan_status_migration() {
+        let status = |number: &str| Status {
+            name: number.to_string(),
+            number: number.to_string(),
+            ..Default::default()
+        };
+        let mut old = StatusPool::new();
+        old.incomplete = vec![status("1"), status("2")];
+        let mut new = old.clone();
+        new.incomplete = vec![status("1")];
+
+        assert!(plan_status_migration(&old, &new, &HashMap::new()).is_err());
+        let mapping = HashMap::from([("2".to_string(), "1".to_string())]);
+        let plan = plan_status_migration(&old, &new, &mapping).unwrap();
+        assert_eq!(plan.get("2").map(String::as_str), Some("1"));
+
+        new.incomplete = vec![status("1"), status("1")];
+        assert!(plan_status_migration(&old, &new, &mapping).is_err());
+    }
+
+    #[test]
+    fn test_wip_violations() {
+        let mut pool = StatusPool::new();
+        pool.incomplete = vec![Status {
+            name: "Doing".to_string(),
+            number: "1".to_string(),
+            wip_limit: Some(1),
+            ..Default::default()
+        }];
+        let mut busy = Task::new("busy".to_string());
+        busy.status = "1".to_string();
+        busy.assignees = Some(vec!["alice".to_string()]);
+
+        assert!(wip_violations(&pool, "1", &[], &[]).is_empty());
+        assert_eq!(wip_violations(&pool, "1", &[], &[&busy]).len(), 1);
+
+        pool.incomplete[0].wip_per_assignee = true;
+        assert!(wip_violations(&pool, "1", &["bob".to_string()], &[&busy]).is_empty());
+        assert_eq!(wip_violations(&pool, "1", &["alice".to_string()], &[&busy]).len(), 1);
+    }
+
+    #[test]
+    fn test_list_position_for_card() {
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        let order = ids(&["a", "x", "b", "y"]);
+        let column = ids(&["a", "b"]);
+
+        assert_eq!(list_position_for_card(&order, &column, Some(0)), 0);
+        assert_eq!(list_position_for_card(&order, &column, Some(1)), 2);
+        assert_eq!(list_position_for_card(&order, &column, None), 3);
+        assert_eq!(list_position_for_card(&order, &[], Some(0)), 4);
+    }
+
+    #[test]
+    fn test_task_filter() {
+        let task = Task {
+            priority: Some("high".to_string()),
+            labels: vec!["bug".to_string()],
+            custom_fields: vec![
+                CustomFieldValue {
+                    field: "team".to_string(),
+                    value: FieldValue::SingleSelect("backend".to_string()),
+                },
+                CustomFieldValue {
+                    field: "points".to_string(),
+                    value: FieldValue::Number(3.0),
+                },
+            ],
+            ..Default::default()
+        };
+        let field = |id: &str, value: Option<&str>| TaskFilter {
+            field: Some((id.to_string(), value.map(str::to_string))),
+            ..Default::default()
+        };
+        let priority = |priority: &str| TaskFilter {
+            priority: Some(priority.to_string()),
+            ..Default::default()
+        };
+        let labels = |labels: &[&str]| TaskFilter {
+            labels: labels.iter().map(|label| label.to_string()).collect(),
+            ..Default::default()
+        };
+
+        assert!(TaskFilter::default().matches(&task));
+        assert!(priority("high").matches(&task));
+        assert!(!priority("low").matches(&task));
+        assert!(labels(&["ui", "bug"]).matches(&task));
+        assert!(!labels(&["ui"]).matches(&task));
+        assert!(field("team", None).matches(&task));
+        assert!(field("points", Some("3")).matches(&task));
+        assert!(!field("team", Some("frontend")).matches(&task));
+        assert!(!field("size", None).matches(&task));
+    }
+
+    #[test]
+    fn test_sum_time() {
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap();
+        let entry = |user: &str, minutes: u32| {
+            manual_entry("task", user, start, None, Some(minutes), String::new()).unwrap()
+        };
+        let mut running = TimeEntry::start("task".to_string(), "bob".to_string());
+        running.started_at = start.into();
+        let estimated = Task {
+            estimate: Some(90),
+            ..Default::default()
+        };
+
+        let now = start + Duration::minutes(20);
+        let totals = sum_time(
+            &[&estimated, &Task::default()],
+            &[entry("alice", 30), entry("alice", 15), running],
+            now,
+        );
+        assert_eq!(totals.estimate, 90);
+        assert_eq!(totals.tracked, 65);
+        assert_eq!(totals.per_user["alice"], 45);
+        assert_eq!(totals.per_user["bob"], 20);
+
+        assert!(manual_entry("task", "alice", start, Some(start), None, String::new()).is_err());
+        assert!(manual_entry("task", "alice", start, None, None, String::new()).is_err());
+    }
+
+    #[test]
+    fn test_recurrence() {
+        let rule = parse_rrule("RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH;COUNT=4").unwrap();
+        assert_eq!(format_rrule(&rule), "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH;COUNT=4");
+        assert!(parse_rrule("FREQ=YEARLY").is_err());
+        assert!(parse_rrule("FREQ=DAILY;BYDAY=MO").is_err());
+        assert!(parse_rrule("FREQ=DAILY;COUNT=2;UNTIL=20240101").is_err());
+
+        // 2024-05-06 is a Monday
+        let monday = Utc.with_ymd_and_hms(2024, 5, 6, 17, 0, 0).unwrap();
+        let thursday = Utc.with_ymd_and_hms(2024, 5, 9, 17, 0, 0).unwrap();
+        assert_eq!(next_occurrence(&rule, monday), Some(thursday));
+        assert_eq!(
+            next_occurrence(&rule, thursday),
+            Some(Utc.with_ymd_and_hms(2024, 5, 20, 17, 0, 0).unwrap())
+        );
+
+        let daily = parse_rrule("FREQ=DAILY;UNTIL=20240507").unwrap();
+        assert_eq!(next_occurrence(&daily, monday), Some(monday + Duration::days(1)));
+        assert_eq!(next_occurrence(&daily, monday + Duration::days(1)), None);
+
+        let monthly = parse_rrule("FREQ=MONTHLY;BYMONTHDAY=31").unwrap();
+        assert_eq!(
+            next_occurrence(&monthly, Utc.with_ymd_and_hms(2024, 1, 31, 9, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2024, 2, 29, 9, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_template_from_tasks() {
+        let day = Utc.with_ymd_and_hms(2024, 5, 6, 17, 0, 0).unwrap();
+        let task = |id: &str, ddl| Task {
+            id: Some(surrealdb::sql::Thing::from(("task", id))),
+            ddl,
+            assignees: Some(vec!["alice".to_owned()]),
+            ..Task::new(id.to_owned())
+        };
+        let tasks = vec![
+            task("b", Some((day + Duration::days(2)).into())),
+            task("a", Some(day.into())),
+            Task {
+                parent: Some("a".to_owned()),
+                ..task("c", None)
+            },
+        ];
+        let links = vec![
+            TaskLink {
+                id: None,
+                incoming: Some(("task", "a").into()),
+                outgoing: Some(("task", "b").into()),
+                kind: "dep".to_owned(),
+            },
+            // points outside the captured tasks
+            TaskLink {
+                id: None,
+                incoming: Some(("task", "a").into()),
+                outgoing: Some(("task", "z").into()),
+                kind: "auto".to_owned(),
+            },
+        ];
+
+        let (template_tasks, template_links) = template_from_tasks(&tasks, &links);
+        let offsets: Vec<_> = template_tasks.iter().map(|task| task.deadline_offset).collect();
+        assert_eq!(offsets, vec![Some(2 * 24 * 60), Some(0), None]);
+        assert_eq!(template_tasks[2].parent.as_deref(), Some("t2"));
+        assert_eq!(template_tasks[0].assignees, vec!["alice".to_owned()]);
+        assert_eq!(
+            template_links,
+            vec![TemplateLink {
+                from: "t2".to_owned(),
+                to: "t1".to_owned(),
+                kind: "dep".to_owned(),
+            }]
+        );
+        assert!(validate_template(&template_tasks, &template_links).is_ok());
+
+        let mut cyclic = template_tasks.clone();
+        cyclic[1].parent = Some("t3".to_owned());
+        assert!(validate_template(&cyclic, &template_links).is_err());
+        let dangling = vec![TemplateLink {
+            to: "t9".to_owned(),
+            ..template_links[0].clone()
+        }];
+        assert!(validate_template(&template_tasks, &dangling).is_err());
+    }
+
+    #[test]
+    fn test_order_by_links() {
+        let ids = |ids: &[&str]| -> Vec<String> { ids.iter().map(|id| id.to_string()).collect() };
+        let link = |from: &str, to: &str| (from.to_owned(), to.to_owned());
+
+        let tasks = ids(&["c", "b", "a", "d"]);
+        let links = vec![link("a", "b"), link("b", "c"), link("x", "d")];
+        assert_eq!(order_by_links(&tasks, &links), ids(&["a", "d", "b", "c"]));
+
+        let cyclic = vec![link("a", "b"), link("b", "a")];
+        assert_eq!(order_by_links(&ids(&["b", "a", "c"]), &cyclic), ids(&["c", "b", "a"]));
+    }
+
+    #[test]
+    fn test_due_reminder() {
+        let due = Utc.with_ymd_and_hms(2024, 5, 6, 17, 0, 0).unwrap();
+        let offsets = [24 * 60, 60];
+        assert_eq!(due_reminder(&offsets, due, due - Duration::days(2)), None);
+        assert_eq!(due_reminder(&offsets, due, due - Duration::hours(5)), Some(24 * 60));
+        assert_eq!(due_reminder(&offsets, due, due - Duration::minutes(30)), Some(60));
+        assert_eq!(due_reminder(&offsets, due, due), None);
+
+        assert_eq!(format_offset(24 * 60), "1 day");
+        assert_eq!(format_offset(180), "3 hours");
+        assert_eq!(format_offset(90), "90 minutes");
+    }
+
+    #[test]
+    fn test_plan_delivery() {
+        let in_app = Delivery {
+            in_app: true,
+            email: false,
+            email_digest: false,
+        };
+        let nowhere = Delivery {
+            in_app: false,
+            ..in_app
+        };
+        let mut preference = NotificationPreference::default();
+        assert_eq!(plan_delivery(&preference, "task_assigned", Some("p1")), in_app);
+
+        preference.disabled = vec!["deadline".to_owned()];
+        preference.muted_projects = vec!["p1".to_owned()];
+        assert_eq!(plan_delivery(&preference, "task_overdue", None), nowhere);
+        assert_eq!(plan_delivery(&preference, "mention", Some("p1")), nowhere);
+        assert_eq!(plan_delivery(&preference, "mention", Some("p2")), in_app);
+
+        let digest = Delivery {
+            email_digest: true,
+            ..nowhere
+        };
+        preference.channels = vec!["email_digest".to_owned()];
+        assert_eq!(plan_delivery(&preference, "task_commented", None), digest);
+
+        preference.channels = vec!["email".to_owned()];
+        assert_eq!(plan_delivery(&preference, "task_commented", None), digest);
+        let email = plan_delivery(&preference, "task_assigned", None);
+        assert_eq!(email, Delivery { email: true, ..nowhere });
+    }
+
+    #[test]
+    fn test_mail_templates() {
+        let notif = |title: &str| Notification {
+            kind: "task_assigned".to_owned(),
+            ..Notification::new(title.to_owned(), "Task description: ship it".to_owned())
+        };
+        let mail = OutgoingMail::new("u1", &notif("Task: a has been assigned to you"), true);
+        let (subject, body) = immediate_mail(&mail);
+        assert_eq!(subject, "[just-dev] Task: a has been assigned to you");
+        assert!(body.contains("Task description: ship it"));
+
+        let other = OutgoingMail::new("u1", &notif("Task: b has been assigned to you"), false);
+        let (subject, body) = digest_mail(&[mail, other]);
+        assert_eq!(subject, "[just-dev] 2 unread notifications");
+        assert!(body.starts_with("- Task: a has been assigned to you\n"));
+        assert!(body.contains("- Task: b has been assigned to you\n"));
+
+        let now = Utc::now();
+        assert!(digest_due(None, Duration::days(1), now));
+        assert!(!digest_due(Some(now - Duration::hours(23)), Duration::days(1), now));
+        assert!(digest_due(Some(now - Duration::hours(25)), Duration::days(1), now));
+    }
+
+    #[test]
+    fn test_webhook_delivery() {
+        let signature = sign("key", "The quick brown fox jumps over the lazy dog");
+        assert_eq!(signature, "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8");
+
+        let text = "[Backend] Task \"Deploy\" was completed".to_owned();
+        let payload = WebhookPayload::new("task_completed", "p1", Some(""), text, ());
+        assert_eq!(payload.actor, None);
+        assert_eq!(
+            render_body("slack", &payload),
+            r#"{"text":"[Backend] Task \"Deploy\" was completed"}"#
+        );
+        assert!(render_body("discord", &payload).starts_with(r#"{"content":"#));
+        let json: serde_json::Value =
+            serde_json::from_str(&render_body("json", &payload)).unwrap();
+        assert_eq!(json["event"], "task_completed");
+        assert_eq!(json["project"], "p1");
+
+        let delays: Vec<u64> = (1..=3).map(|retry| retry_delay(retry).as_secs()).collect();
+        assert_eq!(delays, vec![2, 8, 32]);
+        assert!(should_retry(None));
+        assert!(should_retry(Some(503)));
+        assert!(should_retry(Some(429)));
+        assert!(!should_retry(Some(404)));
+
+        for internal in ["127.0.0.1", "10.1.2.3", "192.168.0.1", "169.254.169.254", "::1"] {
+            assert!(is_internal_ip(internal.parse().unwrap()), "{internal}");
+        }
+        assert!(is_internal_ip("::ffff:172.16.0.1".parse().unwrap()));
+        assert!(!is_internal_ip("93.184.216.34".parse().unwrap()));
+        assert!(!is_internal_ip("2606:4700::1111".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_blocking_change() {
+        // the last incomplete predecessor completed
+        assert_eq!(blocking_change(&[(true, true), (true, false)]), Some(false));
+        // another predecessor still holds the task back
+        assert_eq!(blocking_change(&[(true, true), (false, false)]), None);
+        // a completed predecessor was reopened
+        assert_eq!(blocking_change(&[(false, true), (true, false)]), Some(true));
+        assert_eq!(blocking_change(&[(false, true), (false, false)]), None);
+        // several flipped at once, as in bulk edits
+        assert_eq!(blocking_change(&[(true, true), (true, true)]), Some(false));
+        assert_eq!(blocking_change(&[]), None);
+    }
+
+    #[test]
+    fn test_notif_source() {
+        let thing = |table: &str, id: &str| Some(surrealdb::sql::Thing::from((table, id)));
+        let invitations = InvitationTokenRepository::default();
+        let task = NotificationTarget {
+            target: thing("task", "t1"),
+            live: thing("task", "t1"),
+            task_list: thing("task_list", "l1"),
+            task_list_owner: thing("project", "p1"),
+            ..Default::default()
+        };
+        assert_eq!(
+            notif_source(task.clone(), &invitations),
+            NotificationSource::Task {
+                task: "t1".to_owned(),
+                task_list: "l1".to_owned(),
+                owner: "p1".to_owned(),
+            }
+        );
+        let deleted = NotificationSource::Deleted {
+            table: Some("task".to_owned()),
+            id: Some("t1".to_owned()),
+        };
+        let gone = NotificationTarget { live: None, ..task };
+        assert_eq!(notif_source(gone, &invitations), deleted);
+        assert_eq!(
+            notif_source(NotificationTarget::default(), &invitations),
+            NotificationSource::Deleted { table: None, id: None }
+        );
+
+        let draft = NotificationTarget {
+            target: thing("draft", "d1"),
+            live: thing("draft", "d1"),
+            ..Default::default()
+        };
+        let draft = notif_source(draft, &invitations);
+        assert_eq!(draft, NotificationSource::Draft { draft: "d1".to_owned() });
+
+        let invitation = NotificationTarget {
+            target: thing("invitation", "abc"),
+            ..Default::default()
+        };
+        assert!(matches!(
+            notif_source(invitation.clone(), &invitations),
+            NotificationSource::Deleted { .. }
+        ));
+        let mut invitations = InvitationTokenRepository::default();
+        let info = InvitationInfo {
+            inviter: "u1".to_owned(),
+            invitee: "u2".to_owned(),
+            project: "p1".to_owned(),
+        };
+        invitations.tokens.insert("abc".to_owned(), info);
+        assert_eq!(
+            notif_source(invitation, &invitations),
+            NotificationSource::Invitation {
+                token: "abc".to_owned(),
+                project: "p1".to_owned(),
+                inviter: "u1".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_invitation_notifs() {
+        let token = gen_token();
+        assert_eq!(token.len(), 21);
+        assert!(token.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+
+        let invited = invitation_to_notif("alice", "Backend");
+        assert_eq!(invited.title, "alice invited you to project Backend");
+        let preference = NotificationPreference {
+            channels: vec!["email".to_owned()],
+            ..Default::default()
+        };
+        assert!(plan_delivery(&preference, &invited.kind, None).email);
+
+        let accepted = invitation_answered_to_notif("bob", "Backend", true);
+        assert_eq!(accepted.kind, "invitation_accepted");
+        assert_eq!(accepted.title, "bob accepted your invitation to project Backend");
+        let declined = invitation_answered_to_notif("bob", "Backend", false);
+        assert_eq!(declined.kind, "invitation_declined");
+    }
 }
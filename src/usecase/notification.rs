@@ -3,20 +3,110 @@ use std::io;
 use surrealdb::sql::Thing;
 
 use crate::db::{
-    model::notification::{AssetPath, Notification, NotificationSource},
+    model::{
+        comment::Comment,
+        notification::{
+            Notification, NotificationPreference, NotificationSource, NotificationTarget,
+            OutgoingMail,
+        },
+    },
     repository::{
         agenda::AgendaRepository,
-        notification::NotificationRepository,
+        notification::{NotificationEvent, NotificationRepository},
+        project::ProjectRepository,
         task::TaskRepository,
-        utils::{custom_io_error, exec_query, get_io_error},
+        user::UserRepository,
+        utils::{unwrap_thing, DbModelId},
     },
 };
 
+use super::invitation_token::{InvitationInfo, InvitationTokenRepository};
 use super::util::notification::{
-    assigned_event_to_notif, assigned_task_to_notif, deassign_event_to_notif,
-    deassign_task_to_notif,
+    assigned_event_to_notif, assigned_task_to_notif, commented_task_to_notif,
+    deassign_event_to_notif, deassign_task_to_notif, invitation_answered_to_notif,
+    invitation_to_notif,
 };
 
+/// Preference category of a notification kind; kinds without one are always delivered
+pub fn notification_category(kind: &str) -> Option<&'static str> {
+    match kind {
+        "task_assigned" | "task_deassigned" | "task_bulk_assigned" => Some("task_assignment"),
+        "event_assigned" | "event_deassigned" => Some("event_assignment"),
+        "mention" => Some("mention"),
+        "task_commented" => Some("comment"),
+        "task_due_soon" | "task_overdue" | "event_starting_soon" => Some("deadline"),
+        "pr_merged" => Some("pr_merged"),
+        "task_blocked" | "task_unblocked" => Some("dependency"),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Delivery {
+    pub in_app: bool,
+    /// Emailed right away
+    pub email: bool,
+    pub email_digest: bool,
+}
+
+/// Kinds the `email` channel sends right away; it leaves the rest to the digest
+pub const IMMEDIATE_EMAIL_KINDS: [&str; 6] = [
+    "task_assigned",
+    "event_assigned",
+    "mention",
+    "task_overdue",
+    "pr_merged",
+    "invitation_received",
+];
+
+/// Where a notification of `kind` about something in `project` goes for the user
+pub fn plan_delivery(
+    preference: &NotificationPreference,
+    kind: &str,
+    project: Option<&str>,
+) -> Delivery {
+    let muted =
+        project.is_some_and(|project| preference.muted_projects.iter().any(|p| p == project));
+    let disabled = notification_category(kind)
+        .is_some_and(|category| preference.disabled.iter().any(|c| c == category));
+    let wanted = |channel: &str| {
+        !muted && !disabled && preference.channels.iter().any(|c| c == channel)
+    };
+    let immediate = IMMEDIATE_EMAIL_KINDS.contains(&kind);
+    Delivery {
+        in_app: wanted("in_app"),
+        email: wanted("email") && immediate,
+        email_digest: wanted("email_digest") || wanted("email") && !immediate,
+    }
+}
+
+/// Inserts `notif` and/or queues it for email as the user's preferences say.
+/// Returns the in-app notification if one was created.
+pub async fn deliver_notif(
+    notif_repo: &NotificationRepository,
+    user_id: &str,
+    about_id: &str,
+    about_table: &str,
+    notif: Notification,
+) -> Result<Option<Notification>, io::Error> {
+    let preference = notif_repo.query_preference(user_id).await?;
+    let project = match preference.muted_projects.is_empty() {
+        true => None,
+        false => notif_repo.query_project_of_about(about_table, about_id).await?,
+    };
+    let delivery = plan_delivery(&preference, &notif.kind, project.as_deref());
+    let inserted = match delivery.in_app {
+        true => Some(notif_repo.insert_notif(user_id, about_id, about_table, notif.clone()).await?),
+        false => None,
+    };
+    if delivery.email || delivery.email_digest {
+        let notif = inserted.as_ref().unwrap_or(&notif);
+        let mail = OutgoingMail::new(user_id, notif, delivery.email);
+        notif_repo.insert_outgoing_mail(&mail).await?;
+    }
+    Ok(inserted)
+}
+
 pub async fn assign_task_to_user(
     task_repo: &TaskRepository,
     notif_repo: &NotificationRepository,
@@ -25,9 +115,8 @@ pub async fn assign_task_to_user(
 ) -> Result<(), std::io::Error> {
     task_repo._assign_task_to_user(task_id, user_id).await?;
     let task = task_repo.query_task_by_id(task_id).await?;
-    let _ = notif_repo
-        .insert_notif(user_id, task_id, "task", assigned_task_to_notif(task))
-        .await?;
+    let notif = assigned_task_to_notif(task);
+    let _ = deliver_notif(notif_repo, user_id, task_id, "task", notif).await?;
     Ok(())
 }
 
@@ -39,9 +128,8 @@ pub async fn deassign_task_for_user(
 ) -> Result<(), std::io::Error> {
     task_repo._deassign_task_for_user(task_id, user_id).await?;
     let task = task_repo.query_task_by_id(task_id).await?;
-    let _ = notif_repo
-        .insert_notif(user_id, task_id, "task", deassign_task_to_notif(task))
-        .await?;
+    let notif = deassign_task_to_notif(task);
+    let _ = deliver_notif(notif_repo, user_id, task_id, "task", notif).await?;
     Ok(())
 }
 
@@ -55,9 +143,8 @@ pub async fn assign_event_for_user(
         ._assign_event_for_user(event_id, user_id)
         .await?;
     let event = agenda_repo.query_event_by_id(event_id).await?;
-    let _ = notif_repo
-        .insert_notif(user_id, event_id, "event", assigned_event_to_notif(event))
-        .await?;
+    let notif = assigned_event_to_notif(event);
+    let _ = deliver_notif(notif_repo, user_id, event_id, "event", notif).await?;
     Ok(())
 }
 
@@ -71,57 +158,144 @@ pub async fn deassign_event_for_user(
         ._deassign_event_for_user(event_id, user_id)
         .await?;
     let event = agenda_repo.query_event_by_id(event_id).await?;
-    let _ = notif_repo
-        .insert_notif(user_id, event_id, "event", deassign_event_to_notif(event))
-        .await?;
+    let notif = deassign_event_to_notif(event);
+    let _ = deliver_notif(notif_repo, user_id, event_id, "event", notif).await?;
     Ok(())
 }
 
+/// Notifies every assignee of the task except the comment's author
+pub async fn notify_task_comment(
+    task_repo: &TaskRepository,
+    notif_repo: &NotificationRepository,
+    task_id: &str,
+    comment: &Comment,
+) -> Result<(), std::io::Error> {
+    let task = task_repo.query_task_by_id(task_id).await?;
+    for assignee in task.assignees.clone().unwrap_or_default() {
+        if assignee == comment.author {
+            continue;
+        }
+        let notif = commented_task_to_notif(task.clone(), comment);
+        let _ = deliver_notif(notif_repo, &assignee, task_id, "task", notif).await?;
+    }
+    Ok(())
+}
+
+/// Tells the invitee about the invitation. The notification points at the token, which
+/// it can be accepted or declined with.
+pub async fn notify_invitation(
+    project_repo: &ProjectRepository,
+    user_repo: &UserRepository,
+    notif_repo: &NotificationRepository,
+    token: &str,
+    info: &InvitationInfo,
+) -> Result<(), io::Error> {
+    let inviter = user_repo.query_user_by_id(&info.inviter).await?;
+    let project = project_repo.query_project_by_id(&info.project).await?;
+    let notif = invitation_to_notif(&inviter.username, &project.name);
+    let _ = deliver_notif(notif_repo, &info.invitee, token, "invitation", notif).await?;
+    Ok(())
+}
+
+/// Settles the invitee's notifications about the answered invitation `tokens` and tells
+/// the inviter whether it was accepted
+pub async fn notify_invitation_answer(
+    project_repo: &ProjectRepository,
+    user_repo: &UserRepository,
+    notif_repo: &NotificationRepository,
+    tokens: &[String],
+    info: &InvitationInfo,
+    accepted: bool,
+) -> Result<(), io::Error> {
+    for token in tokens {
+        for user_id in notif_repo.handle_notifs_about("invitation", token).await? {
+            notif_repo.hub.publish(&user_id, NotificationEvent::Updated);
+        }
+    }
+    let invitee = user_repo.query_user_by_id(&info.invitee).await?;
+    let project = project_repo.query_project_by_id(&info.project).await?;
+    let notif = invitation_answered_to_notif(&invitee.username, &project.name, accepted);
+    let _ = deliver_notif(notif_repo, &info.inviter, &info.project, "project", notif).await?;
+    Ok(())
+}
+
+/// Source of a notification from its stored target. Invitations resolve against the
+/// pending tokens; targets that are gone or cut off from their path report as deleted.
+pub fn notif_source(
+    target: NotificationTarget,
+    invitations: &InvitationTokenRepository,
+) -> NotificationSource {
+    let Some(thing) = target.target else {
+        return NotificationSource::Deleted { table: None, id: None };
+    };
+    let table = thing.tb.clone();
+    let id = unwrap_thing(thing);
+    if table == "invitation" {
+        if let Some(info) = invitations.tokens.get(&id) {
+            return NotificationSource::Invitation {
+                token: id,
+                project: info.project.clone(),
+                inviter: info.inviter.clone(),
+            };
+        }
+    }
+
+    let path = |first: Option<Thing>, second: Option<Thing>| {
+        Some((unwrap_thing(first?), unwrap_thing(second?)))
+    };
+    let source = match (target.live.is_some(), table.as_str()) {
+        (false, _) => None,
+        (true, "task") => path(target.task_list, target.task_list_owner).map(
+            |(task_list, owner)| NotificationSource::Task {
+                task: id.clone(),
+                task_list,
+                owner,
+            },
+        ),
+        (true, "event") => path(target.agenda, target.agenda_owner).map(|(agenda, owner)| {
+            NotificationSource::Event {
+                event: id.clone(),
+                agenda,
+                owner,
+            }
+        }),
+        (true, "draft") => Some(NotificationSource::Draft { draft: id.clone() }),
+        (true, "requirement") => target.requirement_project.map(|project| {
+            NotificationSource::Requirement {
+                requirement: id.clone(),
+                project: unwrap_thing(project),
+            }
+        }),
+        (true, "project") => Some(NotificationSource::Project { project: id.clone() }),
+        _ => None,
+    };
+    source.unwrap_or(NotificationSource::Deleted {
+        table: Some(table),
+        id: Some(id),
+    })
+}
+
+/// Notifications with their sources, resolved in one query and in the order of `ids`
+pub async fn query_notifs_with_sources(
+    notif_repo: &NotificationRepository,
+    invitations: &InvitationTokenRepository,
+    ids: &[DbModelId],
+) -> Result<Vec<(Notification, NotificationSource)>, io::Error> {
+    Ok(notif_repo
+        .query_notifs_with_targets(ids)
+        .await?
+        .into_iter()
+        .map(|(notif, target)| (notif, notif_source(target, invitations)))
+        .collect())
+}
+
 pub async fn query_notif_by_id(
     notif_repo: &NotificationRepository,
-    task_repo: &TaskRepository,
-    agenda_repo: &AgendaRepository,
-    // draft_repo: &DraftRepository,
+    invitations: &InvitationTokenRepository,
     id: &str,
 ) -> Result<(Notification, NotificationSource), io::Error> {
-    let notif: Option<Notification> = notif_repo
-        .context
-        .db
-        .select(("notification", id))
-        .await
-        .map_err(get_io_error)?;
-    let mut response = exec_query(
-        &notif_repo.context,
-        format!(
-            "select ->about.out as source from notification where id == notification:{}",
-            id
-        ),
-    )
-    .await?;
-    let source = response
-        .take::<Option<Vec<Thing>>>((0, "source"))
-        .map_err(get_io_error)?
-        .unwrap_or_default()
+    query_notifs_with_sources(notif_repo, invitations, &[id.to_owned()])
+        .await?
         .pop()
-        .unwrap_or(Thing{tb: "".to_owned(), id: surrealdb::sql::Id::String("".to_owned())});
-    let source_id = source.id.clone().to_string();
-    let source = match source.tb.as_str() {
-        "task" => NotificationSource::Task(AssetPath(
-            source_id.to_owned(),
-            task_repo.query_task_path_by_id(&source_id).await?,
-        )),
-        "event" => NotificationSource::Event(AssetPath(
-            source_id.to_owned(),
-            agenda_repo.query_event_path_by_id(&source_id).await?,
-        )),
-        // "draft" => NotificationSource::Draft(source.id.to_string()),
-        _ => NotificationSource::Task(AssetPath(
-            "The target Resource not exists any more".to_owned(),
-            ("Dont_click".to_owned(), "Dont_click".to_owned()),
-        )),
-    };
-    Ok((
-        notif.ok_or(custom_io_error("Notification find failed"))?,
-        source,
-    ))
+        .ok_or(io::Error::new(io::ErrorKind::NotFound, "Notification not found"))
 }
@@ -0,0 +1,244 @@
+use std::{env, io, net::IpAddr, time::Duration as StdDuration};
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::json;
+use sha2::Sha256;
+
+use crate::db::{
+    model::outgoing_webhook::{OutgoingWebhook, WebhookDelivery},
+    repository::{
+        outgoing_webhook::OutgoingWebhookRepository,
+        utils::{get_io_error, get_str_id, DbModelId},
+    },
+};
+
+/// Attempts per delivery, the first one included
+pub const MAX_ATTEMPTS: u32 = 4;
+
+/// What is sent for an event. Chat formats only get `text`.
+#[derive(Clone, Debug, Serialize)]
+pub struct WebhookPayload {
+    pub event: String,
+    pub project: DbModelId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actor: Option<DbModelId>,
+    /// One line summary, e.g. `[Backend] Task "Deploy" was completed`
+    pub text: String,
+    pub data: serde_json::Value,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl WebhookPayload {
+    pub fn new(
+        event: &str,
+        project: &str,
+        actor: Option<&str>,
+        text: String,
+        data: impl Serialize,
+    ) -> Self {
+        WebhookPayload {
+            event: event.to_owned(),
+            project: project.to_owned(),
+            actor: actor.filter(|actor| !actor.is_empty()).map(str::to_owned),
+            text,
+            data: serde_json::to_value(data).unwrap_or_default(),
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Body sent to a webhook of the given format
+pub fn render_body(format: &str, payload: &WebhookPayload) -> String {
+    let body = match format {
+        "slack" => json!({ "text": payload.text }),
+        "discord" => json!({ "content": payload.text }),
+        _ => serde_json::to_value(payload).unwrap_or_default(),
+    };
+    body.to_string()
+}
+
+/// Hex HMAC-SHA256 of the body, sent as `X-JustDev-Signature: sha256=<signature>`
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Wait before the given retry: 2s, 8s, 32s, ...
+pub fn retry_delay(retry: u32) -> StdDuration {
+    StdDuration::from_secs(2 * 4u64.pow(retry.saturating_sub(1)))
+}
+
+/// Whether an attempt that ended with `status` (`None` if nothing answered) is worth
+/// repeating. Other client errors will not go away by themselves.
+pub fn should_retry(status: Option<u16>) -> bool {
+    match status {
+        None => true,
+        Some(status) => status == 429 || status >= 500,
+    }
+}
+
+/// Loopback, private, link-local and other addresses off the public internet
+pub fn is_internal_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                // shared address space of carrier-grade NAT
+                || (a == 100 && b & 0xc0 == 64)
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || first & 0xfe00 == 0xfc00
+                || first & 0xffc0 == 0xfe80
+                || ip.to_ipv4_mapped().is_some_and(|ip| is_internal_ip(IpAddr::V4(ip)))
+        }
+    }
+}
+
+/// Rejects URLs that are not http(s) or whose host resolves to an internal address,
+/// unless the host is one of `allowed_hosts`
+pub async fn check_webhook_url(url: &str, allowed_hosts: &[String]) -> Result<(), io::Error> {
+    let invalid = |reason: &str| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("Webhook URL \"{url}\" {reason}"))
+    };
+    let parsed = reqwest::Url::parse(url).map_err(|_| invalid("is not a valid URL"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(invalid("is not http(s)"));
+    }
+    let host = parsed.host_str().ok_or_else(|| invalid("has no host"))?;
+    if allowed_hosts.iter().any(|allowed| allowed == host) {
+        return Ok(());
+    }
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let mut addrs = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|_| invalid("does not resolve"))?;
+    match addrs.any(|addr| is_internal_ip(addr.ip())) {
+        true => Err(invalid("points at an internal address")),
+        false => Ok(()),
+    }
+}
+
+/// Sends payloads to the webhooks of a project in the background, retrying failed
+/// attempts with backoff and logging every delivery
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    hook_repo: OutgoingWebhookRepository,
+    client: reqwest::Client,
+    /// Internal hosts webhooks may still reach, `JUST_DEV_WEBHOOK_ALLOWED_HOSTS`
+    /// (comma separated)
+    allowed_hosts: Vec<String>,
+}
+
+impl WebhookDispatcher {
+    pub fn new(hook_repo: OutgoingWebhookRepository) -> Self {
+        // a redirect could lead to an internal host
+        let client = reqwest::Client::builder()
+            .timeout(StdDuration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap_or_default();
+        let allowed_hosts = env::var("JUST_DEV_WEBHOOK_ALLOWED_HOSTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|host| !host.is_empty())
+            .map(str::to_owned)
+            .collect();
+        Self {
+            hook_repo,
+            client,
+            allowed_hosts,
+        }
+    }
+
+    pub async fn check_url(&self, url: &str) -> Result<(), io::Error> {
+        check_webhook_url(url, &self.allowed_hosts).await
+    }
+
+    /// Queues the payload for every active webhook of its project subscribed to its event.
+    /// Failures are logged rather than returned so they never fail the triggering request.
+    pub async fn dispatch(&self, payload: WebhookPayload) {
+        let webhooks = match self.hook_repo.query_webhooks_by_project(&payload.project).await {
+            Ok(webhooks) => webhooks,
+            Err(err) => {
+                tracing::warn!("Looking up webhooks failed: {err}");
+                return;
+            }
+        };
+        for webhook in webhooks {
+            if webhook.active && webhook.events.contains(&payload.event) {
+                tokio::spawn(self.clone().deliver(webhook, payload.clone()));
+            }
+        }
+    }
+
+    async fn deliver(self, webhook: OutgoingWebhook, payload: WebhookPayload) {
+        let body = render_body(&webhook.format, &payload);
+        let delivery = WebhookDelivery::new(get_str_id(&webhook.id), payload.event, body);
+        if let Err(err) = self.send_with_retries(&webhook, delivery).await {
+            tracing::warn!("Logging webhook delivery failed: {err}");
+        }
+    }
+
+    async fn send_with_retries(
+        &self,
+        webhook: &OutgoingWebhook,
+        delivery: WebhookDelivery,
+    ) -> Result<(), io::Error> {
+        let mut delivery = self.hook_repo.insert_delivery(&delivery).await?;
+        let delivery_id = get_str_id(&delivery.id);
+        // the host may resolve differently than when the webhook was saved
+        if let Err(err) = self.check_url(&webhook.url).await {
+            delivery.attempts = 1;
+            delivery.error = Some(err.to_string());
+            self.hook_repo.update_delivery(&delivery_id, &delivery).await?;
+            return Ok(());
+        }
+        let signature = format!("sha256={}", sign(&webhook.secret, &delivery.body));
+        loop {
+            if delivery.attempts > 0 {
+                tokio::time::sleep(retry_delay(delivery.attempts)).await;
+            }
+            delivery.attempts += 1;
+            let response = self
+                .client
+                .post(&webhook.url)
+                .header("Content-Type", "application/json")
+                .header("X-JustDev-Event", &delivery.event)
+                .header("X-JustDev-Delivery", &delivery_id)
+                .header("X-JustDev-Signature", &signature)
+                .body(delivery.body.clone())
+                .send()
+                .await;
+            (delivery.status, delivery.error) = match response {
+                Ok(response) => {
+                    let status = response.status();
+                    let error = (!status.is_success()).then(|| status.to_string());
+                    (Some(status.as_u16()), error)
+                }
+                Err(err) => (None, Some(get_io_error(err).to_string())),
+            };
+            delivery.delivered = delivery.error.is_none();
+            delivery = self.hook_repo.update_delivery(&delivery_id, &delivery).await?;
+            if delivery.delivered
+                || delivery.attempts >= MAX_ATTEMPTS
+                || !should_retry(delivery.status)
+            {
+                return Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::io;
+
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, Utc};
+use surrealdb::sql::Datetime;
+
+use crate::db::{
+    model::task::{Recurrence, Task},
+    repository::{
+        notification::NotificationRepository,
+        project::ProjectRepository,
+        task::{TaskEvent, TaskRepository},
+        user::UserRepository,
+        utils::unwrap_thing,
+    },
+};
+
+use super::{board::query_task_list_scope, notification::assign_task_to_user};
+
+const WEEKDAYS: [&str; 7] = ["MO", "TU", "WE", "TH", "FR", "SA", "SU"];
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Parses an RRULE such as `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH;COUNT=10`.
+/// Supports `FREQ` (daily, weekly, monthly), `INTERVAL`, `BYDAY` for weekly rules,
+/// `BYMONTHDAY` for monthly rules, and `COUNT` or `UNTIL`.
+pub fn parse_rrule(rule: &str) -> Result<Recurrence, io::Error> {
+    let mut recurrence = Recurrence {
+        interval: 1,
+        ..Default::default()
+    };
+    let rule = rule.trim();
+    let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+    for part in rule.split(';').filter(|part| !part.is_empty()) {
+        let (key, value) = part
+            .split_once('=')
+            .ok_or_else(|| invalid(format!("Malformed rule part \"{part}\"")))?;
+        let number = || {
+            value
+                .parse::<u32>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| invalid(format!("{key} must be a positive number")))
+        };
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                recurrence.freq = match value.to_ascii_uppercase().as_str() {
+                    "DAILY" => "daily",
+                    "WEEKLY" => "weekly",
+                    "MONTHLY" => "monthly",
+                    _ => return Err(invalid(format!("Unsupported frequency \"{value}\""))),
+                }
+                .to_owned()
+            }
+            "INTERVAL" => recurrence.interval = number()?,
+            "COUNT" => recurrence.count = Some(number()?),
+            "BYMONTHDAY" => {
+                let day = number()?;
+                if day > 31 {
+                    return Err(invalid("BYMONTHDAY must be between 1 and 31".to_owned()));
+                }
+                recurrence.by_month_day = Some(day);
+            }
+            "BYDAY" => {
+                for day in value.split(',') {
+                    let day = day.to_ascii_uppercase();
+                    if !WEEKDAYS.contains(&day.as_str()) {
+                        return Err(invalid(format!("Unknown weekday \"{day}\"")));
+                    }
+                    if !recurrence.by_day.contains(&day) {
+                        recurrence.by_day.push(day);
+                    }
+                }
+            }
+            "UNTIL" => {
+                let until = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ")
+                    .or_else(|_| {
+                        NaiveDate::parse_from_str(value, "%Y%m%d")
+                            .map(|date| date.and_hms_opt(23, 59, 59).unwrap_or_default())
+                    })
+                    .map_err(|_| invalid(format!("Malformed UNTIL \"{value}\"")))?;
+                recurrence.until = Some(Datetime(until.and_utc()));
+            }
+            _ => return Err(invalid(format!("Unsupported rule part \"{key}\""))),
+        }
+    }
+
+    if recurrence.freq.is_empty() {
+        return Err(invalid("FREQ is required".to_owned()));
+    }
+    if !recurrence.by_day.is_empty() && recurrence.freq != "weekly" {
+        return Err(invalid("BYDAY is only supported for weekly rules".to_owned()));
+    }
+    if recurrence.by_month_day.is_some() && recurrence.freq != "monthly" {
+        return Err(invalid("BYMONTHDAY is only supported for monthly rules".to_owned()));
+    }
+    if recurrence.count.is_some() && recurrence.until.is_some() {
+        return Err(invalid("COUNT and UNTIL cannot be combined".to_owned()));
+    }
+    Ok(recurrence)
+}
+
+pub fn format_rrule(recurrence: &Recurrence) -> String {
+    let mut parts = vec![format!("FREQ={}", recurrence.freq.to_ascii_uppercase())];
+    if recurrence.interval > 1 {
+        parts.push(format!("INTERVAL={}", recurrence.interval));
+    }
+    if !recurrence.by_day.is_empty() {
+        parts.push(format!("BYDAY={}", recurrence.by_day.join(",")));
+    }
+    if let Some(day) = recurrence.by_month_day {
+        parts.push(format!("BYMONTHDAY={day}"));
+    }
+    if let Some(count) = recurrence.count {
+        parts.push(format!("COUNT={count}"));
+    }
+    if let Some(until) = &recurrence.until {
+        parts.push(format!("UNTIL={}", until.0.format("%Y%m%dT%H%M%SZ")));
+    }
+    parts.join(";")
+}
+
+/// Deadline of the occurrence following one due at `from`, or `None` past `UNTIL`
+pub fn next_occurrence(recurrence: &Recurrence, from: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let interval = recurrence.interval.max(1);
+    let next = match recurrence.freq.as_str() {
+        "daily" => from + Duration::days(interval.into()),
+        "weekly" => {
+            let mut days: Vec<i64> = recurrence
+                .by_day
+                .iter()
+                .filter_map(|day| WEEKDAYS.iter().position(|d| d == day))
+                .map(|day| day as i64)
+                .collect();
+            days.sort();
+            let current = i64::from(from.weekday().num_days_from_monday());
+            match (days.iter().find(|day| **day > current), days.first()) {
+                (Some(day), _) => from + Duration::days(day - current),
+                (None, Some(first)) => {
+                    from + Duration::days(7 * i64::from(interval) - current + first)
+                }
+                (None, None) => from + Duration::weeks(interval.into()),
+            }
+        }
+        "monthly" => {
+            let month = from
+                .date_naive()
+                .with_day(1)?
+                .checked_add_months(Months::new(interval))?;
+            let last_day = month.checked_add_months(Months::new(1))?.pred_opt()?.day();
+            let day = recurrence.by_month_day.unwrap_or(from.day()).min(last_day);
+            month.with_day(day)?.and_time(from.time()).and_utc()
+        }
+        _ => return None,
+    };
+    match &recurrence.until {
+        Some(until) if next > until.0 => None,
+        _ => Some(next),
+    }
+}
+
+/// Spawns the next occurrence of a recurring task that `old` → `new` completed.
+/// The new task lands at the end of the same task list, under the same parent, with the
+/// next deadline, copying assignees and description; the completed one stays as history.
+pub async fn spawn_next_occurrence(
+    task_repo: &TaskRepository,
+    project_repo: &ProjectRepository,
+    user_repo: &UserRepository,
+    notif_repo: &NotificationRepository,
+    task_id: &str,
+    old: &Task,
+    new: &Task,
+) -> Result<Option<Task>, io::Error> {
+    let Some(recurrence) = &new.recurrence else {
+        return Ok(None);
+    };
+    if old.complete || !new.complete {
+        return Ok(None);
+    }
+    if recurrence.count.is_some_and(|count| new.occurrence + 1 >= count) {
+        return Ok(None);
+    }
+    let series = new.series.clone().unwrap_or(task_id.to_owned());
+    let occurrence = new.occurrence + 1;
+    // reopening and completing a task again must not spawn a second copy
+    if task_repo
+        .query_tasks_of_series(&series)
+        .await?
+        .iter()
+        .any(|task| task.occurrence == occurrence)
+    {
+        return Ok(None);
+    }
+    let base = new.ddl.as_ref().map_or(Utc::now(), |ddl| ddl.0);
+    let Some(ddl) = next_occurrence(recurrence, base) else {
+        return Ok(None);
+    };
+
+    let task_list = task_repo.query_task_list_id_by_task(task_id).await?;
+    let scope = query_task_list_scope(task_repo, project_repo, user_repo, &task_list).await?;
+    let status = scope
+        .status_pool
+        .incomplete
+        .first()
+        .map_or("0".to_owned(), |status| status.number.clone());
+
+    let assignees = new.assignees.clone().unwrap_or_default();
+    let next = Task {
+        id: None,
+        status,
+        complete: false,
+        ddl: Some(Datetime(ddl)),
+        pr: Default::default(),
+        pr_number: 0,
+        pr_assigned: false,
+        series: Some(series),
+        occurrence,
+        parent: None,
+        progress: None,
+        ..new.clone()
+    };
+    // inserting ranks it after the rest of the list rather than next to the completed one
+    let mut next = task_repo.insert_task_for_task_list(&next, &task_list).await?;
+    let next_id = unwrap_thing(next.id.clone().unwrap());
+    for assignee in &assignees {
+        assign_task_to_user(task_repo, notif_repo, &next_id, assignee).await?;
+    }
+    if let Some(parent) = task_repo.query_task_by_id(task_id).await?.parent {
+        task_repo.set_parent_of_task(&next_id, &parent).await?;
+    }
+    next.assignees = Some(assignees);
+    task_repo.events.publish(TaskEvent::Created(next_id));
+    Ok(Some(next))
+}
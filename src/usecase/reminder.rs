@@ -0,0 +1,215 @@
+use std::{env, io, time::Duration as StdDuration};
+
+use chrono::{DateTime, Duration, Utc};
+use surrealdb::sql::{Datetime, Thing};
+
+use crate::db::{
+    model::{notification::Notification, reminder::SentReminder, task::Task},
+    repository::{
+        agenda::AgendaRepository, notification::NotificationRepository, task::TaskRepository,
+        user::UserRepository, utils::get_str_id,
+    },
+};
+
+use super::{
+    notification::deliver_notif,
+    util::notification::{event_reminder_to_notif, task_overdue_to_notif, task_reminder_to_notif},
+};
+
+/// When reminders go out, read from `JUST_DEV_REMINDER_OFFSETS` (minutes before a
+/// deadline, comma separated) and `JUST_DEV_REMINDER_INTERVAL` (seconds between scans)
+#[derive(Clone, Debug)]
+pub struct ReminderConfig {
+    pub offsets: Vec<i64>,
+    pub interval: StdDuration,
+}
+
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        Self {
+            offsets: vec![24 * 60, 60],
+            interval: StdDuration::from_secs(60),
+        }
+    }
+}
+
+impl ReminderConfig {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let offsets = env::var("JUST_DEV_REMINDER_OFFSETS").ok().map(|offsets| {
+            offsets
+                .split(',')
+                .filter_map(|offset| offset.trim().parse::<i64>().ok())
+                .filter(|offset| *offset > 0)
+                .collect::<Vec<_>>()
+        });
+        let interval = env::var("JUST_DEV_REMINDER_INTERVAL")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .map(StdDuration::from_secs);
+        Self {
+            offsets: offsets.unwrap_or(default.offsets),
+            interval: interval.unwrap_or(default.interval),
+        }
+    }
+}
+
+/// The reminder due at `now` for a deadline: the smallest offset whose window has opened.
+/// Offsets missed while the server was down collapse into the latest one.
+pub fn due_reminder(offsets: &[i64], due: DateTime<Utc>, now: DateTime<Utc>) -> Option<i64> {
+    if now >= due {
+        return None;
+    }
+    offsets
+        .iter()
+        .copied()
+        .filter(|offset| now >= due - Duration::minutes(*offset))
+        .min()
+}
+
+pub fn is_overdue(task: &Task, now: DateTime<Utc>) -> bool {
+    !task.complete && task.ddl.as_ref().is_some_and(|ddl| ddl.0 <= now)
+}
+
+/// "1 day", "3 hours", "30 minutes"
+pub fn format_offset(minutes: i64) -> String {
+    let (count, unit) = match minutes {
+        m if m >= 24 * 60 && m % (24 * 60) == 0 => (m / (24 * 60), "day"),
+        m if m >= 60 && m % 60 == 0 => (m / 60, "hour"),
+        m => (m, "minute"),
+    };
+    match count {
+        1 => format!("1 {unit}"),
+        _ => format!("{count} {unit}s"),
+    }
+}
+
+/// Sends `notif` unless this reminder went out before
+async fn send_once(
+    notif_repo: &NotificationRepository,
+    reminder: SentReminder,
+    notif: Notification,
+) -> Result<(), io::Error> {
+    if notif_repo.is_reminder_sent(&reminder).await? {
+        return Ok(());
+    }
+    let about = reminder.about.id.to_string();
+    deliver_notif(notif_repo, &reminder.user, &about, &reminder.about.tb, notif).await?;
+    notif_repo.insert_sent_reminder(&reminder).await?;
+    Ok(())
+}
+
+/// One scan over every user's assigned tasks and agenda events, and over the
+/// assignees of upcoming project events
+pub async fn send_due_reminders(
+    task_repo: &TaskRepository,
+    agenda_repo: &AgendaRepository,
+    user_repo: &UserRepository,
+    notif_repo: &NotificationRepository,
+    config: &ReminderConfig,
+    now: DateTime<Utc>,
+) -> Result<(), io::Error> {
+    for user in user_repo.query_all_user_ids().await? {
+        for (task, _, _) in task_repo.query_assigned_tasks_by_user(&user).await? {
+            let Some(ddl) = task.ddl.clone() else {
+                continue;
+            };
+            if task.complete {
+                continue;
+            }
+            let about = Thing::from(("task", get_str_id(&task.id).as_str()));
+            let (kind, offset, notif) = if is_overdue(&task, now) {
+                ("overdue", 0, task_overdue_to_notif(task))
+            } else if let Some(offset) = due_reminder(&config.offsets, ddl.0, now) {
+                ("reminder", offset, task_reminder_to_notif(task, &format_offset(offset)))
+            } else {
+                continue;
+            };
+            let reminder = SentReminder {
+                id: None,
+                about,
+                user: user.clone(),
+                kind: kind.to_owned(),
+                offset,
+                due: ddl,
+            };
+            send_once(notif_repo, reminder, notif).await?;
+        }
+
+        for agenda in user_repo.query_agenda_by_id_without_from_project(&user).await? {
+            let events = agenda_repo.query_events_by_agenda_id(&agenda).await?;
+            let ids: Vec<_> = events.iter().map(|event| get_str_id(&event.id)).collect();
+            // copies of project events are reminded about through the project event
+            let copies = agenda_repo.query_event_copies(&ids).await?;
+            for event in events {
+                if copies.contains(&get_str_id(&event.id)) {
+                    continue;
+                }
+                let start = event.start_time.0;
+                let Some(offset) = due_reminder(&config.offsets, start, now) else {
+                    continue;
+                };
+                let reminder = SentReminder {
+                    id: None,
+                    about: Thing::from(("event", get_str_id(&event.id).as_str())),
+                    user: user.clone(),
+                    kind: "reminder".to_owned(),
+                    offset,
+                    due: Datetime(start),
+                };
+                let notif = event_reminder_to_notif(event, &format_offset(offset));
+                send_once(notif_repo, reminder, notif).await?;
+            }
+        }
+    }
+
+    let max_offset = config.offsets.iter().copied().max().unwrap_or_default();
+    let (from, until) = (Datetime(now), Datetime(now + Duration::minutes(max_offset)));
+    for event in agenda_repo.query_project_events_starting_between(from, until).await? {
+        let start = event.start_time.0;
+        let Some(offset) = due_reminder(&config.offsets, start, now) else {
+            continue;
+        };
+        let event_id = get_str_id(&event.id);
+        for user in agenda_repo.query_assignees_of_event(&event_id).await? {
+            let reminder = SentReminder {
+                id: None,
+                about: Thing::from(("event", event_id.as_str())),
+                user,
+                kind: "reminder".to_owned(),
+                offset,
+                due: Datetime(start),
+            };
+            let notif = event_reminder_to_notif(event.clone(), &format_offset(offset));
+            send_once(notif_repo, reminder, notif).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Scans for due reminders every `config.interval` until the server stops
+pub async fn run_reminder_scheduler(
+    task_repo: TaskRepository,
+    agenda_repo: AgendaRepository,
+    user_repo: UserRepository,
+    notif_repo: NotificationRepository,
+    config: ReminderConfig,
+) {
+    let mut interval = tokio::time::interval(config.interval);
+    loop {
+        interval.tick().await;
+        if let Err(err) = send_due_reminders(
+            &task_repo,
+            &agenda_repo,
+            &user_repo,
+            &notif_repo,
+            &config,
+            Utc::now(),
+        )
+        .await
+        {
+            tracing::warn!("Sending reminders failed: {err}");
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::io;
+
+use futures::future::try_join_all;
+
+use crate::{
+    api::model::{
+        asset::{Asset, DraftPath, EventPath, RequirementPath, TaskPath},
+        search::SearchResult,
+    },
+    db::repository::{
+        agenda::AgendaRepository,
+        draft::DraftRepository,
+        project::ProjectRepository,
+        requirement::RequirementRepository,
+        task::TaskRepository,
+        user::UserRepository,
+        utils::{get_str_id, DbModelId},
+    },
+};
+
+use super::draft_collaboration::extract_draft_text;
+
+const SNIPPET_RADIUS: usize = 40;
+
+pub struct SearchRepos<'a> {
+    pub user_repo: &'a UserRepository,
+    pub project_repo: &'a ProjectRepository,
+    pub task_repo: &'a TaskRepository,
+    pub agenda_repo: &'a AgendaRepository,
+    pub requ_repo: &'a RequirementRepository,
+    pub draft_repo: &'a DraftRepository,
+}
+
+/// Searches tasks, events, requirements and drafts of every project the user joined
+/// and of the user's personal space. Results are ordered by descending score.
+pub async fn search_for_user(
+    repos: SearchRepos<'_>,
+    user_id: &str,
+    keyword: &str,
+) -> Result<Vec<SearchResult>, io::Error> {
+    let keyword = keyword.trim().to_lowercase();
+    if keyword.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let (admin_projects, member_projects) =
+        repos.user_repo.query_project_join_by_id(user_id).await?;
+    let projects: Vec<DbModelId> = admin_projects
+        .iter()
+        .chain(member_projects.iter())
+        .map(|project| get_str_id(&project.id))
+        .collect();
+
+    // (container id, owner id) where the owner is a project or the user itself
+    let mut task_lists: Vec<(DbModelId, DbModelId)> = repos
+        .user_repo
+        .query_task_list_by_id_without_from_project(user_id)
+        .await?
+        .into_iter()
+        .map(|list| (list, user_id.to_owned()))
+        .collect();
+    let mut agendas: Vec<(DbModelId, DbModelId)> = repos
+        .user_repo
+        .query_agenda_by_id_without_from_project(user_id)
+        .await?
+        .into_iter()
+        .map(|agenda| (agenda, user_id.to_owned()))
+        .collect();
+    for project in &projects {
+        task_lists.extend(
+            repos
+                .project_repo
+                .query_task_list_by_id(project)
+                .await?
+                .into_iter()
+                .map(|list| (list, project.clone())),
+        );
+        agendas.extend(
+            repos
+                .project_repo
+                .query_agenda_by_id(project)
+                .await?
+                .into_iter()
+                .map(|agenda| (agenda, project.clone())),
+        );
+    }
+
+    let owner_of = |containers: &Vec<(DbModelId, DbModelId)>, id: &DbModelId| {
+        containers
+            .iter()
+            .find(|(container, _)| container == id)
+            .map(|(_, owner)| owner.clone())
+            .unwrap_or_default()
+    };
+
+    let mut results = vec![];
+
+    let list_ids: Vec<_> = task_lists.iter().map(|(list, _)| list.clone()).collect();
+    for (task, task_list_id) in repos
+        .task_repo
+        .search_tasks_in_task_lists(&list_ids, &keyword)
+        .await?
+    {
+        if let Some(score) = match_score(&keyword, &task.name, &task.description) {
+            results.push(SearchResult {
+                asset: Asset::Task {
+                    path: TaskPath {
+                        task_id: get_str_id(&task.id),
+                        project_id: owner_of(&task_lists, &task_list_id),
+                        task_list_id,
+                    },
+                },
+                snippet: snippet(&keyword, &task.description),
+                name: task.name,
+                score,
+            });
+        }
+    }
+
+    let agenda_ids: Vec<_> = agendas.iter().map(|(agenda, _)| agenda.clone()).collect();
+    for (event, agenda_id) in repos
+        .agenda_repo
+        .search_events_in_agendas(&agenda_ids, &keyword)
+        .await?
+    {
+        if let Some(score) = match_score(&keyword, &event.name, &event.description) {
+            results.push(SearchResult {
+                asset: Asset::Event {
+                    path: EventPath {
+                        event_id: get_str_id(&event.id),
+                        project_id: owner_of(&agendas, &agenda_id),
+                        agenda_id,
+                    },
+                },
+                snippet: snippet(&keyword, &event.description),
+                name: event.name,
+                score,
+            });
+        }
+    }
+
+    for (requ, project_id) in repos
+        .requ_repo
+        .search_requs_in_projects(&projects, &keyword)
+        .await?
+    {
+        if let Some(score) = match_score(&keyword, &requ.name, &requ.description) {
+            results.push(SearchResult {
+                asset: Asset::Requirement {
+                    path: RequirementPath {
+                        requirement_id: get_str_id(&requ.id),
+                        project_id,
+                    },
+                },
+                snippet: snippet(&keyword, &requ.description),
+                name: requ.name,
+                score,
+            });
+        }
+    }
+
+    let draft_ids = repos.user_repo.query_draft_by_id(user_id).await?;
+    let drafts = try_join_all(
+        draft_ids
+            .iter()
+            .map(|id| async move { repos.draft_repo.query_draft_by_id(id).await }),
+    )
+    .await?;
+    for draft in drafts {
+        let text = extract_draft_text(&draft.content);
+        if let Some(score) = match_score(&keyword, &draft.name, &text) {
+            results.push(SearchResult {
+                asset: Asset::Draft {
+                    path: DraftPath {
+                        id: draft.id.unwrap_or_default(),
+                    },
+                },
+                snippet: snippet(&keyword, &text),
+                name: draft.name,
+                score,
+            });
+        }
+    }
+
+    results.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.name.cmp(&b.name)));
+    Ok(results)
+}
+
+/// Scores a hit: name matches outweigh body matches, exact and prefix names rank highest.
+/// `keyword` must already be lowercase. Returns `None` when nothing matches.
+pub fn match_score(keyword: &str, name: &str, body: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    let name_score = if name == keyword {
+        100
+    } else if name.starts_with(keyword) {
+        60
+    } else if name.contains(keyword) {
+        40
+    } else {
+        0
+    };
+    let body_score = (body.to_lowercase().matches(keyword).count() as u32 * 5).min(30);
+
+    match name_score + body_score {
+        0 => None,
+        score => Some(score),
+    }
+}
+
+/// Text around the first occurrence of `keyword` in `body`, or its beginning if absent.
+pub fn snippet(keyword: &str, body: &str) -> String {
+    let lowercase = body.to_lowercase();
+    let chars: Vec<char> = body.chars().collect();
+    // lowercasing can change byte lengths, so locate the hit by char index
+    let hit = lowercase
+        .find(keyword)
+        .map(|byte| lowercase[..byte].chars().count())
+        .unwrap_or(0)
+        .min(chars.len());
+    let start = hit.saturating_sub(SNIPPET_RADIUS);
+    let end = (hit + keyword.chars().count() + SNIPPET_RADIUS).min(chars.len());
+
+    let mut snippet: String = chars[start..end].iter().collect();
+    if start > 0 {
+        snippet.insert_str(0, "...");
+    }
+    if end < chars.len() {
+        snippet.push_str("...");
+    }
+    snippet
+}
@@ -0,0 +1,163 @@
+use std::{collections::HashMap, io};
+
+use surrealdb::sql::Thing;
+
+use crate::db::{
+    model::{status::StatusPool, task::Task},
+    repository::{
+        notification::NotificationRepository, project::ProjectRepository, task::TaskRepository,
+        user::UserRepository, utils::DbModelId,
+    },
+};
+
+use super::{
+    recurrence::spawn_next_occurrence,
+    task_history::{record_task_change, SOURCE_USER},
+    task_stream::{check_task_switch_complete, refresh_task_status_entry, TaskSwitchable},
+    task_tree::refresh_auto_complete,
+};
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Checks the new pool and resolves where tasks in removed statuses go.
+/// `mapping` maps every removed status number to a remaining number or `complete`.
+pub fn plan_status_migration(
+    old: &StatusPool,
+    new: &StatusPool,
+    mapping: &HashMap<String, String>,
+) -> Result<HashMap<String, String>, io::Error> {
+    let mut ids: Vec<&str> = vec![];
+    for status in &new.incomplete {
+        let id = status.number.as_str();
+        if id.is_empty() || id == "complete" {
+            return Err(invalid(format!("\"{id}\" is not a valid status id")));
+        }
+        if ids.contains(&id) {
+            return Err(invalid(format!("Status id \"{id}\" is used more than once")));
+        }
+        ids.push(id);
+    }
+    let exists = |key: &str| key == "complete" || ids.contains(&key);
+
+    for rule in &new.workflow {
+        let referenced = std::iter::once(&rule.to).chain(rule.from.iter().flatten());
+        if let Some(unknown) = referenced.into_iter().find(|key| !exists(key)) {
+            return Err(invalid(format!("Workflow refers to unknown status \"{unknown}\"")));
+        }
+    }
+
+    let removed: Vec<&str> = old
+        .incomplete
+        .iter()
+        .map(|status| status.number.as_str())
+        .filter(|id| !ids.contains(id))
+        .collect();
+    if let Some(key) = mapping.keys().find(|key| !removed.contains(&key.as_str())) {
+        return Err(invalid(format!("Status \"{key}\" is not being removed")));
+    }
+
+    let mut plan = HashMap::new();
+    for id in removed {
+        let target = mapping
+            .get(id)
+            .ok_or_else(|| invalid(format!("Removed status \"{id}\" needs a replacement")))?;
+        if !exists(target) {
+            return Err(invalid(format!("Replacement status \"{target}\" does not exist")));
+        }
+        plan.insert(id.to_owned(), target.clone());
+    }
+    Ok(plan)
+}
+
+pub struct StatusMigration {
+    /// How many tasks left each removed status
+    pub counts: HashMap<String, usize>,
+    /// Tasks the migration completed, with their state before it
+    pub completed: Vec<(DbModelId, Task)>,
+}
+
+/// Replaces the status pool of `owner` (a project or user) and moves the tasks of
+/// `task_lists` out of removed statuses in one transaction.
+/// Tasks held back by a predecessor cannot be mapped to `complete`.
+pub async fn migrate_status_pool(
+    task_repo: &TaskRepository,
+    owner: Thing,
+    task_lists: &[DbModelId],
+    old: &StatusPool,
+    new: &StatusPool,
+    mapping: &HashMap<String, String>,
+    actor_id: &str,
+) -> Result<StatusMigration, io::Error> {
+    let plan = plan_status_migration(old, new, mapping)?;
+
+    let mut affected = vec![];
+    for list in task_lists {
+        for task_id in task_repo.query_all_tasks_of_task_list(list).await? {
+            let task = task_repo.query_task_by_id(&task_id).await?;
+            if let Some(target) = plan.get(&task.status) {
+                let completes = !task.complete && target == "complete";
+                if completes {
+                    let switchable = check_task_switch_complete(&task_id, task_repo).await?;
+                    if let TaskSwitchable::False = switchable {
+                        return Err(invalid(format!(
+                            "Task {task_id} is blocked by an incomplete predecessor"
+                        )));
+                    }
+                }
+                let mut migrated = task.clone();
+                migrated.status = target.clone();
+                migrated.complete = migrated.complete || target == "complete";
+                affected.push((task_id, task, migrated));
+            }
+        }
+    }
+
+    let updates: Vec<_> = affected
+        .iter()
+        .map(|(id, _, migrated)| (id.clone(), migrated.status.clone(), migrated.complete))
+        .collect();
+    task_repo.replace_status_pool(owner, new, &updates).await?;
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut completed = vec![];
+    for (task_id, task, migrated) in affected {
+        record_task_change(task_repo, &task_id, &task, &migrated, SOURCE_USER, Some(actor_id))
+            .await?;
+        *counts.entry(task.status.clone()).or_default() += 1;
+        if !task.complete && migrated.complete {
+            completed.push((task_id, task));
+        }
+    }
+    Ok(StatusMigration { counts, completed })
+}
+
+/// Runs what completing a task sets off for the tasks a migration completed: successors,
+/// auto-completing parents and the next occurrence of recurring tasks.
+pub async fn finish_status_migration(
+    task_repo: &TaskRepository,
+    project_repo: &ProjectRepository,
+    user_repo: &UserRepository,
+    notif_repo: &NotificationRepository,
+    completed: &[(DbModelId, Task)],
+) -> Result<(), io::Error> {
+    for (task_id, old) in completed {
+        refresh_task_status_entry(task_id, task_repo, notif_repo).await?;
+        if let Some(parent) = &old.parent {
+            refresh_auto_complete(parent, task_repo, notif_repo).await?;
+        }
+        let task = task_repo.query_task_by_id(task_id).await?;
+        spawn_next_occurrence(
+            task_repo,
+            project_repo,
+            user_repo,
+            notif_repo,
+            task_id,
+            old,
+            &task,
+        )
+        .await?;
+    }
+    Ok(())
+}
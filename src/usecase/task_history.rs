@@ -0,0 +1,99 @@
+use std::io;
+
+use crate::db::{
+    model::task::{FieldChange, FieldValue, Task, TaskChange},
+    repository::task::TaskRepository,
+};
+
+use super::recurrence::format_rrule;
+
+pub const SOURCE_USER: &str = "user";
+pub const SOURCE_SYSTEM: &str = "system";
+pub const SOURCE_GITHUB: &str = "github";
+
+/// Field-level differences between two versions of a task
+pub fn diff_tasks(old: &Task, new: &Task) -> Vec<FieldChange> {
+    let deadline = |task: &Task| task.ddl.as_ref().map(|ddl| ddl.0.to_rfc3339()).unwrap_or_default();
+    let assignees = |task: &Task| {
+        let mut assignees = task.assignees.clone().unwrap_or_default();
+        assignees.sort();
+        assignees.join(", ")
+    };
+    let estimate = |task: &Task| task.estimate.map(|m| m.to_string()).unwrap_or_default();
+    let recurrence = |task: &Task| task.recurrence.as_ref().map(format_rrule).unwrap_or_default();
+    let priority = |task: &Task| task.priority.as_deref().unwrap_or_default().to_owned();
+    let custom_fields = |task: &Task| {
+        let mut values: Vec<_> = task
+            .custom_fields
+            .iter()
+            .map(|custom| format!("{}={}", custom.field, format_field_value(&custom.value)))
+            .collect();
+        values.sort();
+        values.join(", ")
+    };
+    let pr = |task: &Task| match task.pr_assigned {
+        true => format!("{}/{}#{}", task.pr.owner, task.pr.repo, task.pr.pull_number),
+        false => String::new(),
+    };
+
+    [
+        ("name", old.name.clone(), new.name.clone()),
+        ("description", old.description.clone(), new.description.clone()),
+        ("status", old.status.clone(), new.status.clone()),
+        ("complete", old.complete.to_string(), new.complete.to_string()),
+        ("deadline", deadline(old), deadline(new)),
+        ("assignees", assignees(old), assignees(new)),
+        ("pr", pr(old), pr(new)),
+        ("priority", priority(old), priority(new)),
+        ("labels", old.labels.join(", "), new.labels.join(", ")),
+        ("estimate", estimate(old), estimate(new)),
+        ("recurrence", recurrence(old), recurrence(new)),
+        ("custom_fields", custom_fields(old), custom_fields(new)),
+    ]
+    .into_iter()
+    .filter(|(_, old, new)| old != new)
+    .map(|(field, old, new)| FieldChange {
+        field: field.to_owned(),
+        old,
+        new,
+    })
+    .collect()
+}
+
+fn format_field_value(value: &FieldValue) -> String {
+    match value {
+        FieldValue::Text(text) | FieldValue::SingleSelect(text) => text.clone(),
+        FieldValue::Number(number) => number.to_string(),
+        FieldValue::Date(date) => date.0.to_rfc3339(),
+        FieldValue::MultiSelect(options) => format!("[{}]", options.join(", ")),
+    }
+}
+
+/// History entry for the task, `None` if nothing tracked changed
+pub fn task_change(
+    task_id: &str,
+    old: &Task,
+    new: &Task,
+    source: &str,
+    actor: Option<&str>,
+) -> Option<TaskChange> {
+    let changes = diff_tasks(old, new);
+    (!changes.is_empty()).then(|| {
+        TaskChange::new(task_id.to_owned(), source, actor.map(str::to_owned), changes)
+    })
+}
+
+/// Appends a history entry for the task unless nothing tracked changed
+pub async fn record_task_change(
+    task_repo: &TaskRepository,
+    task_id: &str,
+    old: &Task,
+    new: &Task,
+    source: &str,
+    actor: Option<&str>,
+) -> Result<(), io::Error> {
+    if let Some(change) = task_change(task_id, old, new, source, actor) {
+        task_repo.insert_task_change(&change).await?;
+    }
+    Ok(())
+}
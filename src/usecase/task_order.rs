@@ -0,0 +1,77 @@
+use std::io;
+
+use crate::db::{
+    model::task::Task,
+    repository::{notification::NotificationRepository, task::TaskRepository, utils::DbModelId},
+};
+
+use super::task_tree::refresh_auto_complete;
+
+/// Moves a task and its subtree into `task_list_id`, placing the task at `position`
+/// (the end when `None`). The task keeps its id, so links and notifications still resolve.
+/// Lists must belong to the same project or the same user.
+pub async fn move_task(
+    task_id: &str,
+    task_list_id: &str,
+    position: Option<usize>,
+    task_repo: &TaskRepository,
+    notif_repo: &NotificationRepository,
+) -> Result<Task, io::Error> {
+    let from_list = task_repo.query_task_list_id_by_task(task_id).await?;
+    let mut moved = vec![task_id.to_owned()];
+    if from_list != task_list_id {
+        let from_source = task_repo.query_task_list_source(&from_list).await?;
+        let to_source = task_repo.query_task_list_source(task_list_id).await?;
+        if from_source != to_source {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Tasks can only move between task lists of the same owner",
+            ));
+        }
+
+        // a subtask leaving its parent's list becomes top level
+        let task = task_repo.query_task_by_id(task_id).await?;
+        if let Some(parent) = task.parent {
+            task_repo.remove_parent_of_task(task_id).await?;
+            refresh_auto_complete(&parent, task_repo, notif_repo).await?;
+        }
+        task_repo.move_task_to_task_list(task_id, task_list_id).await?;
+        for id in task_repo.query_descendants_of_task(task_id).await? {
+            task_repo.move_task_to_task_list(&id, task_list_id).await?;
+            moved.push(id);
+        }
+    }
+
+    // the subtree follows the task instead of keeping ranks from the old list
+    let mut order = task_repo.query_all_tasks_of_task_list(task_list_id).await?;
+    order.retain(|id| !moved.contains(id));
+    let position = position.unwrap_or(order.len()).min(order.len());
+    order.splice(position..position, moved);
+    task_repo.update_task_ranks(&order).await?;
+
+    task_repo.query_task_by_id(task_id).await
+}
+
+/// Puts `tasks` first, in the given order, followed by the remaining tasks of the list
+/// in their current order. Returns the resulting order.
+pub async fn reorder_tasks(
+    task_list_id: &str,
+    tasks: Vec<DbModelId>,
+    task_repo: &TaskRepository,
+) -> Result<Vec<DbModelId>, io::Error> {
+    let current = task_repo.query_all_tasks_of_task_list(task_list_id).await?;
+    for (i, task) in tasks.iter().enumerate() {
+        if !current.contains(task) || tasks[..i].contains(task) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Task {task} is not in the task list or is listed twice"),
+            ));
+        }
+    }
+
+    let rest: Vec<_> = current.into_iter().filter(|task| !tasks.contains(task)).collect();
+    let mut order = tasks;
+    order.extend(rest);
+    task_repo.update_task_ranks(&order).await?;
+    Ok(order)
+}
@@ -1,11 +1,116 @@
 
-use crate::db::repository::{task::TaskRepository, utils::unwrap_thing};
+use crate::db::model::task::Task;
+use crate::db::repository::{
+    notification::NotificationRepository,
+    task::{TaskEvent, TaskRepository},
+    utils::{unwrap_thing, DbModelId},
+};
+use crate::usecase::notification::deliver_notif;
+use crate::usecase::task_history::{record_task_change, SOURCE_SYSTEM};
+use crate::usecase::task_tree::refresh_auto_complete;
+use crate::usecase::util::notification::{blocked_task_to_notif, unblocked_task_to_notif};
+use std::collections::HashMap;
 use std::io::{self};
 
+/// Whether a task went from blocked to unblocked (`Some(false)`) or back (`Some(true)`).
+/// Takes the completion of each `dep` predecessor and whether it just flipped.
+pub fn blocking_change(predecessors: &[(bool, bool)]) -> Option<bool> {
+    let blocked = predecessors.iter().any(|(complete, _)| !complete);
+    let was_blocked = predecessors
+        .iter()
+        .any(|(complete, flipped)| complete == flipped);
+    (blocked != was_blocked).then_some(blocked)
+}
+
+/// Tells the assignees of the task that it got blocked or unblocked by the
+/// predecessors in `flipped`, or by incomplete ones in `unlinked` that stopped being a
+/// `dep` predecessor. Finished tasks are left alone.
+async fn notify_blocking_change(
+    task_id: &str,
+    task_repo: &TaskRepository,
+    notif_repo: &NotificationRepository,
+    flipped: &[DbModelId],
+    unlinked: &[DbModelId],
+) -> Result<(), io::Error> {
+    let task = task_repo.query_task_by_id(task_id).await?;
+    if task.complete {
+        return Ok(());
+    }
+    let mut predecessors = vec![];
+    let mut flipped_tasks = vec![];
+    for link in task_repo.query_task_incoming_links_by_task_id(task_id).await? {
+        if link.kind != "dep" {
+            continue;
+        }
+        let pre_id = unwrap_thing(link.incoming.unwrap());
+        let pre_task = task_repo.query_task_by_id(&pre_id).await?;
+        let is_flipped = flipped.contains(&pre_id);
+        predecessors.push((pre_task.complete, is_flipped));
+        if is_flipped {
+            flipped_tasks.push(pre_task);
+        }
+    }
+    for pre_id in unlinked {
+        // held the task back until now
+        predecessors.push((true, true));
+        flipped_tasks.push(task_repo.query_task_by_id(pre_id).await?);
+    }
+    let Some(blocked) = blocking_change(&predecessors) else {
+        return Ok(());
+    };
+
+    for assignee in task.assignees.clone().unwrap_or_default() {
+        let notif = match blocked {
+            true => blocked_task_to_notif(task.clone(), &flipped_tasks),
+            false => unblocked_task_to_notif(task.clone(), &flipped_tasks),
+        };
+        let _ = deliver_notif(notif_repo, &assignee, task_id, "task", notif).await?;
+    }
+    Ok(())
+}
+
+/// Brings the task in line with its predecessors. `flipped` are the predecessors whose
+/// completion just changed; assignees hear about the task getting blocked or unblocked.
 pub async fn refresh_task_status(
     task_id: &String,
     task_repo: &TaskRepository,
+    notif_repo: &NotificationRepository,
+    flipped: &[DbModelId],
 ) -> Result<(), std::io::Error> {
+    sync_with_predecessors(task_id, task_repo, notif_repo).await?;
+    if !flipped.is_empty() {
+        notify_blocking_change(task_id, task_repo, notif_repo, flipped, &[]).await?;
+    }
+    Ok(())
+}
+
+/// Like `refresh_task_status` after the link from `from` to `to` was created, deleted or
+/// changed its kind. Assignees hear about it if the link started or stopped being a `dep`
+/// link to an incomplete task.
+pub async fn refresh_linked_task_status(
+    to: &String,
+    from: &str,
+    was_dep: bool,
+    is_dep: bool,
+    task_repo: &TaskRepository,
+    notif_repo: &NotificationRepository,
+) -> Result<(), io::Error> {
+    sync_with_predecessors(to, task_repo, notif_repo).await?;
+    if was_dep == is_dep || task_repo.query_task_by_id(from).await?.complete {
+        return Ok(());
+    }
+    let from = [from.to_owned()];
+    match is_dep {
+        true => notify_blocking_change(to, task_repo, notif_repo, &from, &[]).await,
+        false => notify_blocking_change(to, task_repo, notif_repo, &[], &from).await,
+    }
+}
+
+async fn sync_with_predecessors(
+    task_id: &String,
+    task_repo: &TaskRepository,
+    notif_repo: &NotificationRepository,
+) -> Result<(), io::Error> {
     // get all pre tasks
     // strategy:
     // 1. status between incomplete status can be changed freely
@@ -31,12 +136,8 @@ pub async fn refresh_task_status(
         if pre_task.complete == false {
             new_task.complete = false;
             if new_task.complete != db_task.complete {
-                task_repo.update_task_by_id(&task_id, &new_task).await?;
-                Box::pin(async move {
-                    refresh_task_status_entry(task_id, task_repo).await?;
-                    Ok::<_, io::Error>(())
-                })
-                .await?;
+                write_forced_completion(task_id, &db_task, &new_task, task_repo, notif_repo)
+                    .await?;
             }
             return Ok(());
         }
@@ -51,39 +152,55 @@ pub async fn refresh_task_status(
         {
             new_task.complete = false;
             if db_task.complete != new_task.complete {
-                task_repo.update_task_by_id(&task_id, &new_task).await?;
-                Box::pin(async move {
-                    refresh_task_status_entry(task_id, task_repo).await?;
-                    Ok::<_, io::Error>(())
-                })
-                .await?;
+                write_forced_completion(task_id, &db_task, &new_task, task_repo, notif_repo)
+                    .await?;
             }
             return Ok(());
         }
 
         new_task.complete = true;
         if db_task.complete != new_task.complete {
-            task_repo.update_task_by_id(&task_id, &new_task).await?;
-            Box::pin(async move {
-                refresh_task_status_entry(task_id, task_repo).await?;
-                Ok::<_, io::Error>(())
-            })
-            .await?;
+            write_forced_completion(task_id, &db_task, &new_task, task_repo, notif_repo).await?;
         }
     }
 
     Ok(())
 }
 
+/// Saves a completion the predecessors forced on the task and passes it on to its
+/// successors
+async fn write_forced_completion(
+    task_id: &str,
+    old: &Task,
+    new: &Task,
+    task_repo: &TaskRepository,
+    notif_repo: &NotificationRepository,
+) -> Result<(), io::Error> {
+    task_repo.update_task_by_id(task_id, new).await?;
+    record_task_change(task_repo, task_id, old, new, SOURCE_SYSTEM, None).await?;
+    task_repo.events.publish(TaskEvent::Updated(task_id.to_owned()));
+    Box::pin(async move {
+        refresh_task_status_entry(task_id, task_repo, notif_repo).await?;
+        if let Some(parent) = &new.parent {
+            refresh_auto_complete(parent, task_repo, notif_repo).await?;
+        }
+        Ok::<_, io::Error>(())
+    })
+    .await
+}
+
 pub async fn refresh_task_status_entry(
     task_id: &str,
     task_repo: &TaskRepository,
+    notif_repo: &NotificationRepository,
 ) -> Result<(), io::Error> {
     let outgoings = task_repo
         .query_task_outgoing_links_by_task_id(task_id)
         .await?;
+    let flipped = [task_id.to_owned()];
     for link in outgoings {
-        refresh_task_status(&unwrap_thing(link.outgoing.unwrap()), task_repo).await?;
+        let successor = unwrap_thing(link.outgoing.unwrap());
+        refresh_task_status(&successor, task_repo, notif_repo, &flipped).await?;
     }
     Ok(())
 }
@@ -98,6 +215,20 @@ pub async fn check_task_switch_complete(
     task_id: &str,
     task_repo: &TaskRepository,
 ) -> Result<TaskSwitchable, io::Error> {
+    check_task_switch_complete_planned(task_id, task_repo, &HashMap::new()).await
+}
+
+/// Like `check_task_switch_complete`, but predecessors in `planned` count with the
+/// completion about to be written rather than the stored one
+pub async fn check_task_switch_complete_planned(
+    task_id: &str,
+    task_repo: &TaskRepository,
+    planned: &HashMap<DbModelId, bool>,
+) -> Result<TaskSwitchable, io::Error> {
+    let complete = |task: &Task| {
+        let id = task.id.clone().map(unwrap_thing).unwrap_or_default();
+        planned.get(&id).copied().unwrap_or(task.complete)
+    };
     let pre_tasks_links = task_repo
         .query_task_incoming_links_by_task_id(task_id)
         .await?;
@@ -110,7 +241,7 @@ pub async fn check_task_switch_complete(
         let pre_task = task_repo
             .query_task_by_id(&unwrap_thing(dep_task.to_owned().incoming.unwrap()))
             .await?;
-        if pre_task.complete == false {
+        if !complete(&pre_task) {
             return Ok(TaskSwitchable::False);
         }
     }
@@ -119,8 +250,8 @@ pub async fn check_task_switch_complete(
         if task_repo
             .task_links_to_tasks(pre_tasks_links)
             .await?
-            .into_iter()
-            .all(|task| task.complete == true)
+            .iter()
+            .all(complete)
         {
             return Ok(TaskSwitchable::True);
         }
@@ -0,0 +1,124 @@
+use std::io;
+
+use crate::db::{
+    model::task::Task,
+    repository::{
+        notification::NotificationRepository,
+        task::{TaskEvent, TaskRepository},
+        utils::DbModelId,
+    },
+};
+
+use super::{
+    task_history::{record_task_change, SOURCE_SYSTEM},
+    task_stream::refresh_task_status_entry,
+};
+
+/// Keeps `task_id` in line with its subtasks if it opted into auto-completion: completes it
+/// once all of them are complete and reopens it when one is reopened, then walks up to its
+/// parent. Does nothing for tasks without subtasks.
+pub async fn refresh_auto_complete(
+    task_id: &str,
+    task_repo: &TaskRepository,
+    notif_repo: &NotificationRepository,
+) -> Result<(), io::Error> {
+    let mut task_id = task_id.to_owned();
+    loop {
+        let mut task = task_repo.query_task_by_id(&task_id).await?;
+        let Some(all_complete) = task
+            .progress
+            .as_ref()
+            .map(|progress| progress.complete == progress.total)
+        else {
+            return Ok(());
+        };
+        if !task.auto_complete || task.complete == all_complete {
+            return Ok(());
+        }
+
+        let old = task.clone();
+        (task.complete, task.status) = match all_complete {
+            true => (true, "complete".to_owned()),
+            false => (false, "incomplete".to_owned()),
+        };
+        task_repo.update_task_by_id(&task_id, &task).await?;
+        record_task_change(task_repo, &task_id, &old, &task, SOURCE_SYSTEM, None).await?;
+        task_repo.events.publish(TaskEvent::Updated(task_id.clone()));
+        refresh_task_status_entry(&task_id, task_repo, notif_repo).await?;
+
+        match task.parent {
+            Some(parent) => task_id = parent,
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Re-parents a task together with its subtree. `None` detaches it to the top level.
+/// When the new parent lives in another task list, the whole subtree follows it.
+pub async fn move_subtree(
+    task_id: &str,
+    parent_id: Option<&str>,
+    task_repo: &TaskRepository,
+    notif_repo: &NotificationRepository,
+) -> Result<Task, io::Error> {
+    let task = task_repo.query_task_by_id(task_id).await?;
+
+    match parent_id {
+        Some(parent_id) => {
+            let descendants = task_repo.query_descendants_of_task(task_id).await?;
+            if parent_id == task_id || descendants.iter().any(|id| id == parent_id) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "A task cannot become a subtask of its own subtree",
+                ));
+            }
+            let task_list = task_repo.query_task_list_id_by_task(task_id).await?;
+            let parent_list = task_repo.query_task_list_id_by_task(parent_id).await?;
+            if task_list != parent_list {
+                let source = task_repo.query_task_list_source(&task_list).await?;
+                let parent_source = task_repo.query_task_list_source(&parent_list).await?;
+                if source != parent_source {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Tasks can only move between task lists of the same owner",
+                    ));
+                }
+            }
+            task_repo.set_parent_of_task(task_id, parent_id).await?;
+
+            if task_list != parent_list {
+                task_repo.move_task_to_task_list(task_id, &parent_list).await?;
+                for id in &descendants {
+                    task_repo.move_task_to_task_list(id, &parent_list).await?;
+                }
+            }
+            refresh_auto_complete(parent_id, task_repo, notif_repo).await?;
+        }
+        None => task_repo.remove_parent_of_task(task_id).await?,
+    }
+
+    // the old parent may have lost its last incomplete subtask
+    if let Some(old_parent) = task.parent.filter(|old| Some(old.as_str()) != parent_id) {
+        refresh_auto_complete(&old_parent, task_repo, notif_repo).await?;
+    }
+    task_repo.query_task_by_id(task_id).await
+}
+
+/// Deletes a task and all of its subtasks. Returns the ids of the deleted tasks,
+/// subtasks first.
+pub async fn delete_subtree(
+    task_id: &str,
+    task_repo: &TaskRepository,
+    notif_repo: &NotificationRepository,
+) -> Result<Vec<DbModelId>, io::Error> {
+    let task = task_repo.query_task_by_id(task_id).await?;
+    let mut deleted = task_repo.query_descendants_of_task(task_id).await?;
+    deleted.reverse();
+    deleted.push(task_id.to_owned());
+    task_repo.delete_tasks(&deleted).await?;
+
+    if let Some(parent) = task.parent {
+        refresh_auto_complete(&parent, task_repo, notif_repo).await?;
+    }
+    Ok(deleted)
+}
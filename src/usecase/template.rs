@@ -0,0 +1,230 @@
+use std::{collections::HashMap, io};
+
+use chrono::{DateTime, Duration, Utc};
+use surrealdb::sql::Datetime;
+
+use crate::db::{
+    model::{
+        task::{Task, TaskLink},
+        template::{Template, TemplateLink, TemplateTask},
+    },
+    repository::{
+        notification::NotificationRepository,
+        project::ProjectRepository,
+        task::TaskRepository,
+        user::UserRepository,
+        utils::{get_str_id, unwrap_thing, DbModelId},
+    },
+};
+
+use super::{
+    board::{query_scope_tasks, query_task_list_scope},
+    custom_field::query_project_of_task_list,
+    notification::assign_task_to_user,
+    task_stream::refresh_task_status,
+};
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Captures tasks and the links among them. Deadlines become offsets from the
+/// earliest deadline, so the first task falls due when the template is instantiated.
+pub fn template_from_tasks(
+    tasks: &[Task],
+    links: &[TaskLink],
+) -> (Vec<TemplateTask>, Vec<TemplateLink>) {
+    let keys: HashMap<DbModelId, String> = tasks
+        .iter()
+        .enumerate()
+        .map(|(i, task)| (get_str_id(&task.id), format!("t{}", i + 1)))
+        .collect();
+    let anchor = tasks.iter().filter_map(|task| task.ddl.as_ref()).map(|ddl| ddl.0).min();
+
+    let template_tasks = tasks
+        .iter()
+        .map(|task| TemplateTask {
+            key: keys[&get_str_id(&task.id)].clone(),
+            name: task.name.clone(),
+            description: task.description.clone(),
+            deadline_offset: task
+                .ddl
+                .as_ref()
+                .zip(anchor)
+                .map(|(ddl, anchor)| (ddl.0 - anchor).num_minutes()),
+            assignees: task.assignees.clone().unwrap_or_default(),
+            priority: task.priority.clone(),
+            labels: task.labels.clone(),
+            estimate: task.estimate,
+            auto_complete: task.auto_complete,
+            parent: task.parent.as_ref().and_then(|parent| keys.get(parent).cloned()),
+        })
+        .collect();
+
+    let template_links = links
+        .iter()
+        .filter_map(|link| {
+            let from = keys.get(&unwrap_thing(link.incoming.clone()?))?;
+            let to = keys.get(&unwrap_thing(link.outgoing.clone()?))?;
+            Some(TemplateLink {
+                from: from.clone(),
+                to: to.clone(),
+                kind: link.kind.clone(),
+            })
+        })
+        .collect();
+    (template_tasks, template_links)
+}
+
+/// Snapshot of a task list, ready to be stored as a template
+pub async fn snapshot_task_list(
+    task_repo: &TaskRepository,
+    task_list_id: &str,
+) -> Result<(Vec<TemplateTask>, Vec<TemplateLink>), io::Error> {
+    let tasks: Vec<_> = query_scope_tasks(task_repo, &[task_list_id.to_owned()])
+        .await?
+        .into_iter()
+        .map(|(task, _)| task)
+        .collect();
+    let mut links = vec![];
+    for task in &tasks {
+        links.extend(
+            task_repo
+                .query_task_outgoing_links_by_task_id(&get_str_id(&task.id))
+                .await?,
+        );
+    }
+    Ok(template_from_tasks(&tasks, &links))
+}
+
+/// Checks that keys are unique and that links and parents refer to template tasks
+pub fn validate_template(tasks: &[TemplateTask], links: &[TemplateLink]) -> Result<(), io::Error> {
+    let mut keys: Vec<&str> = vec![];
+    for task in tasks {
+        if task.key.is_empty() || keys.contains(&task.key.as_str()) {
+            return Err(invalid(format!("Task key \"{}\" is empty or used twice", task.key)));
+        }
+        keys.push(&task.key);
+    }
+    for task in tasks {
+        let mut parent = task.parent.as_deref();
+        let mut depth = 0;
+        while let Some(key) = parent {
+            if !keys.contains(&key) {
+                return Err(invalid(format!("Unknown parent \"{key}\"")));
+            }
+            depth += 1;
+            if key == task.key || depth > tasks.len() {
+                return Err(invalid(format!("Task \"{}\" is its own ancestor", task.key)));
+            }
+            parent = tasks.iter().find(|t| t.key == key).and_then(|t| t.parent.as_deref());
+        }
+    }
+    for link in links {
+        if !keys.contains(&link.from.as_str()) || !keys.contains(&link.to.as_str()) {
+            return Err(invalid(format!(
+                "Link {} -> {} refers to unknown tasks",
+                link.from, link.to
+            )));
+        }
+        if link.from == link.to {
+            return Err(invalid(format!("Task \"{}\" cannot link to itself", link.from)));
+        }
+        if link.kind != "auto" && link.kind != "dep" {
+            return Err(invalid(format!("Unknown link type \"{}\"", link.kind)));
+        }
+    }
+    Ok(())
+}
+
+/// Where a template is instantiated
+pub struct TemplateTarget<'a> {
+    pub task_list_id: &'a str,
+    /// Deadline offsets count from here
+    pub start: DateTime<Utc>,
+    /// Users default assignees may be kept for
+    pub members: &'a [DbModelId],
+}
+
+/// Creates the template tasks in the target task list, then rewires parents and
+/// links to the new task ids. Assignees outside the target members and labels the
+/// target project does not define are dropped.
+pub async fn instantiate_template(
+    task_repo: &TaskRepository,
+    project_repo: &ProjectRepository,
+    user_repo: &UserRepository,
+    notif_repo: &NotificationRepository,
+    template: &Template,
+    target: TemplateTarget<'_>,
+) -> Result<Vec<Task>, io::Error> {
+    let (tasks, links) = (&template.tasks, &template.links);
+    let TemplateTarget {
+        task_list_id,
+        start,
+        members,
+    } = target;
+    validate_template(tasks, links)?;
+    let scope = query_task_list_scope(task_repo, project_repo, user_repo, task_list_id).await?;
+    let status = scope
+        .status_pool
+        .incomplete
+        .first()
+        .map_or("0".to_owned(), |status| status.number.clone());
+    let project = query_project_of_task_list(task_repo, project_repo, task_list_id).await?;
+    let labels: Vec<_> = project
+        .iter()
+        .flat_map(|project| project.labels.iter().map(|label| label.id.clone()))
+        .collect();
+
+    let mut ids: HashMap<&str, DbModelId> = HashMap::new();
+    let mut created = vec![];
+    for template_task in tasks {
+        let assignees: Vec<_> = template_task
+            .assignees
+            .iter()
+            .filter(|assignee| members.contains(assignee))
+            .cloned()
+            .collect();
+        let task = Task {
+            description: template_task.description.clone(),
+            status: status.clone(),
+            ddl: template_task
+                .deadline_offset
+                .map(|offset| Datetime(start + Duration::minutes(offset))),
+            priority: template_task.priority.clone(),
+            labels: template_task
+                .labels
+                .iter()
+                .filter(|label| labels.contains(label))
+                .cloned()
+                .collect(),
+            estimate: template_task.estimate,
+            auto_complete: template_task.auto_complete,
+            ..Task::new(template_task.name.clone())
+        };
+        let mut task = task_repo.insert_task_for_task_list(&task, task_list_id).await?;
+        let task_id = get_str_id(&task.id);
+        for assignee in &assignees {
+            assign_task_to_user(task_repo, notif_repo, &task_id, assignee).await?;
+        }
+        task.assignees = Some(assignees);
+        ids.insert(&template_task.key, task_id);
+        created.push(task);
+    }
+
+    for template_task in tasks {
+        if let Some(parent) = &template_task.parent {
+            task_repo
+                .set_parent_of_task(&ids[template_task.key.as_str()], &ids[parent.as_str()])
+                .await?;
+        }
+    }
+    for link in links {
+        let to = &ids[link.to.as_str()];
+        task_repo
+            .insert_task_link(&ids[link.from.as_str()], to, &link.kind)
+            .await?;
+        refresh_task_status(to, task_repo, notif_repo, &[]).await?;
+    }
+    Ok(created)
+}
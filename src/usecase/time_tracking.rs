@@ -0,0 +1,121 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+};
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use surrealdb::sql::Datetime;
+
+use crate::db::{
+    model::{task::Task, time_entry::TimeEntry},
+    repository::{
+        task::TaskRepository,
+        utils::{get_str_id, DbModelId},
+    },
+};
+
+/// Starts a timer for `user_id` on the task. A timer the user still has running
+/// elsewhere is stopped first, since a user tracks one task at a time.
+pub async fn start_timer(
+    task_repo: &TaskRepository,
+    task_id: &str,
+    user_id: &str,
+) -> Result<TimeEntry, io::Error> {
+    if let Some(running) = task_repo.query_running_time_entry(user_id).await? {
+        if running.task == task_id {
+            return Ok(running);
+        }
+        stop_timer(task_repo, user_id).await?;
+    }
+    task_repo
+        .insert_time_entry(&TimeEntry::start(task_id.to_owned(), user_id.to_owned()))
+        .await
+}
+
+/// Stops the running timer of `user_id`
+pub async fn stop_timer(task_repo: &TaskRepository, user_id: &str) -> Result<TimeEntry, io::Error> {
+    let mut entry = task_repo
+        .query_running_time_entry(user_id)
+        .await?
+        .ok_or(io::Error::new(io::ErrorKind::NotFound, "No timer is running"))?;
+    let entry_id = get_str_id(&entry.id);
+    entry.id = None;
+    entry.ended_at = Some(Datetime(Utc::now()));
+    task_repo.update_time_entry(&entry_id, &entry).await
+}
+
+/// Builds a manual entry from either an end time or a duration in minutes
+pub fn manual_entry(
+    task_id: &str,
+    user_id: &str,
+    started_at: DateTime<Utc>,
+    ended_at: Option<DateTime<Utc>>,
+    minutes: Option<u32>,
+    note: String,
+) -> Result<TimeEntry, io::Error> {
+    let ended_at = match (ended_at, minutes) {
+        (Some(ended_at), None) => ended_at,
+        (None, Some(minutes)) => started_at + Duration::minutes(minutes.into()),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Give either an end time or a duration",
+            ))
+        }
+    };
+    if ended_at <= started_at {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "A time entry must end after it starts",
+        ));
+    }
+    Ok(TimeEntry {
+        id: None,
+        task: task_id.to_owned(),
+        user: user_id.to_owned(),
+        started_at: Datetime(started_at),
+        ended_at: Some(Datetime(ended_at)),
+        note,
+        manual: true,
+    })
+}
+
+/// Estimated and tracked minutes over a set of tasks
+#[derive(Debug, Default, PartialEq)]
+pub struct TimeTotals {
+    pub estimate: u32,
+    pub tracked: i64,
+    pub per_user: HashMap<DbModelId, i64>,
+}
+
+pub fn sum_time(tasks: &[&Task], entries: &[TimeEntry], now: DateTime<Utc>) -> TimeTotals {
+    let mut totals = TimeTotals {
+        estimate: tasks.iter().filter_map(|task| task.estimate).sum(),
+        ..Default::default()
+    };
+    for entry in entries {
+        let minutes = entry.minutes(now);
+        totals.tracked += minutes;
+        *totals.per_user.entry(entry.user.clone()).or_default() += minutes;
+    }
+    totals
+}
+
+/// Rolls estimates and tracked time up over the given tasks
+pub async fn query_time_totals(
+    task_repo: &TaskRepository,
+    tasks: &[&Task],
+) -> Result<TimeTotals, io::Error> {
+    let ids: Vec<_> = tasks.iter().map(|task| get_str_id(&task.id)).collect();
+    let entries = task_repo.query_time_entries_of_tasks(&ids).await?;
+    Ok(sum_time(tasks, &entries, Utc::now()))
+}
+
+/// Minutes tracked per day, keyed by the UTC day each entry started on
+pub fn minutes_per_day(entries: &[TimeEntry], now: DateTime<Utc>) -> BTreeMap<NaiveDate, i64> {
+    let mut days = BTreeMap::new();
+    for entry in entries {
+        *days.entry(entry.started_at.0.date_naive()).or_default() += entry.minutes(now);
+    }
+    days
+}
@@ -1,4 +1,4 @@
-use crate::db::model::{agenda::Event, notification::Notification, task::Task};
+use crate::db::model::{agenda::Event, comment::Comment, notification::Notification, task::Task};
 
 
 pub fn assigned_task_to_notif(task: Task) -> Notification {
@@ -7,6 +7,8 @@ pub fn assigned_task_to_notif(task: Task) -> Notification {
         title: format!("Task: {} has been assigned to you", task.name),
         content: format!("Task description: {}", task.description),
         handled: false,
+        created_at: None,
+        kind: "task_assigned".to_owned(),
     }
 }
 
@@ -17,6 +19,8 @@ pub fn deassign_task_to_notif(task: Task) -> Notification {
         title: format!("Task: {} has been deassigned from you", task.name),
         content: format!("Task description: {}", task.description),
         handled: false,
+        created_at: None,
+        kind: "task_deassigned".to_owned(),
     }
 }
 
@@ -26,6 +30,8 @@ pub fn assigned_event_to_notif(event: Event) -> Notification {
         title: format!("Event: {} has been assigned to you", event.name),
         content: format!("Event description: {}", event.description),
         handled: false,
+        created_at: None,
+        kind: "event_assigned".to_owned(),
     }
 }
 
@@ -35,5 +41,146 @@ pub fn deassign_event_to_notif(event: Event) -> Notification {
         title: format!("Event: {} has been deassigned from you", event.name),
         content: format!("Event description: {}", event.description),
         handled: false,
+        created_at: None,
+        kind: "event_deassigned".to_owned(),
     }
-}
\ No newline at end of file
+}
+pub fn commented_task_to_notif(task: Task, comment: &Comment) -> Notification {
+    Notification {
+        id: None,
+        title: format!("Task: {} has a new comment", task.name),
+        content: format!("Comment: {}", comment.content),
+        handled: false,
+        created_at: None,
+        kind: "task_commented".to_owned(),
+    }
+}
+
+pub fn mentioned_to_notif(subject: &str, name: &str, text: &str) -> Notification {
+    Notification {
+        id: None,
+        title: format!("You have been mentioned in {}: {}", subject, name),
+        content: format!("{}: {}", subject, text),
+        handled: false,
+        created_at: None,
+        kind: "mention".to_owned(),
+    }
+}
+
+/// Summary of the assignment changes a bulk operation made for one user
+pub fn bulk_assignment_to_notif(assigned: &[String], deassigned: &[String]) -> Notification {
+    let mut title = vec![];
+    let mut content = vec![];
+    if !assigned.is_empty() {
+        title.push(format!("{} tasks have been assigned to you", assigned.len()));
+        content.push(format!("Assigned: {}", assigned.join(", ")));
+    }
+    if !deassigned.is_empty() {
+        title.push(format!("{} tasks have been deassigned from you", deassigned.len()));
+        content.push(format!("Deassigned: {}", deassigned.join(", ")));
+    }
+    Notification {
+        id: None,
+        title: title.join("; "),
+        content: content.join("\n"),
+        handled: false,
+        created_at: None,
+        kind: "task_bulk_assigned".to_owned(),
+    }
+}
+
+pub fn task_reminder_to_notif(task: Task, due_in: &str) -> Notification {
+    Notification {
+        id: None,
+        title: format!("Task: {} is due in {}", task.name, due_in),
+        content: format!("Task description: {}", task.description),
+        handled: false,
+        created_at: None,
+        kind: "task_due_soon".to_owned(),
+    }
+}
+
+pub fn task_overdue_to_notif(task: Task) -> Notification {
+    Notification {
+        id: None,
+        title: format!("Task: {} is overdue", task.name),
+        content: format!("Task description: {}", task.description),
+        handled: false,
+        created_at: None,
+        kind: "task_overdue".to_owned(),
+    }
+}
+
+pub fn event_reminder_to_notif(event: Event, starts_in: &str) -> Notification {
+    Notification {
+        id: None,
+        title: format!("Event: {} starts in {}", event.name, starts_in),
+        content: format!("Event description: {}", event.description),
+        handled: false,
+        created_at: None,
+        kind: "event_starting_soon".to_owned(),
+    }
+}
+
+fn task_names(tasks: &[Task]) -> String {
+    tasks.iter().map(|task| task.name.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+pub fn blocked_task_to_notif(task: Task, reopened: &[Task]) -> Notification {
+    Notification {
+        id: None,
+        title: format!("Task: {} is blocked again", task.name),
+        content: format!("Reopened predecessors: {}", task_names(reopened)),
+        handled: false,
+        created_at: None,
+        kind: "task_blocked".to_owned(),
+    }
+}
+
+pub fn unblocked_task_to_notif(task: Task, completed: &[Task]) -> Notification {
+    Notification {
+        id: None,
+        title: format!("Task: {} is no longer blocked", task.name),
+        content: format!("Completed predecessors: {}", task_names(completed)),
+        handled: false,
+        created_at: None,
+        kind: "task_unblocked".to_owned(),
+    }
+}
+
+pub fn pr_merged_to_notif(task: Task) -> Notification {
+    Notification {
+        id: None,
+        title: format!("Task: {} was completed by a merged PR", task.name),
+        content: format!("PR #{} of {} was merged", task.pr.pull_number, task.pr.repo),
+        handled: false,
+        created_at: None,
+        kind: "pr_merged".to_owned(),
+    }
+}
+
+pub fn invitation_to_notif(inviter: &str, project: &str) -> Notification {
+    Notification {
+        id: None,
+        title: format!("{} invited you to project {}", inviter, project),
+        content: "Accept the invitation to join the project, or decline it".to_owned(),
+        handled: false,
+        created_at: None,
+        kind: "invitation_received".to_owned(),
+    }
+}
+
+pub fn invitation_answered_to_notif(invitee: &str, project: &str, accepted: bool) -> Notification {
+    let (answer, kind) = match accepted {
+        true => ("accepted", "invitation_accepted"),
+        false => ("declined", "invitation_declined"),
+    };
+    Notification {
+        id: None,
+        title: format!("{} {} your invitation to project {}", invitee, answer, project),
+        content: format!("Project: {}", project),
+        handled: false,
+        created_at: None,
+        kind: kind.to_owned(),
+    }
+}
@@ -0,0 +1,100 @@
+use std::io;
+
+use axum_login::AuthUser;
+
+use crate::db::{
+    model::{status::StatusPool, task::Task},
+    repository::{project::ProjectRepository, task::TaskRepository, user::UserRepository},
+};
+
+/// Status key of a task as used by workflow rules: the status number or `complete`
+pub fn status_key(task: &Task) -> String {
+    match task.complete {
+        true => "complete".to_owned(),
+        false => task.status.clone(),
+    }
+}
+
+fn status_name(pool: &StatusPool, key: &str) -> String {
+    if key == "complete" {
+        return pool.complete.name.clone();
+    }
+    pool.incomplete
+        .iter()
+        .find(|status| status.number == key)
+        .map_or(key.to_owned(), |status| status.name.clone())
+}
+
+/// Validates moving `task` from status `from` to `to` against the pool's workflow.
+/// `task` is the task as it is about to be saved, so required fields set in the same
+/// request count.
+pub fn check_transition(
+    pool: &StatusPool,
+    from: &str,
+    to: &str,
+    task: &Task,
+    is_admin: bool,
+) -> Result<(), io::Error> {
+    if from == to {
+        return Ok(());
+    }
+    let (from_name, to_name) = (status_name(pool, from), status_name(pool, to));
+
+    for rule in pool.workflow.iter().filter(|rule| rule.to == to) {
+        if let Some(allowed) = &rule.from {
+            if !allowed.iter().any(|status| status == from) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Cannot move a task from \"{from_name}\" to \"{to_name}\""),
+                ));
+            }
+        }
+        if rule.admin_only && !is_admin {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("Only the project admin can move tasks to \"{to_name}\""),
+            ));
+        }
+        for field in &rule.required_fields {
+            let present = match field.as_str() {
+                "pr" => task.pr_assigned,
+                "assignees" => task.assignees.as_ref().is_some_and(|a| !a.is_empty()),
+                "description" => !task.description.trim().is_empty(),
+                "deadline" => task.ddl.is_some(),
+                _ => true,
+            };
+            if !present {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Field \"{field}\" is required before entering \"{to_name}\""),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Status pool governing the task (its project's, or its owner's for personal lists)
+/// and whether `actor_id` administers it
+pub async fn query_workflow_of_task(
+    task_repo: &TaskRepository,
+    project_repo: &ProjectRepository,
+    user_repo: &UserRepository,
+    task_id: &str,
+    actor_id: &str,
+) -> Result<(StatusPool, bool), io::Error> {
+    let task_list = task_repo.query_task_list_id_by_task(task_id).await?;
+    let source = task_repo.query_task_list_source(&task_list).await?;
+    let source_id = source.id.to_string();
+    match source.tb.as_str() {
+        "project" => {
+            let project = project_repo.query_project_by_id(&source_id).await?;
+            let admin = project_repo.query_admin_by_id(&source_id).await?;
+            Ok((project.status_pool, admin.id() == actor_id))
+        }
+        _ => {
+            let user = user_repo.query_user_by_id(&source_id).await?;
+            Ok((user.status_pool, source_id == actor_id))
+        }
+    }
+}